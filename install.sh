@@ -39,6 +39,10 @@ cargo build --release
 echo -e "${CYAN}Installing swiftboot to /usr/local/bin...${NC}"
 sudo install -m 755 target/release/swiftboot /usr/local/bin/swiftboot
 
+echo -e "${CYAN}Installing the man page to /usr/local/share/man/man1...${NC}"
+target/release/swiftboot generate-man > target/release/swiftboot.1
+sudo install -Dm 644 target/release/swiftboot.1 /usr/local/share/man/man1/swiftboot.1
+
 echo -e "${GREEN}✓ Installation complete!${NC}"
 echo
 echo "You can now run swiftboot with:"
@@ -0,0 +1,5435 @@
+use crate::batch;
+use crate::config::{BootOnceMethod, BootToInitial, Config, ConfirmDestructive, RebootChoice};
+use crate::keymap::{KeyAction, Keymap};
+use crate::modal::{self, ButtonKey};
+use crate::partition::{self, PartitionResolver, PartitionStatus};
+use crate::report;
+use crate::state::{
+    self, ArmedBootNext, BootNextOutcome, DeferredOrder, SafeApply, State, TemporaryOrder,
+};
+use crate::text_input::TextInput;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::widgets::ListState;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque, hash_map::Entry};
+use std::sync::OnceLock;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use swiftboot::efi::{
+    self, BootEntry, BootStatus, CommandOutput, DeviceKind, EntryId, EntryKind, FirmwareInfo,
+    OsKind, SudoEvent, Timing, sort_by_order,
+};
+use swiftboot::efivars;
+
+pub const COUNTDOWN: Duration = Duration::from_secs(5);
+// The firmware menu timeout offered when it is zero at reboot.
+pub const REBOOT_TIMEOUT: u16 = 3;
+// Rows kept visible above and below the selection when scrolling.
+const SCROLL_MARGIN: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Focus {
+    Priority,
+    BootOnce,
+    Extra(EntryKind),
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Action {
+    None,
+    SetOrder(Vec<EntryId>),
+    BootOnce(String),
+    // Boots the entry once by putting it first in BootOrder, for firmware
+    // that ignores BootNext; see `config::BootOnceMethod`.
+    BootOnceByReorder(String),
+    SetExtraOrder(EntryKind, Vec<String>),
+    SetTimeout(u16),
+    Activate(String),
+    Deactivate(String),
+    Delete(String),
+    // Writes the order when the system next shuts down, through a systemd
+    // unit; see `efi::defer_args`.
+    DeferOrder(Vec<EntryId>),
+    CancelDeferred,
+    // `efibootmgr -N`, on the way out; see `UIState::QuitBootNext`.
+    ClearBootNext,
+    // Reads the entries and the status through sudo, where efibootmgr needs
+    // root even to read; see `efi::ReadNeedsRoot`.
+    ReadEntries,
+    // Runs each action in turn under the same password, stopping at the
+    // first failure.
+    Sequence(Vec<Action>),
+}
+
+impl Action {
+    // The individual operations, with sequences flattened.
+    pub fn steps(&self) -> Vec<&Action> {
+        match self {
+            Action::Sequence(actions) => actions.iter().flat_map(Action::steps).collect(),
+            Action::None => Vec::new(),
+            action => vec![action],
+        }
+    }
+
+    pub fn boot_once_id(&self) -> Option<&str> {
+        self.steps().into_iter().find_map(|step| match step {
+            Action::BootOnce(id) | Action::BootOnceByReorder(id) => Some(id.as_str()),
+            _ => None,
+        })
+    }
+
+    // The same action with every BootOnce done by reordering instead.
+    fn by_reorder(self) -> Action {
+        match self {
+            Action::BootOnce(id) => Action::BootOnceByReorder(id),
+            Action::Sequence(actions) => {
+                Action::Sequence(actions.into_iter().map(Action::by_reorder).collect())
+            }
+            action => action,
+        }
+    }
+
+    pub fn sets_order(&self) -> bool {
+        self.steps()
+            .iter()
+            .any(|step| matches!(step, Action::SetOrder(_)))
+    }
+
+    // Steps that write a variable, and so need room in the variable store.
+    // Deleting frees space and the deferral steps write nothing yet.
+    fn writes_variable(&self) -> bool {
+        matches!(
+            self,
+            Action::SetOrder(_)
+                | Action::BootOnce(_)
+                | Action::BootOnceByReorder(_)
+                | Action::SetExtraOrder(..)
+                | Action::SetTimeout(_)
+                | Action::Activate(_)
+                | Action::Deactivate(_)
+        )
+    }
+
+    // Deferral steps set up or stop a systemd unit, and a read through sudo
+    // is only ever needed without root, so they always run through sudo,
+    // whatever the backend.
+    fn runs_through_sudo(&self) -> bool {
+        matches!(
+            self,
+            Action::DeferOrder(_) | Action::CancelDeferred | Action::ReadEntries
+        )
+    }
+}
+
+// A write to the firmware that succeeded, recorded for the exit summary.
+pub enum Change {
+    Order {
+        // None for BootOrder, otherwise DriverOrder or SysPrepOrder.
+        kind: Option<EntryKind>,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    BootNext(String),
+    Timeout(u16),
+    Activated(String),
+    Deactivated(String),
+    // The entry's name, as it's gone afterwards.
+    Deleted(String),
+    Deferred(Vec<String>),
+    DeferralCancelled,
+    BootNextCleared,
+}
+
+// A step of the pending action with its command built, ready to run.
+struct PreparedStep {
+    args: Vec<String>,
+    step: Action,
+    // None for a read.
+    change: Option<Change>,
+    // Steps still to run once this one succeeds.
+    rest: VecDeque<Action>,
+    // Recorded before the write; see `App::run_step`.
+    safety: Option<SafeApply>,
+    // BootOrder and BootNext as the command was built against them.
+    order: Vec<String>,
+    next: Option<String>,
+}
+
+// A step whose password was wrong. The retry runs the same command again
+// rather than building it anew from `action`, unless the boot setup has
+// changed underneath in the meantime.
+struct PasswordRetry {
+    action: Action,
+    prepared: PreparedStep,
+}
+
+// The step of the pending action whose command is running in the background
+// while the Processing popup is shown.
+struct Job {
+    events: Receiver<SudoEvent>,
+    prepared: PreparedStep,
+    started: Instant,
+    // The last second-factor prompt seen on stderr, e.g. "Please touch the
+    // device."
+    prompt: Option<String>,
+    cancelled: bool,
+}
+
+// Whether a line of sudo's stderr asks the user to do something, such as
+// touching a security key, rather than reporting an error.
+// Checked for every line sudo prints, so compiled once.
+fn is_auth_prompt(line: &str) -> bool {
+    static PROMPT: OnceLock<Regex> = OnceLock::new();
+    PROMPT
+        .get_or_init(|| {
+            Regex::new(r"(?i)\b(touch|tap|swipe|insert|fingerprint|pin|security key|token)\b")
+                .unwrap()
+        })
+        .is_match(line)
+}
+
+// Whether the partition an entry boots from is attached.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DeviceState {
+    Present,
+    // A removable device (USB stick, disc) that isn't plugged in right now.
+    Unplugged,
+    // A fixed disk partition that no longer exists: a dangling entry.
+    Missing,
+}
+
+// What an entry is to the firmware right now, shown in the list gutter.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Markers {
+    // BootCurrent: this boot started it.
+    pub current: bool,
+    // First in BootOrder.
+    pub default: bool,
+    // BootNext: the next boot starts it once.
+    pub next: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum InactiveChoice {
+    Activate,
+    Proceed,
+    Cancel,
+}
+
+// What the firmware update warning goes on to once confirmed.
+#[derive(Clone, PartialEq)]
+pub enum AfterFirmwareUpdate {
+    // Writing the pending action, which arms BootNext.
+    Writes,
+    Countdown(ActionSummary),
+    RebootNow,
+}
+
+// Where the coming reboot lands, for the reboot prompts and the countdown.
+#[derive(Clone, PartialEq)]
+pub struct ActionSummary {
+    pub target: String,
+    // BootNext (or a temporary reorder) rather than a new default order.
+    pub boot_once: bool,
+    // False for a reboot with nothing written first; see `reboot_target`.
+    pub changed: bool,
+}
+
+impl ActionSummary {
+    // What kind of boot the target is, for the reboot prompts.
+    pub fn kind(&self) -> &'static str {
+        match (self.changed, self.boot_once) {
+            (true, true) => "boot once; the default order is unchanged",
+            (true, false) => "the new default",
+            (false, true) => "BootNext, armed earlier",
+            (false, false) => "the current default",
+        }
+    }
+}
+
+pub enum UIState {
+    Main,
+    AskPassword,
+    Processing,
+    PasswordError,
+    ConfirmBootOnce,
+    // The entry the pending action would boot is inactive.
+    ConfirmInactive(String),
+    // The pending order puts Windows first with a Linux entry below it; see
+    // `windows_first_warning`. Informational only.
+    WindowsFirst {
+        windows: String,
+        linux: String,
+    },
+    // fwupd has a firmware update staged, which runs on the next reboot
+    // before BootNext; see `App::firmware_update`.
+    FirmwareUpdate(AfterFirmwareUpdate),
+    // `boot_once_method = "ask"`: BootNext or a temporary reorder.
+    ChooseBootOnceMethod,
+    // A temporary reorder has had its boot; offer to put the order back.
+    RestoreOrder(TemporaryOrder),
+    // The order can't be deferred to shutdown, for the reason given; offer
+    // to apply it now instead.
+    DeferUnavailable(String),
+    // The change deferred to shutdown, with the option to cancel it.
+    Deferred,
+    ConfirmReboot(ActionSummary),
+    BootNextArmed(ActionSummary),
+    // `paused_at` freezes the countdown; resuming pushes the deadline back by
+    // however long it was paused.
+    CountdownReboot {
+        target: ActionSummary,
+        deadline: Instant,
+        total: Duration,
+        paused_at: Option<Instant>,
+    },
+    QuitConfirm,
+    // Quitting while the BootNext armed in this session is still set: keep
+    // it, clear it and quit, or stay. Holds the target's name.
+    QuitBootNext(String),
+    // Deleting the entry with this ID; see `App::delete_phrase`.
+    ConfirmDelete(String),
+    // Another instance holds the lock; offer read-only mode or exit.
+    InstanceLocked(Option<u32>),
+    // First-run introduction, see `State::welcomed`.
+    Welcome,
+    ProfilePicker,
+    Details(BootEntry),
+    // The full text of `command_warnings`.
+    Warnings,
+    Help,
+    ErrorMessage(String),
+    // A write failed for lack of space in the variable store; shown with
+    // `efi::NVRAM_FULL_ADVICE`.
+    NvramFull(String),
+    // The firmware accepted a BootOrder write but kept a different order;
+    // `then` is the state the write would otherwise have led to.
+    OrderModified {
+        requested: Vec<String>,
+        actual: Vec<String>,
+        then: Box<UIState>,
+    },
+}
+
+// A panel's cursor. It is anchored to the entry ID so it stays on the same
+// entry when the list is reordered or refreshed; `index` is only the
+// fallback used when that entry disappears.
+struct Selection {
+    id: Option<String>,
+    index: usize,
+}
+
+// A Driver#### or SysPrep#### list shown in advanced mode. These are
+// reordered like the boot order but have no boot-once counterpart.
+pub struct EntryList {
+    pub kind: EntryKind,
+    // The draft: reordered in place until it is applied.
+    pub entries: Vec<BootEntry>,
+    // The order as last read from or written to the firmware.
+    pub live_order: Vec<String>,
+}
+
+impl EntryList {
+    pub fn new(kind: EntryKind, entries: Vec<BootEntry>) -> EntryList {
+        let live_order = entries.iter().map(|e| e.id.clone()).collect();
+        EntryList {
+            kind,
+            entries,
+            live_order,
+        }
+    }
+
+    pub fn draft_order(&self) -> Vec<String> {
+        self.entries.iter().map(|e| e.id.clone()).collect()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.draft_order() != self.live_order
+    }
+
+    pub fn moved_offsets(&self) -> Vec<isize> {
+        position_shifts(&self.live_order, &self.draft_order())
+    }
+}
+
+pub struct App {
+    // Every Boot#### entry as last read, placeholders for BootOrder IDs
+    // without one included. Reordering never touches these.
+    pub entries: Vec<BootEntry>,
+    // The Priority panel's draft: every entry's ID in the order an apply
+    // would write, hidden ones included. The panel's rows (and the Boot To
+    // panel's) are `entries` in this order; reordering only ever changes
+    // this.
+    draft: Vec<String>,
+    // Every entry in the order last read from or written to the firmware,
+    // with the ones BootOrder leaves out after the others. Refreshed by
+    // every apply, so the draft is never compared against a stale copy.
+    // `is_dirty`, the moved markers, reset and the apply review all go by
+    // the draft against this.
+    pub live_order: Vec<String>,
+    // False when the firmware has no BootOrder variable; applying from the
+    // Priority panel then creates one from the displayed order.
+    pub has_boot_order: bool,
+    // The BootOrder as last read from or written to the firmware. Entries
+    // outside it can still be picked for a one-time boot.
+    pub boot_order: Vec<String>,
+    pub current_boot_id: String,
+    pub boot_current: Option<String>,
+    pub boot_next: Option<String>,
+    // The BootNext armed in this session, for the reminder on quitting.
+    session_boot_next: Option<String>,
+    // Seconds the firmware boot menu waits; None when BootTimeout isn't set.
+    pub timeout: Option<u16>,
+    // Where to reboot once a timeout set from the reboot prompt is written.
+    reboot_after_timeout: Option<ActionSummary>,
+    // What the boot before this one started, for "boot previous".
+    pub previous_boot: Option<String>,
+    pub extra_lists: Vec<EntryList>,
+    // Where each referenced partition GUID lives, see `resolve_partitions`.
+    pub partitions: HashMap<String, PartitionStatus>,
+    pub focus: Focus,
+    selections: HashMap<Focus, Selection>,
+    // Per-panel scroll offsets, kept across focus changes and redraws.
+    list_states: HashMap<Focus, ListState>,
+    pub state: UIState,
+    pub password: TextInput,
+    pub show_password: bool,
+    // Enter was pressed on an empty password field; cleared by the next key.
+    pub password_empty: bool,
+    // How long the last read of the entries took, when over `slow_read_ms`.
+    pub slow_read: Option<Duration>,
+    pub pending_action: Action,
+    pub boot_once_yes: bool,
+    pub inactive_choice: InactiveChoice,
+    pub reboot_yes: bool,
+    pub reboot_choice: RebootChoice,
+    pub quit_yes: bool,
+    // Highlighted button of `UIState::QuitBootNext`.
+    pub quit_boot_next_choice: usize,
+    // Highlighted button of `UIState::ConfirmDelete`: Delete (0) or Cancel,
+    // and what has been typed into its name field.
+    pub delete_choice: usize,
+    pub delete_typed: TextInput,
+    pub read_only_yes: bool,
+    // Highlighted button of the Windows-first warning: "Don't show again"
+    // (true) or Continue.
+    pub windows_first_dismiss: bool,
+    // Highlighted button of the error popup: Retry (true) or Back.
+    pub error_retry: bool,
+    // Highlighted button of the boot once method question: reorder (true)
+    // or BootNext.
+    pub boot_once_reorder: bool,
+    // Highlighted button of the restore question: Restore (true) or Keep.
+    pub restore_yes: bool,
+    // Highlighted button when deferring is unavailable: Apply now (true) or
+    // Cancel.
+    pub apply_now_yes: bool,
+    // Highlighted button of the deferred change popup: Keep (true) or
+    // Cancel the change.
+    pub keep_deferred: bool,
+    // Highlighted row of the profile picker.
+    pub profile_index: usize,
+    // Whether firmware-internal entries (see
+    // `BootEntry::is_firmware_internal`) are left out of the panels. They
+    // keep their places in the draft, so every order written keeps them
+    // exactly where they were.
+    hiding_firmware_entries: bool,
+    // "Pin the rest": applying only permutes the entries moved explicitly
+    // in the Priority panel (`moved`) among their own positions; see
+    // `pin_untouched`.
+    pub pin_rest: bool,
+    moved: Vec<String>,
+    // What has been typed for type-ahead and when, reset after a pause.
+    type_ahead: Option<(String, Instant)>,
+    pub moving: Option<Moving>,
+    // Working orders to go back to with Ctrl+Z / Ctrl+Y, newest last.
+    undo: Vec<OrderSnapshot>,
+    redo: Vec<OrderSnapshot>,
+    // Why writes are disabled, when they are: navigation only.
+    pub read_only: Option<&'static str>,
+    // `--backend efivarfs`: writes go straight to efivarfs as root, so
+    // there is no password to ask for.
+    pub efivarfs_writes: bool,
+    // The entries were read from efivarfs at startup, so re-reads go there
+    // too.
+    pub reads_efivarfs: bool,
+    // efibootmgr needs root even to read here, so reads go through sudo
+    // like writes; see `read_with_sudo`.
+    pub reads_need_root: bool,
+    // Every write made this session, in order, for the exit summary.
+    pub changes: Vec<Change>,
+    // Safety net to record when the pending order is written; see
+    // `order_action`.
+    pending_safety: Option<SafeApply>,
+    // Requested and actual BootOrder of the last write, when they differ.
+    modified_order: Option<(Vec<String>, Vec<String>)>,
+    // BootOrder as of the last successful write this session. A refresh
+    // that finds a different order sets `order_drift` (applied, live) for
+    // the banner; dismissing it remembers the live order so the banner
+    // stays away until the order changes again.
+    last_applied: Option<Vec<String>>,
+    pub order_drift: Option<(Vec<String>, Vec<String>)>,
+    // A profile whose `suggest` window is now and whose order isn't the live
+    // one; shown as a banner until applied or dismissed.
+    pub suggested_profile: Option<String>,
+    // What efibootmgr printed while succeeding, e.g. "Could not parse device
+    // path"; shown as a banner until dismissed.
+    pub command_warnings: Option<String>,
+    // Set by the edit-order key: the text for the main loop to open in
+    // $EDITOR, with the TUI suspended. See `finish_order_edit`.
+    pub order_to_edit: Option<String>,
+    dismissed_drift: Option<Vec<String>>,
+    job: Option<Job>,
+    password_retry: Option<PasswordRetry>,
+    // Shown under the title and in the details popup.
+    pub firmware: FirmwareInfo,
+    // The device fwupd will update on the next reboot, if any; see
+    // `fwupd::pending_update`. Warned about once per session.
+    pub firmware_update: Option<String>,
+    firmware_update_seen: bool,
+    // Highlighted button of the firmware update warning: Continue (true)
+    // or Cancel.
+    pub firmware_update_yes: bool,
+    pub show_ids: bool,
+    // The Priority panel shows section headers per disk; see `group_headers`.
+    pub grouped: bool,
+    pub toast: Option<(String, Instant)>,
+    pub should_quit: bool,
+    pub config: Config,
+    pub keymap: Keymap,
+    saved_state: State,
+}
+
+// A panel's working order before a reorder, and the entry it moved, so
+// undo can put the selection back on it.
+struct OrderSnapshot {
+    panel: Focus,
+    order: Vec<String>,
+    moved: Option<String>,
+}
+
+// An entry picked up with `KeyAction::MoveMode`. It travels through its
+// panel as a preview of where it will land: dropping it records a single
+// undo step, cancelling puts the panel back as it was.
+pub struct Moving {
+    pub panel: Focus,
+    pub id: String,
+    // The panel's order when the entry was picked up.
+    original: Vec<String>,
+}
+
+// Pause after which type-ahead starts over.
+const TYPE_AHEAD_RESET: Duration = Duration::from_millis(700);
+
+// How many reorders undo goes back.
+const UNDO_LIMIT: usize = 50;
+
+// Placeholders for BootOrder IDs without an entry (see
+// `BootEntry::placeholder`) stay in the order, but nothing says what they
+// would start.
+const UNKNOWN_BOOT_ONCE: &str = "Unknown entries can't be booted once from here";
+
+// Characters of the name `confirm_destructive = "type-name"` asks for.
+pub const TYPED_NAME_CHARS: usize = 4;
+
+// Reasons for `App::read_only`.
+pub const READ_ONLY_LOCKED: &str = "another swiftboot instance is running";
+pub const READ_ONLY_REQUESTED: &str = "started with --read-only";
+pub const READ_ONLY_NO_EFIBOOTMGR: &str = "efibootmgr isn't available";
+
+fn is_failure(state: &UIState) -> bool {
+    matches!(
+        state,
+        UIState::PasswordError | UIState::ErrorMessage(_) | UIState::NvramFull(_)
+    )
+}
+
+// A warning when `action` writes a variable and the variable store is
+// nearly full, for before it is applied.
+pub fn low_nvram_warning(action: &Action) -> Option<String> {
+    if !action.steps().iter().any(|step| step.writes_variable()) {
+        return None;
+    }
+    let space = efivars::nvram_space().filter(|space| space.is_low())?;
+    Some(format!(
+        "NVRAM is nearly full ({:.1} KiB of {} KiB free); writing may fail",
+        space.free as f64 / 1024.0,
+        space.total / 1024
+    ))
+}
+
+// The scroll offset closest to `offset` that shows `selected` with
+// SCROLL_MARGIN rows around it, without scrolling past the end of the list.
+pub fn ensure_visible(offset: usize, selected: Option<usize>, len: usize, height: usize) -> usize {
+    if len <= height {
+        return 0;
+    }
+    let max_offset = len - height;
+    let Some(selected) = selected else {
+        return offset.min(max_offset);
+    };
+    let margin = SCROLL_MARGIN.min(height.saturating_sub(1) / 2);
+    let lowest = (selected + margin + 1).saturating_sub(height);
+    let highest = selected.saturating_sub(margin).max(lowest);
+    offset.clamp(lowest, highest).min(max_offset)
+}
+
+// Index of the entry the Boot To panel starts on. With a single entry, or
+// when every entry is the running one, that is simply the first.
+fn initial_boot_to(
+    entries: &[BootEntry],
+    setting: BootToInitial,
+    current: Option<&str>,
+    last_used: Option<&str>,
+) -> usize {
+    let first_non_current = || {
+        entries
+            .iter()
+            .position(|e| Some(e.id.as_str()) != current && !e.unknown)
+            .unwrap_or(0)
+    };
+    match setting {
+        BootToInitial::First => 0,
+        BootToInitial::FirstNonCurrent => first_non_current(),
+        BootToInitial::LastUsed => last_used
+            .and_then(|id| entries.iter().position(|e| e.id == id))
+            .unwrap_or_else(first_non_current),
+    }
+}
+
+// The IDs of `entries` as `sort_by_order` sorts them by `order`. Like
+// every other order kept here they are only checked (see
+// `set_order_action`) when written.
+fn draft_from(entries: &[BootEntry], order: &[String]) -> Vec<String> {
+    let mut sorted = entries.to_vec();
+    sort_by_order(&mut sorted, order);
+    sorted.into_iter().map(|e| e.id).collect()
+}
+
+// The order `working` asks for, restricted to the `moved` entries: every
+// other entry keeps its exact position in `original`, and the moved ones
+// fill the positions they held there, in the order `working` has them.
+// E.g. original A B C D E, working D B C A E with A and D moved: D B C A E;
+// working A D B C E with D moved alone: A B C D E, as D's only slot is its
+// own.
+pub fn pin_untouched(original: &[String], working: &[String], moved: &[String]) -> Vec<String> {
+    let mut moved_in_order = working.iter().filter(|id| moved.contains(id));
+    original
+        .iter()
+        .map(|id| {
+            if moved.contains(id) {
+                moved_in_order.next().unwrap_or(id).clone()
+            } else {
+                id.clone()
+            }
+        })
+        .collect()
+}
+
+// Row of entry `index` in a list with `headers` (see `App::group_headers`)
+// interleaved: the entry's index plus the headers at or above it.
+pub fn display_row(headers: &[Option<String>], index: usize) -> usize {
+    index + headers[..=index].iter().flatten().count()
+}
+
+// How far each entry has moved relative to `original`: positive values mean
+// the entry was moved up (towards higher priority), zero means untouched.
+pub fn position_shifts(original: &[String], current: &[String]) -> Vec<isize> {
+    current
+        .iter()
+        .enumerate()
+        .map(
+            |(i, id)| match original.iter().position(|other| other == id) {
+                Some(j) => j as isize - i as isize,
+                None => 0,
+            },
+        )
+        .collect()
+}
+
+impl App {
+    pub fn new(
+        config: Config,
+        keymap: Keymap,
+        mut saved_state: State,
+        mut entries: Vec<BootEntry>,
+        status: BootStatus,
+        extra_lists: Vec<EntryList>,
+    ) -> App {
+        let order = status.order;
+        let current_boot_id = order.first().cloned().unwrap_or_default();
+
+        efi::add_placeholders(&mut entries, &order);
+        sort_by_order(&mut entries, &order);
+
+        let draft = draft_from(&entries, &order);
+        let live_order = draft.clone();
+        // Ignored when that entry has since been deleted.
+        let previous_boot = saved_state
+            .record_boot(status.current.as_deref())
+            .filter(|id| entries.iter().any(|e| &e.id == id));
+        let show_ids = saved_state.show_ids.unwrap_or(config.show_ids);
+        let boot_to = initial_boot_to(
+            &entries,
+            config.boot_to_initial,
+            status.current.as_deref(),
+            saved_state.last_boot_once.as_deref(),
+        );
+        let state = if saved_state.welcomed {
+            UIState::Main
+        } else {
+            UIState::Welcome
+        };
+
+        let mut app = App {
+            entries,
+            draft,
+            live_order,
+            has_boot_order: !order.is_empty(),
+            boot_order: order,
+            current_boot_id,
+            boot_current: status.current.clone(),
+            boot_next: status.next,
+            session_boot_next: None,
+            timeout: status.timeout,
+            reboot_after_timeout: None,
+            previous_boot,
+            extra_lists,
+            partitions: HashMap::new(),
+            focus: Focus::Priority,
+            selections: HashMap::new(),
+            list_states: HashMap::new(),
+            state,
+            password: TextInput::default(),
+            show_password: false,
+            password_empty: false,
+            slow_read: None,
+            pending_action: Action::None,
+            boot_once_yes: true,
+            inactive_choice: InactiveChoice::Activate,
+            reboot_yes: true,
+            reboot_choice: config.after_boot_once,
+            quit_yes: false,
+            quit_boot_next_choice: 0,
+            delete_choice: 1,
+            delete_typed: TextInput::default(),
+            read_only_yes: true,
+            windows_first_dismiss: false,
+            error_retry: true,
+            boot_once_reorder: false,
+            restore_yes: true,
+            apply_now_yes: true,
+            keep_deferred: true,
+            profile_index: 0,
+            hiding_firmware_entries: false,
+            pin_rest: false,
+            moved: Vec::new(),
+            type_ahead: None,
+            moving: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_applied: None,
+            order_drift: None,
+            suggested_profile: None,
+            command_warnings: None,
+            order_to_edit: None,
+            dismissed_drift: None,
+            read_only: None,
+            efivarfs_writes: false,
+            reads_efivarfs: false,
+            reads_need_root: false,
+            changes: Vec::new(),
+            pending_safety: None,
+            modified_order: None,
+            job: None,
+            password_retry: None,
+            firmware: FirmwareInfo::default(),
+            firmware_update: None,
+            firmware_update_seen: false,
+            firmware_update_yes: true,
+            show_ids,
+            grouped: saved_state.grouped,
+            toast: None,
+            should_quit: false,
+            config,
+            keymap,
+            saved_state,
+        };
+        app.select_index(Focus::BootOnce, boot_to);
+        app
+    }
+
+    pub fn display_name<'a>(&'a self, entry: &'a BootEntry) -> &'a str {
+        self.config.alias_for(&entry.id).unwrap_or(&entry.name)
+    }
+
+    // The draft order, hidden entries included.
+    pub fn draft_order(&self) -> Vec<String> {
+        self.draft
+            .iter()
+            .filter(|id| self.entry(id).is_some())
+            .cloned()
+            .collect()
+    }
+
+    // Makes `order` the draft: its entries first, then the ones it leaves
+    // out, sorted by ID as `sort_by_order` does.
+    pub fn set_draft(&mut self, order: &[String]) {
+        self.draft = draft_from(&self.entries, order);
+    }
+
+    // Every boot entry, shown or hidden.
+    pub fn all_entries(&self) -> impl Iterator<Item = &BootEntry> {
+        self.entries.iter()
+    }
+
+    fn entry(&self, id: &str) -> Option<&BootEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    fn is_hidden(&self, entry: &BootEntry) -> bool {
+        self.hiding_firmware_entries && entry.is_firmware_internal()
+    }
+
+    // The Priority panel's rows: the draft without the hidden entries.
+    pub fn rows(&self) -> Vec<&BootEntry> {
+        self.draft
+            .iter()
+            .filter_map(|id| self.entry(id))
+            .filter(|e| !self.is_hidden(e))
+            .collect()
+    }
+
+    // The entries left out of the panels, in draft order.
+    pub fn hidden_entries(&self) -> Vec<&BootEntry> {
+        self.draft
+            .iter()
+            .filter_map(|id| self.entry(id))
+            .filter(|e| self.is_hidden(e))
+            .collect()
+    }
+
+    // Takes the firmware-internal entries out of the panels, or puts them
+    // back. Undo history is per panel layout, so it goes either way.
+    pub fn set_firmware_entries_hidden(&mut self, hide: bool) {
+        self.hiding_firmware_entries = hide;
+        self.clear_history();
+    }
+
+    // Looks up the partition of every entry whose device path names one.
+    pub fn resolve_partitions(&mut self, resolver: &dyn PartitionResolver) {
+        let entries = self
+            .entries
+            .iter()
+            .chain(self.extra_lists.iter().flat_map(|l| &l.entries));
+        for guid in entries.filter_map(|e| partition::partition_guid(&e.device_path)) {
+            if let Entry::Vacant(slot) = self.partitions.entry(guid) {
+                let status = resolver.resolve(slot.key());
+                slot.insert(status);
+            }
+        }
+        self.demote_missing_entries();
+    }
+
+    pub fn partition_status(&self, entry: &BootEntry) -> Option<&PartitionStatus> {
+        self.partitions
+            .get(&partition::partition_guid(&entry.device_path)?)
+    }
+
+    pub fn markers(&self, entry: &BootEntry) -> Markers {
+        Markers {
+            current: self.boot_current.as_deref() == Some(entry.id.as_str()),
+            default: self.boot_order.first() == Some(&entry.id),
+            next: self.boot_next.as_deref() == Some(entry.id.as_str()),
+        }
+    }
+
+    pub fn device_state(&self, entry: &BootEntry) -> DeviceState {
+        match self.partition_status(entry) {
+            Some(PartitionStatus::Missing) if entry.is_removable() => DeviceState::Unplugged,
+            Some(PartitionStatus::Missing) => DeviceState::Missing,
+            _ => DeviceState::Present,
+        }
+    }
+
+    // False for entries that exist but aren't listed in the BootOrder.
+    pub fn in_boot_order(&self, entry: &BootEntry) -> bool {
+        !self.has_boot_order || self.boot_order.contains(&entry.id)
+    }
+
+    // The section an entry is listed under in the grouped view.
+    pub fn group_of(&self, entry: &BootEntry) -> String {
+        if entry.is_removable() {
+            return "USB / removable".to_string();
+        }
+        if entry.device_kind() == DeviceKind::Network {
+            return "Network".to_string();
+        }
+        match self.partition_status(entry) {
+            Some(PartitionStatus::Found(info)) => partition::disk_name(&info.device),
+            _ => "Other".to_string(),
+        }
+    }
+
+    // For each entry of the panel, the section header shown above it in the
+    // grouped view. Only the Priority panel is grouped, and a header starts
+    // every run of entries on the same disk, so the rows still follow the
+    // boot order exactly and moving an entry across sections is a plain swap.
+    pub fn group_headers(&self, panel: Focus) -> Vec<Option<String>> {
+        let entries = self.panel_entries(panel);
+        if !self.grouped || panel != Focus::Priority {
+            return vec![None; entries.len()];
+        }
+        let mut last = None;
+        entries
+            .iter()
+            .map(|e| {
+                let group = self.group_of(e);
+                if last.as_ref() == Some(&group) {
+                    return None;
+                }
+                last = Some(group.clone());
+                Some(group)
+            })
+            .collect()
+    }
+
+    // Without a BootOrder the displayed order is only a suggestion, so
+    // entries whose device is gone are moved out of the way and left out
+    // when it is applied.
+    fn demote_missing_entries(&mut self) {
+        if self.has_boot_order {
+            return;
+        }
+        let mut draft = std::mem::take(&mut self.draft);
+        draft.sort_by_key(|id| {
+            self.entry(id)
+                .is_some_and(|e| self.device_state(e) == DeviceState::Missing)
+        });
+        self.draft = draft;
+        self.live_order = self.draft_order();
+    }
+
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.config.profiles.keys().map(String::as_str).collect()
+    }
+
+    // The full boot order a profile asks for: its entries first, then the
+    // ones it doesn't mention in their current relative order. IDs that
+    // don't exist on this system are an error.
+    pub fn profile_order(&self, name: &str) -> Result<Vec<String>, String> {
+        let profile = self
+            .config
+            .profiles
+            .get(name)
+            .ok_or_else(|| format!("No profile named \"{}\"", name))?;
+
+        let find = |id: &str| self.all_entries().find(|e| e.id.eq_ignore_ascii_case(id));
+        let unknown: Vec<&str> = profile
+            .order
+            .iter()
+            .map(String::as_str)
+            .filter(|id| find(id).is_none())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(format!(
+                "Profile \"{}\" refers to unknown entries: {}",
+                name,
+                unknown.join(", ")
+            ));
+        }
+
+        let mut order: Vec<String> = Vec::new();
+        for e in profile.order.iter().filter_map(|id| find(id)) {
+            if !order.contains(&e.id) {
+                order.push(e.id.clone());
+            }
+        }
+        let rest: Vec<String> = self
+            .draft_order()
+            .into_iter()
+            .filter(|id| !order.contains(id))
+            .collect();
+        order.extend(rest);
+        Ok(order)
+    }
+
+    // `--focus` and `--select`: where the TUI starts. A selector that
+    // matches nothing is reported and otherwise ignored.
+    pub fn start_at(&mut self, focus: Option<Focus>, select: Option<&str>) {
+        if let Some(focus) = focus {
+            self.focus = focus;
+        }
+        let Some(reference) = select else {
+            return;
+        };
+        match batch::find_entry(self, reference, &[]) {
+            Ok(id) => {
+                if let Some(i) = self.rows().iter().position(|e| e.id == id) {
+                    self.select_index(self.focus, i);
+                }
+            }
+            Err(e) => self.show_toast(format!("--select didn't match: {}", e)),
+        }
+    }
+
+    // The Priority panel as an editable file: one `ID  Name` line per entry.
+    // Hidden entries aren't listed and keep their places.
+    pub fn order_text(&self) -> String {
+        let mut text = String::from(
+            "# Boot order, first line first. Move lines to reorder; only the ID at\n\
+             # the start of each line counts. Entries left out keep their current\n\
+             # order after the listed ones. Save and quit to use it, or empty the\n\
+             # file to change nothing. Nothing is written until you apply.\n",
+        );
+        for e in self.rows() {
+            text.push_str(&format!("{}  {}\n", e.id, self.display_name(e)));
+        }
+        text
+    }
+
+    // Reads back the file from `order_text` into the draft. Unknown and
+    // repeated IDs are errors and leave the draft alone.
+    pub fn finish_order_edit(&mut self, edited: Result<String, String>) {
+        let result = edited.and_then(|text| self.parse_order_text(&text));
+        match result {
+            Ok(None) => self.show_toast("Order file was empty; nothing changed"),
+            Ok(Some((order, left_out))) => {
+                let visible = self.row_ids(Focus::Priority);
+                if order == visible {
+                    self.show_toast("Order unchanged");
+                    return;
+                }
+                self.record_reorder(Focus::Priority, None);
+                for (i, id) in order.iter().enumerate() {
+                    if visible.get(i) != Some(id) {
+                        self.mark_moved(id.clone());
+                    }
+                }
+                self.reorder_entries(&order);
+                self.focus = Focus::Priority;
+                let message = match left_out {
+                    0 => "Order edited; press Enter to review and apply".to_string(),
+                    n => format!(
+                        "Order edited; {} unlisted {} kept at the end. Press Enter to review and apply",
+                        n,
+                        if n == 1 { "entry was" } else { "entries were" }
+                    ),
+                };
+                self.show_toast(message);
+            }
+            Err(e) => self.show_invalid_order(format!("The edited order wasn't used: {}", e)),
+        }
+    }
+
+    // The full visible order from the edited text and how many entries it
+    // left out, or None when the text lists nothing.
+    fn parse_order_text(&self, text: &str) -> Result<Option<(Vec<String>, usize)>, String> {
+        let mut order: Vec<String> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let word = line.split_whitespace().next().unwrap_or_default();
+            let raw = word
+                .get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("boot"))
+                .map_or(word, |_| &word[4..]);
+            let id = EntryId::parse(raw)
+                .ok_or_else(|| format!("line {}: \"{}\" is not a Boot#### ID", i + 1, word))?;
+            let id = id.as_str().to_string();
+            if !self.rows().iter().any(|e| e.id == id) {
+                return Err(format!("line {}: there is no Boot{} entry", i + 1, id));
+            }
+            if order.contains(&id) {
+                return Err(format!("line {}: Boot{} is listed twice", i + 1, id));
+            }
+            order.push(id);
+        }
+        if order.is_empty() {
+            return Ok(None);
+        }
+        let rest: Vec<String> = self
+            .row_ids(Focus::Priority)
+            .into_iter()
+            .filter(|id| !order.contains(id))
+            .collect();
+        let left_out = rest.len();
+        order.extend(rest);
+        Ok(Some((order, left_out)))
+    }
+
+    // Remembers the panel's order before a reorder of `moved`; a new change
+    // drops whatever could still be redone.
+    fn record_reorder(&mut self, panel: Focus, moved: Option<String>) {
+        let order = self.row_ids(panel);
+        self.record_order(panel, order, moved);
+    }
+
+    // `record_reorder` for a change already made, given the order before it.
+    fn record_order(&mut self, panel: Focus, order: Vec<String>, moved: Option<String>) {
+        if panel == Focus::Priority
+            && let Some(id) = &moved
+        {
+            self.mark_moved(id.clone());
+        }
+        self.undo.push(OrderSnapshot {
+            panel,
+            order,
+            moved,
+        });
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn mark_moved(&mut self, id: String) {
+        if !self.moved.contains(&id) {
+            self.moved.push(id);
+        }
+    }
+
+    // The entry a swap displaces changes places too, so "pin the rest"
+    // applies the swap rather than keeping it in its old slot.
+    fn mark_swapped(&mut self, panel: Focus, index: usize) {
+        if panel == Focus::Priority
+            && let Some(id) = self.row_ids(panel).get(index)
+        {
+            self.mark_moved(id.clone());
+        }
+    }
+
+    // Steps back (or forward again) through the recorded reorders. The
+    // snapshot's panel gets focus, with the moved entry selected.
+    fn step_history(&mut self, undo: bool) -> bool {
+        let popped = if undo {
+            self.undo.pop()
+        } else {
+            self.redo.pop()
+        };
+        let Some(snapshot) = popped else {
+            return false;
+        };
+        let panel = snapshot.panel;
+        let current = OrderSnapshot {
+            panel,
+            order: self.row_ids(panel),
+            moved: snapshot.moved.clone(),
+        };
+        if undo {
+            self.redo.push(current);
+        } else {
+            self.undo.push(current);
+        }
+        self.set_panel_order(panel, &snapshot.order);
+        self.focus = panel;
+        if let Some(i) = snapshot
+            .moved
+            .and_then(|id| self.panel_entries(panel).iter().position(|e| e.id == id))
+        {
+            self.select_index(panel, i);
+        }
+        true
+    }
+
+    // An applied order is the new baseline; undo doesn't reach past it.
+    fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.moved.clear();
+    }
+
+    // Rearranges the Priority panel's rows to `order`. Rows it leaves out
+    // go after the others, and hidden entries keep their places.
+    pub fn reorder_entries(&mut self, order: &[String]) {
+        self.set_panel_order(Focus::Priority, order);
+    }
+
+    // Puts the draft back to the order in effect (for an Extra panel, that
+    // panel's). Undo brings the draft back.
+    pub fn reset_order(&mut self, panel: Focus) -> bool {
+        let panel = match panel {
+            Focus::BootOnce => Focus::Priority,
+            panel => panel,
+        };
+        let live = match panel {
+            Focus::Extra(kind) => match self.extra_list(kind) {
+                Some(list) if list.is_dirty() => list.live_order.clone(),
+                _ => return false,
+            },
+            _ if self.draft_order() != self.live_order => self.live_order.clone(),
+            _ => return false,
+        };
+        self.record_reorder(panel, None);
+        match panel {
+            Focus::Extra(_) => self.set_panel_order(panel, &live),
+            _ => self.set_draft(&live),
+        }
+        self.focus = panel;
+        true
+    }
+
+    fn apply_profile(&mut self, name: &str) {
+        match self.profile_order(name) {
+            Ok(order) => {
+                self.suggested_profile = None;
+                self.reorder_entries(&order);
+                self.focus = Focus::Priority;
+                // Hidden entries stay where they are, whatever the profile says.
+                self.set_order_and_review(&self.draft_order());
+            }
+            Err(e) => {
+                self.state = UIState::Main;
+                self.show_toast(e);
+            }
+        }
+    }
+
+    // Checks an order before anything is written: every ID must be four hex
+    // digits, appear once, and name a Boot#### entry that exists.
+    pub fn set_order_action(&self, order: &[String]) -> Result<Action, String> {
+        let mut ids: Vec<EntryId> = Vec::new();
+        let mut problems = Vec::new();
+        for raw in order {
+            match EntryId::parse(raw) {
+                None => problems.push(format!("malformed '{}'", raw)),
+                Some(id) if ids.contains(&id) => {
+                    problems.push(format!("duplicate {}", id.as_str()))
+                }
+                Some(id) if !self.all_entries().any(|e| e.id == id.as_str()) => {
+                    problems.push(format!("unknown {}", id.as_str()))
+                }
+                Some(id) => ids.push(id),
+            }
+        }
+        if problems.is_empty() {
+            Ok(Action::SetOrder(ids))
+        } else {
+            Err(format!("Invalid boot order: {}", problems.join(", ")))
+        }
+    }
+
+    // `set_order_action`, plus the safety net when `safe_apply` is on: the
+    // previous default is armed as BootNext and the previous order is kept
+    // in the state file until `swiftboot confirm`.
+    pub fn order_action(&mut self, order: &[String]) -> Result<Action, String> {
+        let action = self.set_order_action(order)?;
+        self.pending_safety = None;
+        let old_order = self.live_order.clone();
+        let reverting = self
+            .saved_state
+            .safe_apply
+            .as_ref()
+            .is_some_and(|s| s.old_order == order);
+        if !self.config.safe_apply || reverting || old_order == order {
+            return Ok(action);
+        }
+
+        let old_default = old_order.first().cloned();
+        self.pending_safety = Some(SafeApply {
+            old_order,
+            new_order: order.to_vec(),
+            deadline: 0,
+        });
+        Ok(match old_default {
+            Some(id) => Action::Sequence(vec![action, Action::BootOnce(id)]),
+            None => action,
+        })
+    }
+
+    pub fn safe_apply(&self) -> Option<&SafeApply> {
+        self.saved_state.safe_apply.as_ref()
+    }
+
+    // `swiftboot revert`, and the automatic revert once the safe apply
+    // window has passed: puts the saved order back.
+    pub fn revert_action(&self) -> Result<Action, String> {
+        match &self.saved_state.safe_apply {
+            Some(safety) => self.set_order_action(&safety.old_order),
+            None => Err("No safe apply is pending; nothing to revert".to_string()),
+        }
+    }
+
+    // Queues the revert when the safe apply window passed without a
+    // confirmation. Returns whether it did.
+    pub fn check_safe_apply(&mut self) -> bool {
+        // The welcome popup can wait for the next run; the lock popup can't.
+        if !matches!(self.state, UIState::Main | UIState::Welcome)
+            || !self
+                .safe_apply()
+                .is_some_and(|s| s.expired(state::unix_now()))
+        {
+            return false;
+        }
+        match self.revert_action() {
+            Ok(action) => {
+                if let Some(safety) = self.safe_apply() {
+                    let order = safety.old_order.clone();
+                    self.reorder_entries(&order);
+                }
+                self.pending_action = action;
+                self.review_pending();
+            }
+            Err(e) => self.show_invalid_order(e),
+        }
+        true
+    }
+
+    // Sets `order` as the pending action, or shows why it can't be applied.
+    fn set_order_and_review(&mut self, order: &[String]) {
+        match self.order_action(order) {
+            Ok(action) => {
+                self.pending_action = action;
+                self.review_pending();
+            }
+            Err(e) => self.show_invalid_order(e),
+        }
+    }
+
+    fn show_invalid_order(&mut self, message: String) {
+        self.pending_action = Action::None;
+        self.error_retry = false;
+        self.state = UIState::ErrorMessage(message);
+    }
+
+    // Swaps the first two entries of the working order, the usual way to
+    // make the other OS the default. Returns the new first entry's name.
+    pub fn swap_default(&mut self) -> Result<String, String> {
+        let mut order = self.row_ids(Focus::Priority);
+        if order.len() < 2 {
+            return Err("Need at least two boot entries to swap".to_string());
+        }
+        self.record_reorder(Focus::Priority, Some(order[1].clone()));
+        self.mark_moved(order[0].clone());
+        order.swap(0, 1);
+        self.set_panel_order(Focus::Priority, &order);
+        Ok(self.entry_name(&order[0]))
+    }
+
+    // The order the Priority panel applies.
+    pub fn order_to_apply(&self) -> Vec<String> {
+        if self.pin_rest {
+            return pin_untouched(&self.live_order, &self.draft_order(), &self.moved);
+        }
+        let order = self.draft_order();
+        if self.has_boot_order {
+            return order;
+        }
+        order
+            .into_iter()
+            .filter(|id| {
+                self.all_entries()
+                    .any(|e| &e.id == id && self.device_state(e) != DeviceState::Missing)
+            })
+            .collect()
+    }
+
+    // True while the working order differs from what was last read from or
+    // written to the firmware. Every exit path should consult this.
+    pub fn is_dirty(&self) -> bool {
+        self.draft_order() != self.live_order || self.extra_lists.iter().any(EntryList::is_dirty)
+    }
+
+    pub fn request_quit(&mut self) {
+        if self.is_dirty() {
+            self.state = UIState::QuitConfirm;
+            self.quit_yes = false;
+        } else {
+            self.quit_or_remind();
+        }
+    }
+
+    // A BootNext armed earlier in the session and left for later is easily
+    // forgotten until some unrelated reboot, so quitting mentions it.
+    fn quit_or_remind(&mut self) {
+        match &self.session_boot_next {
+            Some(id) if self.boot_next.as_ref() == Some(id) => {
+                self.state = UIState::QuitBootNext(self.entry_name(id));
+                self.quit_boot_next_choice = 0;
+            }
+            _ => self.should_quit = true,
+        }
+    }
+
+    // Hidden entries don't count: the shifts are between visible rows.
+    pub fn moved_offsets(&self) -> Vec<isize> {
+        let rows = self.row_ids(Focus::Priority);
+        let original: Vec<String> = self
+            .live_order
+            .iter()
+            .filter(|id| rows.contains(id))
+            .cloned()
+            .collect();
+        position_shifts(&original, &rows)
+    }
+
+    pub fn extra_list(&self, kind: EntryKind) -> Option<&EntryList> {
+        self.extra_lists.iter().find(|l| l.kind == kind)
+    }
+
+    // The entries a panel shows. The Priority and Boot To panels share the
+    // boot entries.
+    pub fn panel_entries(&self, panel: Focus) -> Vec<&BootEntry> {
+        match panel {
+            Focus::Priority | Focus::BootOnce => self.rows(),
+            Focus::Extra(kind) => self
+                .extra_list(kind)
+                .map_or_else(Vec::new, |l| l.entries.iter().collect()),
+        }
+    }
+
+    fn row_ids(&self, panel: Focus) -> Vec<String> {
+        self.panel_entries(panel)
+            .iter()
+            .map(|e| e.id.clone())
+            .collect()
+    }
+
+    // Rearranges a panel's rows to `order`, the ones it leaves out after
+    // the others. In the Priority panel the rows take the draft's visible
+    // slots, so hidden entries stay where they are. The Boot To panel
+    // follows the Priority panel and can't be reordered itself.
+    fn set_panel_order(&mut self, panel: Focus, order: &[String]) {
+        let rank = |id: &str| order.iter().position(|o| o == id).unwrap_or(order.len());
+        match panel {
+            Focus::Priority => {
+                let shown: Vec<bool> = self
+                    .draft
+                    .iter()
+                    .map(|id| self.entry(id).is_some_and(|e| !self.is_hidden(e)))
+                    .collect();
+                let mut rows: Vec<String> = self
+                    .draft
+                    .iter()
+                    .zip(&shown)
+                    .filter(|(_, shown)| **shown)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                rows.sort_by_key(|id| rank(id));
+                let mut rows = rows.into_iter();
+                for (slot, shown) in self.draft.iter_mut().zip(shown) {
+                    if shown && let Some(id) = rows.next() {
+                        *slot = id;
+                    }
+                }
+            }
+            Focus::BootOnce => {}
+            Focus::Extra(kind) => {
+                if let Some(list) = self.extra_lists.iter_mut().find(|l| l.kind == kind) {
+                    list.entries.sort_by_key(|e| rank(&e.id));
+                }
+            }
+        }
+    }
+
+    // Index of the panel's selected entry in its entries, falling back to
+    // the nearest remaining row when the selected entry no longer exists.
+    pub fn selected_index(&self, panel: Focus) -> Option<usize> {
+        let entries = self.panel_entries(panel);
+        if entries.is_empty() {
+            return None;
+        }
+        let Some(selection) = self.selections.get(&panel) else {
+            return Some(0);
+        };
+        selection
+            .id
+            .as_ref()
+            .and_then(|id| entries.iter().position(|e| &e.id == id))
+            .or(Some(selection.index.min(entries.len() - 1)))
+    }
+
+    pub fn selected_entry(&self, panel: Focus) -> Option<&BootEntry> {
+        self.selected_index(panel)
+            .map(|i| self.panel_entries(panel)[i])
+    }
+
+    fn select_index(&mut self, panel: Focus, index: usize) {
+        let id = self.panel_entries(panel).get(index).map(|e| e.id.clone());
+        self.selections.insert(panel, Selection { id, index });
+    }
+
+    // Moves the panel's scroll offset just enough to keep the selection,
+    // plus a margin, within `height` rows.
+    pub fn scroll_to_selection(&mut self, panel: Focus, height: usize) {
+        let headers = self.group_headers(panel);
+        let selected = self.selected_index(panel).map(|i| display_row(&headers, i));
+        let len = self.panel_entries(panel).len() + headers.iter().flatten().count();
+        let state = self.list_states.entry(panel).or_default();
+        *state.offset_mut() = ensure_visible(state.offset(), selected, len, height);
+    }
+
+    pub fn list_state(&self, panel: Focus) -> ListState {
+        self.list_states.get(&panel).cloned().unwrap_or_default()
+    }
+
+    // Panels in Tab order. Only panels that are currently shown take part
+    // in focus cycling.
+    pub fn visible_panels(&self) -> Vec<Focus> {
+        let mut panels = vec![Focus::Priority, Focus::BootOnce];
+        panels.extend(self.extra_lists.iter().map(|l| Focus::Extra(l.kind)));
+        panels
+    }
+
+    pub fn is_focused(&self, panel: Focus) -> bool {
+        self.focus == panel
+    }
+
+    pub fn focus_next(&mut self) {
+        self.cycle_focus(1);
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.cycle_focus(-1);
+    }
+
+    fn cycle_focus(&mut self, step: isize) {
+        let panels = self.visible_panels();
+        let len = panels.len() as isize;
+        let current = panels.iter().position(|&p| p == self.focus).unwrap_or(0) as isize;
+        self.focus = panels[(current + step).rem_euclid(len) as usize];
+    }
+
+    fn start_countdown(&mut self, target: ActionSummary) {
+        if self.warn_firmware_update(AfterFirmwareUpdate::Countdown(target.clone())) {
+            return;
+        }
+        self.state = UIState::CountdownReboot {
+            target,
+            deadline: Instant::now() + COUNTDOWN,
+            total: COUNTDOWN,
+            paused_at: None,
+        };
+    }
+
+    pub fn countdown_remaining(&self, now: Instant) -> Option<Duration> {
+        match self.state {
+            UIState::CountdownReboot {
+                deadline,
+                paused_at,
+                ..
+            } => Some(deadline.saturating_duration_since(paused_at.unwrap_or(now))),
+            _ => None,
+        }
+    }
+
+    fn toggle_countdown_pause(&mut self) {
+        if let UIState::CountdownReboot {
+            deadline,
+            paused_at,
+            ..
+        } = &mut self.state
+        {
+            let now = Instant::now();
+            match paused_at.take() {
+                Some(since) => *deadline += now - since,
+                None => *paused_at = Some(now),
+            }
+        }
+    }
+
+    // False with `reboot_cmd = []`: swiftboot then only arms BootNext and
+    // leaves the reboot to the user.
+    pub fn can_reboot(&self) -> bool {
+        self.config
+            .reboot_cmd
+            .as_ref()
+            .is_none_or(|c| !c.is_empty())
+    }
+
+    // Every reboot goes through here. On success the app quits while the
+    // system goes down; a failure is shown like any other failed command.
+    pub fn perform_reboot(&mut self) {
+        if !self.can_reboot() {
+            self.state = UIState::Main;
+            self.show_toast("Rebooting is disabled (reboot_cmd = []); reboot manually");
+            return;
+        }
+        match efi::reboot(self.config.reboot_cmd.as_deref()) {
+            Ok(()) => self.should_quit = true,
+            Err(e) => {
+                self.pending_action = Action::None;
+                self.error_retry = false;
+                self.state = UIState::ErrorMessage(e);
+            }
+        }
+    }
+
+    // Shows the firmware update warning instead of going on to `next`, the
+    // first time this session. Returns whether it did.
+    fn warn_firmware_update(&mut self, next: AfterFirmwareUpdate) -> bool {
+        if self.firmware_update.is_none() || self.firmware_update_seen {
+            return false;
+        }
+        self.firmware_update_yes = true;
+        self.state = UIState::FirmwareUpdate(next);
+        true
+    }
+
+    pub fn show_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+    }
+
+    // Advances timers: expires toasts and runs the reboot countdown,
+    // rebooting once it has run out.
+    pub fn tick(&mut self) {
+        if let Some((_, shown)) = &self.toast
+            && shown.elapsed() >= Duration::from_secs(3)
+        {
+            self.toast = None;
+        }
+
+        if self.countdown_remaining(Instant::now()) == Some(Duration::ZERO) {
+            self.perform_reboot();
+        }
+    }
+
+    // Drives the pending action while in Processing: starts the command for
+    // its first step, then picks up the result once the command is done and
+    // moves on to the next step. Call it repeatedly until `is_running` is
+    // false.
+    pub fn execute_pending(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut job = match self.job.take() {
+            Some(job) => job,
+            None => {
+                let prepared = match self.password_retry.take() {
+                    Some(retry) if retry.action == self.pending_action => {
+                        if let Some(message) = self.changed_since(&retry.prepared) {
+                            self.pending_action = Action::None;
+                            self.password.clear();
+                            self.error_retry = false;
+                            self.state = UIState::ErrorMessage(message);
+                            return Ok(());
+                        }
+                        retry.prepared
+                    }
+                    _ => {
+                        let mut steps: VecDeque<Action> =
+                            self.pending_action.steps().into_iter().cloned().collect();
+                        let Some(step) = steps.pop_front() else {
+                            self.state = UIState::Main;
+                            return Ok(());
+                        };
+                        self.prepare_step(step, steps)
+                    }
+                };
+                self.run_step(prepared)?
+            }
+        };
+
+        loop {
+            match job.events.try_recv() {
+                Ok(SudoEvent::Stderr(line)) => {
+                    if is_auth_prompt(&line) {
+                        job.prompt = Some(line);
+                    }
+                }
+                Ok(SudoEvent::Done(output)) => return self.finish_job(job, output),
+                Err(TryRecvError::Empty) => {
+                    self.job = Some(job);
+                    return Ok(());
+                }
+                Err(TryRecvError::Disconnected) => return Err("Lost track of sudo".into()),
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    // How long the running command has taken and what it last asked for.
+    pub fn job_status(&self) -> Option<(Duration, Option<&str>)> {
+        self.job
+            .as_ref()
+            .map(|job| (job.started.elapsed(), job.prompt.as_deref()))
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.job.as_ref().is_some_and(|job| job.cancelled)
+    }
+
+    // Stops the running command. Its result still arrives through
+    // `execute_pending`; a command that had already succeeded is kept.
+    pub fn cancel_job(&mut self) {
+        if let Some(job) = &mut self.job {
+            job.cancelled = true;
+            efi::kill_child();
+        }
+    }
+
+    fn prepare_step(&mut self, step: Action, rest: VecDeque<Action>) -> PreparedStep {
+        let (args, change) = match &step {
+            Action::ReadEntries => (efi::read_args(), None),
+            step => {
+                let (args, change) = self.command_for(step);
+                (args, Some(change))
+            }
+        };
+        let safety = match &step {
+            Action::SetOrder(order) => self
+                .pending_safety
+                .take_if(|safety| safety.new_order == EntryId::to_strings(order)),
+            _ => None,
+        };
+        PreparedStep {
+            args,
+            step,
+            change,
+            rest,
+            safety,
+            order: self.boot_order.clone(),
+            next: self.boot_next.clone(),
+        }
+    }
+
+    fn run_step(&mut self, prepared: PreparedStep) -> Result<Job, Box<dyn std::error::Error>> {
+        // Recorded before the write so the old order survives a crash or
+        // power loss half-way through.
+        if let Some(safety) = &prepared.safety {
+            self.saved_state.safe_apply = Some(SafeApply {
+                deadline: state::unix_now() + self.config.safe_apply_minutes * 60,
+                ..safety.clone()
+            });
+            self.saved_state.save()?;
+        }
+        let events = if self.efivarfs_writes && !prepared.step.runs_through_sudo() {
+            efivars::spawn_write(self.write_for(&prepared.step))
+        } else {
+            efi::spawn_sudo_command(&prepared.args, self.password.text())?
+        };
+        Ok(Job {
+            events,
+            prepared,
+            started: Instant::now(),
+            prompt: None,
+            cancelled: false,
+        })
+    }
+
+    // Why a prepared command is no longer what the user confirmed: BootOrder
+    // or BootNext changed since it was built, e.g. by another tool while the
+    // password was being retyped. None when it still applies.
+    fn changed_since(&self, prepared: &PreparedStep) -> Option<String> {
+        let status = self.read_status().ok()?;
+        if status.order == prepared.order && status.next == prepared.next {
+            return None;
+        }
+        Some(
+            "The boot setup changed while the password was asked for, so nothing was written. \
+             Check the entries and try again."
+                .to_string(),
+        )
+    }
+
+    fn finish_job(
+        &mut self,
+        mut job: Job,
+        output: CommandOutput,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ok = output.success;
+        // A safety net for an order that never got written is dropped again.
+        if !ok
+            && let Action::SetOrder(order) = &job.prepared.step
+            && self
+                .saved_state
+                .safe_apply
+                .as_ref()
+                .is_some_and(|s| s.new_order == EntryId::to_strings(order))
+        {
+            self.saved_state.safe_apply = None;
+            let _ = self.saved_state.save();
+        }
+
+        if job.cancelled && !ok {
+            self.password.clear();
+            self.state = UIState::Main;
+            self.show_toast("Cancelled");
+            return Ok(());
+        }
+
+        let mut rest = std::mem::take(&mut job.prepared.rest);
+        let state = if ok {
+            let message = &output.stderr;
+            if !message.is_empty() {
+                let warnings = self.command_warnings.get_or_insert_with(String::new);
+                if !warnings.is_empty() {
+                    warnings.push('\n');
+                }
+                warnings.push_str(message);
+            }
+            self.changes.extend(job.prepared.change);
+            self.step_succeeded(&job.prepared.step, &output.stdout)
+        } else if output.password_rejected() {
+            self.password_retry = Some(PasswordRetry {
+                action: self.pending_action.clone(),
+                prepared: PreparedStep {
+                    rest: std::mem::take(&mut rest),
+                    ..job.prepared
+                },
+            });
+            UIState::PasswordError
+        } else if let Action::DeferOrder(_) = job.prepared.step {
+            self.password.clear();
+            self.apply_now_yes = true;
+            UIState::DeferUnavailable(output.stderr)
+        } else if output.nvram_full() {
+            UIState::NvramFull(output.stderr)
+        } else {
+            UIState::ErrorMessage(output.stderr)
+        };
+
+        if !is_failure(&state)
+            && let Some(next) = rest.pop_front()
+        {
+            let prepared = self.prepare_step(next, rest);
+            self.job = Some(self.run_step(prepared)?);
+            return Ok(());
+        }
+
+        self.state = match self.modified_order.take() {
+            Some((requested, actual)) if !is_failure(&state) => UIState::OrderModified {
+                requested,
+                actual,
+                then: Box::new(state),
+            },
+            _ => state,
+        };
+        if is_failure(&self.state) {
+            self.password.clear();
+            self.error_retry = true;
+        }
+        Ok(())
+    }
+
+    // The efibootmgr arguments for one step, and what it changes.
+    fn command_for(&self, step: &Action) -> (Vec<String>, Change) {
+        match step {
+            Action::SetOrder(order_ids) => (
+                efi::efibootmgr_args(&["-o", &EntryId::join(order_ids)]),
+                Change::Order {
+                    kind: None,
+                    old: self.live_order.clone(),
+                    new: EntryId::to_strings(order_ids),
+                },
+            ),
+            Action::SetExtraOrder(kind, order_ids) => (
+                efi::efibootmgr_args(&[kind.flag(), "-o", &order_ids.join(",")]),
+                Change::Order {
+                    kind: Some(*kind),
+                    old: self
+                        .extra_list(*kind)
+                        .map(|l| l.live_order.clone())
+                        .unwrap_or_default(),
+                    new: order_ids.clone(),
+                },
+            ),
+            Action::BootOnce(id) => (
+                efi::efibootmgr_args(&["-n", id]),
+                Change::BootNext(id.clone()),
+            ),
+            Action::BootOnceByReorder(id) => {
+                let order = self.temporary_order_for(id);
+                (
+                    efi::efibootmgr_args(&["-o", &order.join(",")]),
+                    Change::Order {
+                        kind: None,
+                        old: self.boot_order.clone(),
+                        new: order,
+                    },
+                )
+            }
+            Action::SetTimeout(seconds) => (
+                efi::efibootmgr_args(&["-t", &seconds.to_string()]),
+                Change::Timeout(*seconds),
+            ),
+            Action::Activate(id) => (
+                efi::efibootmgr_args(&["-a", "-b", id]),
+                Change::Activated(id.clone()),
+            ),
+            Action::Deactivate(id) => (
+                efi::efibootmgr_args(&["-A", "-b", id]),
+                Change::Deactivated(id.clone()),
+            ),
+            // efibootmgr also takes the ID out of BootOrder.
+            Action::Delete(id) => (
+                efi::efibootmgr_args(&["-B", "-b", id]),
+                Change::Deleted(self.entry_name(id)),
+            ),
+            Action::DeferOrder(order_ids) => (
+                efi::defer_args(&efi::efibootmgr_args(&["-o", &EntryId::join(order_ids)])),
+                Change::Deferred(EntryId::to_strings(order_ids)),
+            ),
+            Action::CancelDeferred => (efi::cancel_deferred_args(), Change::DeferralCancelled),
+            Action::ClearBootNext => (efi::efibootmgr_args(&["-N"]), Change::BootNextCleared),
+            Action::ReadEntries => unreachable!("reads change nothing; see `prepare_step`"),
+            Action::Sequence(_) | Action::None => {
+                unreachable!("steps are never sequences or empty")
+            }
+        }
+    }
+
+    // The same step for `--backend efivarfs`.
+    fn write_for(&self, step: &Action) -> efivars::Write {
+        match step {
+            Action::SetOrder(order_ids) => {
+                efivars::Write::Order("BootOrder", EntryId::to_strings(order_ids))
+            }
+            Action::SetExtraOrder(kind, order_ids) => {
+                efivars::Write::Order(kind.order_variable(), order_ids.clone())
+            }
+            Action::BootOnce(id) => efivars::Write::Next(id.clone()),
+            Action::BootOnceByReorder(id) => {
+                efivars::Write::Order("BootOrder", self.temporary_order_for(id))
+            }
+            Action::SetTimeout(seconds) => efivars::Write::Timeout(*seconds),
+            Action::Activate(id) => efivars::Write::Active(id.clone(), true),
+            Action::Deactivate(id) => efivars::Write::Active(id.clone(), false),
+            Action::Delete(id) => efivars::Write::Delete(id.clone()),
+            Action::ClearBootNext => efivars::Write::ClearNext,
+            Action::DeferOrder(_) | Action::CancelDeferred | Action::ReadEntries => {
+                unreachable!("these steps always run through sudo")
+            }
+            Action::Sequence(_) | Action::None => {
+                unreachable!("steps are never sequences or empty")
+            }
+        }
+    }
+
+    // The boot status as the backend in use sees it.
+    fn read_status(&self) -> Result<BootStatus, Box<dyn std::error::Error>> {
+        if self.reads_efivarfs {
+            Ok(efivars::read_status())
+        } else {
+            efi::fetch_boot_status()
+        }
+    }
+
+    // `r`: re-reads the entries and BootOrder, e.g. after changing them with
+    // another tool. Unapplied edits would be lost, so those come first.
+    pub fn refresh(&mut self) -> Result<(), String> {
+        if self.is_dirty() {
+            return Err("Apply or undo the changes before refreshing".to_string());
+        }
+        // Reads since startup, e.g. after a write, aren't part of this one.
+        efi::take_timings();
+        let fetched = if self.reads_efivarfs {
+            efivars::read_entries("Boot").map(|entries| (entries, efivars::read_status()))
+        } else {
+            efi::fetch_boot_entries().and_then(|entries| Ok((entries, efi::fetch_boot_status()?)))
+        };
+        self.note_read_time(&efi::take_timings());
+        report::save_captures();
+        let (entries, status) = fetched.map_err(|e| e.to_string())?;
+        self.take_entries(entries, status);
+        Ok(())
+    }
+
+    // Asks for the password to read the entries through sudo, where
+    // efibootmgr can't read them as this user: at startup, and for a
+    // refresh.
+    pub fn read_with_sudo(&mut self) {
+        self.pending_action = Action::ReadEntries;
+        self.password.clear();
+        self.state = UIState::AskPassword;
+    }
+
+    // Replaces the entries and the status with freshly read ones.
+    fn take_entries(&mut self, mut entries: Vec<BootEntry>, status: BootStatus) {
+        efi::add_placeholders(&mut entries, &status.order);
+        sort_by_order(&mut entries, &status.order);
+
+        self.draft = draft_from(&entries, &status.order);
+        self.entries = entries;
+        self.live_order = self.draft_order();
+        self.current_boot_id = status.order.first().cloned().unwrap_or_default();
+        self.has_boot_order = !status.order.is_empty();
+        self.boot_current = status.current;
+        self.boot_next = status.next;
+        self.timeout = status.timeout;
+        self.check_order_drift(&status.order);
+        self.boot_order = status.order;
+        self.clear_history();
+    }
+
+    // Keeps how long a read of the entries took when that is over
+    // `slow_read_ms`, for the status bar: slow firmware variables shouldn't
+    // look like a slow swiftboot.
+    pub fn note_read_time(&mut self, timings: &[Timing]) {
+        let total: Duration = timings.iter().map(Timing::total).sum();
+        let threshold = Duration::from_millis(self.config.slow_read_ms);
+        self.slow_read = (self.config.slow_read_ms > 0 && total > threshold).then_some(total);
+    }
+
+    fn check_order_drift(&mut self, live: &[String]) {
+        let Some(applied) = &self.last_applied else {
+            return;
+        };
+        if applied == live {
+            self.order_drift = None;
+            self.dismissed_drift = None;
+        } else if self.dismissed_drift.as_deref() != Some(live) {
+            self.order_drift = Some((applied.clone(), live.to_vec()));
+            self.dismissed_drift = None;
+        }
+    }
+
+    // The banners go one at a time, top first.
+    pub fn dismiss_banner(&mut self) {
+        if let Some((_, live)) = self.order_drift.take() {
+            self.dismissed_drift = Some(live);
+        } else if self.command_warnings.is_some() {
+            self.command_warnings = None;
+        } else {
+            self.suggested_profile = None;
+        }
+    }
+
+    // At startup: the first profile scheduled for now that would change the
+    // order, highlighted in the picker so `p` then Enter applies it.
+    pub fn check_profile_suggestion(&mut self) {
+        let live = self.draft_order();
+        let suggested = self.config.profiles.iter().position(|(name, profile)| {
+            profile.suggest.as_ref().is_some_and(|s| s.matches_now())
+                && self.profile_order(name).is_ok_and(|order| order != live)
+        });
+        if let Some(index) = suggested {
+            self.profile_index = index;
+            self.suggested_profile = self.profile_names().get(index).map(|n| n.to_string());
+        }
+    }
+
+    // Adds `c` to what has been typed so far, starting over after a pause,
+    // and selects the first entry in the focused panel whose name starts
+    // with it. When nothing does, `c` on its own is tried, so a mistyped
+    // letter doesn't need a pause to recover from. Returns whether the
+    // selection moved.
+    pub fn type_ahead(&mut self, c: char, now: Instant) -> bool {
+        let mut typed = match self.type_ahead.take() {
+            Some((typed, last)) if now.duration_since(last) < TYPE_AHEAD_RESET => typed,
+            _ => String::new(),
+        };
+        typed.extend(c.to_lowercase());
+
+        let find = |prefix: &str| {
+            self.panel_entries(self.focus)
+                .iter()
+                .position(|e| self.display_name(e).to_lowercase().starts_with(prefix))
+        };
+        let found = match find(&typed) {
+            Some(i) => Some(i),
+            None => {
+                let single = c.to_lowercase().to_string();
+                let i = find(&single);
+                if i.is_some() {
+                    typed = single;
+                }
+                i
+            }
+        };
+        self.type_ahead = Some((typed, now));
+        match found {
+            Some(i) => {
+                self.select_index(self.focus, i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Where a confirmed action goes next: the password prompt, unless
+    // there's no password to ask for.
+    fn password_state(&self) -> UIState {
+        let sudo = self
+            .pending_action
+            .steps()
+            .iter()
+            .any(|s| s.runs_through_sudo());
+        if self.efivarfs_writes && !sudo {
+            UIState::Processing
+        } else {
+            UIState::AskPassword
+        }
+    }
+
+    // Updates the app after a step's command succeeded and returns the
+    // state to continue in.
+    fn step_succeeded(&mut self, step: &Action, stdout: &str) -> UIState {
+        match step {
+            Action::SetOrder(order_ids) => {
+                let reverted = self
+                    .safe_apply()
+                    .is_some_and(|s| s.old_order == EntryId::to_strings(order_ids));
+                if reverted {
+                    self.saved_state.safe_apply = None;
+                    let _ = self.saved_state.save();
+                }
+                // Restored, or replaced by an order set on purpose: either
+                // way there's nothing left to put back.
+                if self.saved_state.temporary_order.take().is_some() {
+                    let _ = self.saved_state.save();
+                }
+                let order = self.verify_order(EntryId::to_strings(order_ids), stdout);
+                // An armed BootNext still goes first.
+                let target = match &self.boot_next {
+                    Some(id) => self.summary(id, true),
+                    None => self.summary(order.first().map_or("", String::as_str), false),
+                };
+                self.clear_history();
+                self.last_applied = Some(order.clone());
+                self.order_drift = None;
+                self.dismissed_drift = None;
+                // What was written can differ from the draft (pinned rest,
+                // left-out missing entries, a firmware rewrite); the draft
+                // starts over from it, entries outside it included.
+                self.set_draft(&order);
+                self.boot_order = order;
+                self.live_order = self.draft_order();
+                self.has_boot_order = true;
+                // Expert mode never reboots on its own after an order change.
+                if self.config.expert_mode {
+                    self.show_toast(
+                        "Boot order applied (expert mode, not confirmed); reboot when ready",
+                    );
+                    UIState::Main
+                } else if self.can_reboot() {
+                    UIState::ConfirmReboot(target)
+                } else {
+                    self.show_toast("Boot order updated; reboot manually to use it");
+                    UIState::Main
+                }
+            }
+            Action::SetExtraOrder(kind, order_ids) => {
+                if let Some(list) = self.extra_lists.iter_mut().find(|l| l.kind == *kind) {
+                    list.live_order = order_ids.clone();
+                }
+                self.clear_history();
+                self.show_toast(format!("{}Order updated", kind.prefix()));
+                UIState::Main
+            }
+            Action::BootOnce(id) => {
+                self.saved_state.last_boot_once = Some(id.clone());
+                self.saved_state.armed_boot_next = Some(ArmedBootNext {
+                    target: id.clone(),
+                    armed_at: state::unix_now(),
+                    boot_id: state::boot_id(),
+                });
+                let _ = self.saved_state.save();
+                self.boot_next = Some(id.clone());
+                self.session_boot_next = Some(id.clone());
+                self.reboot_choice = self.config.after_boot_once;
+                // Straight to the countdown, unless BootNext only came along
+                // with an order change (apply and boot once, safe apply).
+                if self.config.expert_mode && self.can_reboot() && !self.pending_action.sets_order()
+                {
+                    self.start_countdown(self.summary(id, true));
+                    std::mem::replace(&mut self.state, UIState::Main)
+                } else if self.can_reboot() {
+                    UIState::BootNextArmed(self.summary(id, true))
+                } else {
+                    let name = self.entry_name(id);
+                    self.show_toast(format!("BootNext armed: reboot manually to start {}", name));
+                    UIState::Main
+                }
+            }
+            Action::BootOnceByReorder(id) => {
+                let order = self.temporary_order_for(id);
+                // A second temporary reorder still goes back to the order
+                // from before the first.
+                let original = match self.saved_state.temporary_order.take() {
+                    Some(earlier) => earlier.original,
+                    None => self.boot_order.clone(),
+                };
+                self.saved_state.temporary_order = Some(TemporaryOrder {
+                    original,
+                    target: id.clone(),
+                    boot_id: state::boot_id(),
+                });
+                self.saved_state.last_boot_once = Some(id.clone());
+                let _ = self.saved_state.save();
+                self.set_draft(&order);
+                self.clear_history();
+                self.live_order = self.draft_order();
+                self.boot_order = order;
+                self.has_boot_order = true;
+                if self.can_reboot() {
+                    UIState::ConfirmReboot(self.summary(id, true))
+                } else {
+                    let name = self.entry_name(id);
+                    self.show_toast(format!(
+                        "{} is first until the next run restores the order; reboot manually",
+                        name
+                    ));
+                    UIState::Main
+                }
+            }
+            Action::Activate(id) | Action::Deactivate(id) => {
+                let active = matches!(step, Action::Activate(_));
+                for entry in self.entries.iter_mut().filter(|e| e.id == *id) {
+                    entry.active = active;
+                }
+                UIState::Main
+            }
+            Action::Delete(id) => {
+                self.entries.retain(|e| e.id != *id);
+                self.draft.retain(|other| other != id);
+                self.boot_order.retain(|other| other != id);
+                self.live_order.retain(|other| other != id);
+                UIState::Main
+            }
+            Action::DeferOrder(order_ids) => {
+                let order = EntryId::to_strings(order_ids);
+                self.show_toast(format!(
+                    "Deferred: {} from the next shutdown on",
+                    self.order_names(&order)
+                ));
+                self.saved_state.deferred = Some(DeferredOrder {
+                    order,
+                    deferred_at: state::unix_now(),
+                    boot_id: state::boot_id(),
+                });
+                let _ = self.saved_state.save();
+                // The firmware keeps the live order until then, so the draft
+                // goes back to it.
+                let live = self.live_order.clone();
+                self.set_draft(&live);
+                self.clear_history();
+                UIState::Main
+            }
+            Action::CancelDeferred => {
+                self.saved_state.deferred = None;
+                let _ = self.saved_state.save();
+                self.show_toast("Deferred change cancelled");
+                UIState::Main
+            }
+            Action::ClearBootNext => {
+                self.boot_next = None;
+                self.saved_state.armed_boot_next = None;
+                let _ = self.saved_state.save();
+                self.should_quit = true;
+                UIState::Main
+            }
+            Action::ReadEntries => {
+                efi::capture("sudo efibootmgr -v", stdout);
+                report::save_captures();
+                self.take_entries(
+                    efi::parse_entries(stdout, "Boot"),
+                    efi::parse_status(stdout),
+                );
+                self.resolve_partitions(&partition::SystemResolver::new());
+                self.start_at(None, None);
+                self.show_toast("Boot entries read with sudo");
+                UIState::Main
+            }
+            Action::SetTimeout(seconds) => {
+                self.timeout = Some(*seconds);
+                match self.reboot_after_timeout.take() {
+                    Some(target) => {
+                        self.start_countdown(target);
+                        std::mem::replace(&mut self.state, UIState::Main)
+                    }
+                    None => UIState::Main,
+                }
+            }
+            Action::Sequence(_) | Action::None => UIState::Main,
+        }
+    }
+
+    // Some firmware accepts a BootOrder write and then rewrites it, so the
+    // order is read back rather than trusting the exit code. efibootmgr
+    // prints the variables again after writing, so `stdout` usually has it;
+    // otherwise (efivarfs, or `-q` among the extra arguments) it is read
+    // anew. Returns the order actually in effect.
+    fn verify_order(&mut self, requested: Vec<String>, stdout: &str) -> Vec<String> {
+        let echoed = efi::parse_status(stdout);
+        let status = if !echoed.order.is_empty() {
+            echoed
+        } else if let Ok(status) = self.read_status() {
+            status
+        } else {
+            return requested;
+        };
+        if status.order == requested {
+            return requested;
+        }
+
+        let actual = status.order;
+        self.set_draft(&actual);
+        if let Some(Change::Order {
+            kind: None, new, ..
+        }) = self.changes.last_mut()
+        {
+            *new = actual.clone();
+        }
+        self.modified_order = Some((requested, actual.clone()));
+        actual
+    }
+
+    // Runs the failed action again. The boot status is re-read first, so
+    // nothing is done if the change has meanwhile been made elsewhere.
+    fn retry_pending(&mut self) {
+        if let Ok(status) = self.read_status() {
+            let done = match &self.pending_action {
+                Action::SetOrder(order) => status.order == EntryId::to_strings(order),
+                Action::BootOnce(id) => status.next.as_deref() == Some(id.as_str()),
+                _ => false,
+            };
+            self.boot_next = status.next;
+            self.has_boot_order = !status.order.is_empty();
+
+            if done {
+                if let Action::SetOrder(order) = &self.pending_action {
+                    self.boot_order = EntryId::to_strings(order);
+                    self.set_draft(&EntryId::to_strings(order));
+                    self.live_order = self.draft_order();
+                }
+                self.pending_action = Action::None;
+                self.state = UIState::Main;
+                self.show_toast("Already applied, nothing to retry");
+                return;
+            }
+        }
+
+        self.password.clear();
+        self.state = if efi::sudo_cached() {
+            UIState::Processing
+        } else {
+            self.password_state()
+        };
+    }
+
+    // The inactive entry the pending action would boot, if any: the BootNext
+    // target, or the new first entry of the boot order.
+    fn inactive_target(&self) -> Option<&BootEntry> {
+        let id = match (&self.pending_action, self.pending_action.boot_once_id()) {
+            (_, Some(id)) => id,
+            (Action::SetOrder(order), None) => order.first()?.as_str(),
+            _ => return None,
+        };
+        self.all_entries().find(|e| e.id == id && !e.active)
+    }
+
+    // Moves on from the main view once an action is pending: warn about an
+    // inactive target first, then confirm and ask for the password.
+    fn review_pending(&mut self) {
+        // The safe apply net relies on BootNext itself, so it's left alone.
+        let plain_boot_once = self.pending_safety.is_none()
+            && self
+                .pending_action
+                .steps()
+                .iter()
+                .any(|step| matches!(step, Action::BootOnce(_)));
+        if plain_boot_once {
+            match self.config.boot_once_method {
+                BootOnceMethod::BootNext => {}
+                BootOnceMethod::ReorderTemporarily => self.boot_once_by_reorder(),
+                BootOnceMethod::Ask => {
+                    self.boot_once_reorder = false;
+                    self.state = UIState::ChooseBootOnceMethod;
+                    return;
+                }
+            }
+        }
+        self.review_target();
+    }
+
+    pub fn boot_once_by_reorder(&mut self) {
+        let action = std::mem::replace(&mut self.pending_action, Action::None);
+        self.pending_action = action.by_reorder();
+    }
+
+    // BootOrder with `id` moved to the front, the rest as they are.
+    fn temporary_order_for(&self, id: &str) -> Vec<String> {
+        let mut order = vec![id.to_string()];
+        order.extend(self.boot_order.iter().filter(|other| *other != id).cloned());
+        order
+    }
+
+    // Offers to restore the order a temporary reorder replaced, once the
+    // machine has booted since. Returns whether it did.
+    pub fn check_temporary_order(&mut self) -> bool {
+        if !matches!(self.state, UIState::Main) {
+            return false;
+        }
+        let Some(temporary) = self.saved_state.temporary_order.clone() else {
+            return false;
+        };
+        if !temporary.restore_due(
+            state::boot_id().as_deref(),
+            self.saved_state.boot_current.as_deref(),
+        ) {
+            return false;
+        }
+        // Already back, e.g. restored by hand.
+        if temporary.original == self.boot_order {
+            self.saved_state.temporary_order = None;
+            let _ = self.saved_state.save();
+            return false;
+        }
+        self.restore_yes = true;
+        self.state = UIState::RestoreOrder(temporary);
+        true
+    }
+
+    // The restore of a temporary reorder, leaving out entries that have
+    // since been deleted.
+    pub fn restore_action(&self, temporary: &TemporaryOrder) -> Result<Action, String> {
+        let order: Vec<String> = temporary
+            .original
+            .iter()
+            .filter(|id| self.all_entries().any(|e| &e.id == *id))
+            .cloned()
+            .collect();
+        self.set_order_action(&order)
+    }
+
+    // What became of the BootNext armed in an earlier run, once it's no
+    // longer pending: a message to show, and the record is dropped. A
+    // pending one is left to the » marker.
+    pub fn check_armed_boot_next(&mut self) -> Option<String> {
+        let armed = self.saved_state.armed_boot_next.clone()?;
+        let outcome = armed.outcome(
+            self.boot_next.as_deref(),
+            self.boot_current.as_deref(),
+            state::boot_id().as_deref(),
+        );
+        if outcome == BootNextOutcome::Pending {
+            return None;
+        }
+        self.saved_state.armed_boot_next = None;
+        let _ = self.saved_state.save();
+        let name = self.entry_name(&armed.target);
+        Some(match outcome {
+            BootNextOutcome::Completed => format!("Previous one-shot boot into {} completed", name),
+            _ => format!(
+                "The one-shot boot into {} armed {} was cleared without being used",
+                name,
+                state::local_time(armed.armed_at)
+            ),
+        })
+    }
+
+    pub fn deferred(&self) -> Option<&DeferredOrder> {
+        self.saved_state.deferred.as_ref()
+    }
+
+    // What became of a change deferred in an earlier run, once its unit is
+    // gone: a message to show, and the record is dropped. The unit doesn't
+    // outlive the boot, so after a reboot it has had its chance; within the
+    // same boot it was stopped by something else.
+    pub fn check_deferred(&mut self) -> Option<String> {
+        let deferred = self.saved_state.deferred.clone()?;
+        if efi::deferred_unit_active() {
+            return None;
+        }
+        self.saved_state.deferred = None;
+        let _ = self.saved_state.save();
+        let rebooted = match (deferred.boot_id.as_deref(), state::boot_id()) {
+            (Some(then), Some(now)) => then != now.as_str(),
+            _ => true,
+        };
+        let names = self.order_names(&deferred.order);
+        Some(if !rebooted {
+            format!(
+                "The boot order deferred {} was cancelled outside swiftboot",
+                state::local_time(deferred.deferred_at)
+            )
+        } else if self.boot_order == deferred.order {
+            format!("The boot order deferred to shutdown was applied: {}", names)
+        } else {
+            format!(
+                "The boot order deferred to shutdown wasn't applied: {}",
+                names
+            )
+        })
+    }
+
+    // Why the pending order can't be deferred to shutdown, if so.
+    fn defer_unavailable(&self) -> Option<String> {
+        if self.efivarfs_writes {
+            return Some(
+                "the efivarfs backend can't hand the write to a shutdown unit".to_string(),
+            );
+        }
+        efi::defer_unavailable()
+    }
+
+    // Sets up the pending order to be written at the next shutdown instead
+    // of now. A change deferred earlier is replaced.
+    fn defer_order(&mut self) {
+        let order = self.order_to_apply();
+        if self.has_boot_order && order == self.boot_order && self.deferred().is_none() {
+            self.show_toast("This order is already in effect; it applies from the next reboot");
+            return;
+        }
+        if let Some(reason) = self.defer_unavailable() {
+            self.apply_now_yes = true;
+            self.state = UIState::DeferUnavailable(reason);
+            return;
+        }
+        let ids = match self.set_order_action(&order) {
+            Ok(Action::SetOrder(ids)) => ids,
+            Ok(_) => return,
+            Err(e) => return self.show_invalid_order(e),
+        };
+        self.pending_action = if efi::deferred_unit_active() {
+            Action::Sequence(vec![Action::CancelDeferred, Action::DeferOrder(ids)])
+        } else {
+            Action::DeferOrder(ids)
+        };
+        self.confirm_pending();
+    }
+
+    // Declining a restore keeps the current order for good.
+    pub fn keep_temporary_order(&mut self) {
+        self.saved_state.temporary_order = None;
+        let _ = self.saved_state.save();
+    }
+
+    fn review_target(&mut self) {
+        match self.inactive_target() {
+            Some(e) => {
+                self.state = UIState::ConfirmInactive(e.id.clone());
+                self.inactive_choice = InactiveChoice::Activate;
+            }
+            None => self.confirm_pending(),
+        }
+    }
+
+    fn confirm_pending(&mut self) {
+        match self.windows_first_warning() {
+            Some((windows, linux)) => {
+                self.windows_first_dismiss = false;
+                self.state = UIState::WindowsFirst { windows, linux };
+            }
+            None => self.confirm_writes(),
+        }
+    }
+
+    // Windows feature updates are known to rewrite BootOrder, sometimes
+    // dropping the Linux entries from it. When the pending order newly puts
+    // a Windows entry first with a Linux entry further down, returns both
+    // names so the user can be told before applying.
+    pub fn windows_first_warning(&self) -> Option<(String, String)> {
+        if !self.config.warn_windows_first || self.saved_state.windows_first_dismissed {
+            return None;
+        }
+        let order = self
+            .pending_action
+            .steps()
+            .into_iter()
+            .find_map(|step| match step {
+                Action::SetOrder(ids) | Action::DeferOrder(ids) => Some(EntryId::to_strings(ids)),
+                _ => None,
+            })?;
+        let entry = |id: &String| self.all_entries().find(|e| &e.id == id);
+        let first = entry(order.first()?)?;
+        if first.os_kind() != OsKind::Windows || self.boot_order.first() == Some(&first.id) {
+            return None;
+        }
+        let linux = order[1..]
+            .iter()
+            .filter_map(entry)
+            .find(|e| e.os_kind() == OsKind::Linux)?;
+        Some((
+            self.display_name(first).to_string(),
+            self.display_name(linux).to_string(),
+        ))
+    }
+
+    pub fn dismiss_windows_first_warning(&mut self) {
+        self.saved_state.windows_first_dismissed = true;
+        let _ = self.saved_state.save();
+    }
+
+    // Boot-once actions are confirmed unless disabled in the config; the
+    // combined apply + boot once always is, so both steps can be reviewed.
+    fn confirm_writes(&mut self) {
+        if self.pending_action.boot_once_id().is_some()
+            && self.warn_firmware_update(AfterFirmwareUpdate::Writes)
+        {
+            return;
+        }
+        self.password.clear();
+        if let Some(warning) = low_nvram_warning(&self.pending_action) {
+            self.show_toast(warning);
+        }
+        let action = &self.pending_action;
+        self.state = if self.config.expert_mode {
+            self.expert_write_state()
+        } else if action.boot_once_id().is_some()
+            && (self.config.confirm_boot_once || action.sets_order())
+        {
+            self.boot_once_yes = true;
+            UIState::ConfirmBootOnce
+        } else {
+            self.password_state()
+        };
+    }
+
+    // Expert mode goes straight to writing when sudo won't ask.
+    fn expert_write_state(&self) -> UIState {
+        if !self.efivarfs_writes && (efivars::is_root() || efi::sudo_cached()) {
+            UIState::Processing
+        } else {
+            self.password_state()
+        }
+    }
+
+    // The change is written either way; only the reboot is off, so say what
+    // the next one will do.
+    fn reboot_postponed(&mut self, target: &ActionSummary) {
+        self.show_toast(if !target.changed {
+            "Reboot cancelled".to_string()
+        } else if target.boot_once {
+            format!(
+                "Reboot cancelled; BootNext stays armed: {} on next reboot",
+                target.target
+            )
+        } else {
+            format!(
+                "Reboot cancelled; the new order is applied: {} from the next reboot on",
+                target.target
+            )
+        });
+    }
+
+    fn arm_for_later(&mut self) {
+        self.state = UIState::Main;
+        let name = self.boot_next_name().unwrap_or_default();
+        self.show_toast(format!("BootNext armed: {} on next reboot", name));
+    }
+
+    fn summary(&self, id: &str, boot_once: bool) -> ActionSummary {
+        ActionSummary {
+            target: self.entry_name(id),
+            boot_once,
+            changed: true,
+        }
+    }
+
+    // Where a reboot with nothing written first lands: the armed BootNext,
+    // or else the first entry in BootOrder.
+    pub fn reboot_target(&self) -> ActionSummary {
+        let (target, boot_once) = match (&self.boot_next, self.boot_order.first()) {
+            (Some(id), _) => (self.entry_name(id), true),
+            (None, Some(id)) => (self.entry_name(id), false),
+            (None, None) => ("the firmware's default".to_string(), false),
+        };
+        ActionSummary {
+            target,
+            boot_once,
+            changed: false,
+        }
+    }
+
+    // The reboot key: the same prompt and countdown as after an apply, with
+    // no write before them. Expert mode goes straight to the countdown.
+    fn request_reboot(&mut self) {
+        if !self.can_reboot() {
+            self.show_toast("Rebooting is disabled (reboot_cmd = []); reboot manually");
+            return;
+        }
+        let target = self.reboot_target();
+        if self.config.expert_mode {
+            self.start_countdown(target);
+        } else {
+            self.reboot_yes = true;
+            self.state = UIState::ConfirmReboot(target);
+        }
+    }
+
+    pub fn boot_next_name(&self) -> Option<String> {
+        self.boot_next.as_deref().map(|id| self.entry_name(id))
+    }
+
+    pub fn previous_boot_name(&self) -> Option<String> {
+        self.previous_boot.as_deref().map(|id| self.entry_name(id))
+    }
+
+    // What was written this session, one line each, for the terminal after
+    // exit. Repeated writes to the same order collapse into first -> last.
+    pub fn exit_summary(&self) -> Vec<String> {
+        let mut orders: Vec<(Option<EntryKind>, &[String], &[String])> = Vec::new();
+        let mut lines = Vec::new();
+        for change in &self.changes {
+            match change {
+                Change::Order { kind, old, new } => {
+                    match orders.iter_mut().find(|(k, _, _)| k == kind) {
+                        Some(entry) => entry.2 = new,
+                        None => orders.push((*kind, old, new)),
+                    }
+                }
+                Change::BootNext(id) => {
+                    lines.push(format!("BootNext set to {}", self.entry_name(id)))
+                }
+                Change::Timeout(seconds) => lines.push(format!("Timeout set to {}s", seconds)),
+                Change::Activated(id) => lines.push(format!("activated {}", self.entry_name(id))),
+                Change::Deactivated(id) => {
+                    lines.push(format!("deactivated {}", self.entry_name(id)))
+                }
+                Change::Deleted(name) => lines.push(format!("deleted {}", name)),
+                Change::Deferred(order) => lines.push(format!(
+                    "BootOrder deferred to the next shutdown: {}",
+                    self.order_names(order)
+                )),
+                Change::DeferralCancelled => lines.push("deferred change cancelled".to_string()),
+                Change::BootNextCleared => lines.push("BootNext cleared".to_string()),
+            }
+        }
+
+        let mut summary: Vec<String> = orders
+            .into_iter()
+            .map(|(kind, old, new)| {
+                let (label, old, new) = match kind {
+                    Some(kind) => (
+                        format!("{}Order", kind.prefix()),
+                        old.join(","),
+                        new.join(","),
+                    ),
+                    None => (
+                        "BootOrder".to_string(),
+                        self.order_names(old),
+                        self.order_names(new),
+                    ),
+                };
+                format!("{} changed from {} to {}", label, old, new)
+            })
+            .collect();
+        summary.extend(lines);
+        if summary.is_empty() {
+            summary.push("no changes made".to_string());
+        }
+        summary
+    }
+
+    pub fn order_names(&self, order: &[String]) -> String {
+        if order.is_empty() {
+            return "(none)".to_string();
+        }
+        let names: Vec<String> = order.iter().map(|id| self.entry_name(id)).collect();
+        names.join(", ")
+    }
+
+    pub fn entry_name(&self, id: &str) -> String {
+        match self.all_entries().find(|e| e.id == id) {
+            Some(e) => self.display_name(e).to_string(),
+            None => format!("Boot{}", id),
+        }
+    }
+
+    // What has to be typed before deleting `id` with `confirm_destructive =
+    // "type-name"`: the start of its name as shown. None with "button".
+    pub fn delete_phrase(&self, id: &str) -> Option<String> {
+        if self.config.confirm_destructive != ConfirmDestructive::TypeName {
+            return None;
+        }
+        let name = self.entry_name(id);
+        Some(name.trim().chars().take(TYPED_NAME_CHARS).collect())
+    }
+
+    // Whether the Delete button of `UIState::ConfirmDelete` can be pressed.
+    // The typed text is compared ignoring case.
+    pub fn delete_allowed(&self, id: &str) -> bool {
+        self.delete_phrase(id)
+            .is_none_or(|phrase| phrase.to_lowercase() == self.delete_typed.text().to_lowercase())
+    }
+
+    // Whether a main-view action means anything in the focused panel. Lets
+    // the same key do different things in different panels.
+    fn applies(&self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::MoveUp | KeyAction::MoveDown | KeyAction::MoveMode => {
+                self.focus != Focus::BootOnce
+            }
+            KeyAction::ApplyAndBootOnce
+            | KeyAction::ApplyAtShutdown
+            | KeyAction::SwapDefault
+            | KeyAction::PinRest
+            | KeyAction::EditOrder => self.focus == Focus::Priority,
+            KeyAction::BootPrevious => self.focus == Focus::BootOnce,
+            KeyAction::DeleteEntry => !matches!(self.focus, Focus::Extra(_)),
+            KeyAction::DismissBanner => {
+                self.order_drift.is_some()
+                    || self.command_warnings.is_some()
+                    || self.suggested_profile.is_some()
+            }
+            KeyAction::Warnings => self.command_warnings.is_some(),
+            _ => true,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        match self.state {
+            UIState::Main => self.handle_main_key(key),
+
+            UIState::AskPassword => {
+                self.password_empty = false;
+                match key.code {
+                    KeyCode::Esc => {
+                        if self.pending_action == Action::ReadEntries {
+                            self.show_toast("Boot entries not read; refresh to ask again");
+                        }
+                        self.password.clear();
+                        self.password_retry = None;
+                        self.pending_action = Action::None;
+                        self.state = UIState::Main;
+                    }
+                    KeyCode::Tab => {
+                        self.show_password = !self.show_password;
+                    }
+                    // sudo would take the bare newline as a wrong password and
+                    // count it towards the faillock limit. Spaces are sent as
+                    // typed, so a password of only spaces still goes through.
+                    KeyCode::Enter if self.password.is_empty() => self.password_empty = true,
+                    KeyCode::Enter => {
+                        self.state = UIState::Processing;
+                    }
+                    _ => {
+                        self.password.handle_key(key);
+                    }
+                }
+            }
+
+            UIState::Processing => {
+                if key.code == KeyCode::Esc {
+                    self.cancel_job();
+                }
+            }
+
+            UIState::PasswordError => {
+                self.state = UIState::AskPassword;
+            }
+
+            UIState::ConfirmBootOnce => match key.code {
+                KeyCode::Esc => {
+                    self.pending_action = Action::None;
+                    self.state = UIState::Main;
+                }
+                KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
+                    self.boot_once_yes = !self.boot_once_yes;
+                }
+                KeyCode::Enter => {
+                    if self.boot_once_yes {
+                        self.password.clear();
+                        self.state = self.password_state();
+                    } else {
+                        self.pending_action = Action::None;
+                        self.state = UIState::Main;
+                    }
+                }
+                _ => {}
+            },
+
+            UIState::ConfirmInactive(ref id) => match key.code {
+                KeyCode::Left | KeyCode::BackTab => {
+                    self.inactive_choice = match self.inactive_choice {
+                        InactiveChoice::Activate => InactiveChoice::Cancel,
+                        InactiveChoice::Proceed => InactiveChoice::Activate,
+                        InactiveChoice::Cancel => InactiveChoice::Proceed,
+                    };
+                }
+                KeyCode::Right | KeyCode::Tab => {
+                    self.inactive_choice = match self.inactive_choice {
+                        InactiveChoice::Activate => InactiveChoice::Proceed,
+                        InactiveChoice::Proceed => InactiveChoice::Cancel,
+                        InactiveChoice::Cancel => InactiveChoice::Activate,
+                    };
+                }
+                KeyCode::Enter => match self.inactive_choice {
+                    InactiveChoice::Activate => {
+                        let action = std::mem::replace(&mut self.pending_action, Action::None);
+                        self.pending_action =
+                            Action::Sequence(vec![Action::Activate(id.clone()), action]);
+                        self.confirm_pending();
+                    }
+                    InactiveChoice::Proceed => self.confirm_pending(),
+                    InactiveChoice::Cancel => {
+                        self.pending_action = Action::None;
+                        self.state = UIState::Main;
+                    }
+                },
+                KeyCode::Esc => {
+                    self.pending_action = Action::None;
+                    self.state = UIState::Main;
+                }
+                _ => {}
+            },
+
+            UIState::WindowsFirst { .. } => match key.code {
+                KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                    self.windows_first_dismiss = !self.windows_first_dismiss;
+                }
+                KeyCode::Enter => {
+                    if self.windows_first_dismiss {
+                        self.dismiss_windows_first_warning();
+                    }
+                    self.confirm_writes();
+                }
+                KeyCode::Esc => {
+                    self.pending_action = Action::None;
+                    self.state = UIState::Main;
+                }
+                _ => {}
+            },
+
+            UIState::ChooseBootOnceMethod => match key.code {
+                KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                    self.boot_once_reorder = !self.boot_once_reorder;
+                }
+                KeyCode::Enter => {
+                    if self.boot_once_reorder {
+                        self.boot_once_by_reorder();
+                    }
+                    self.review_target();
+                }
+                KeyCode::Esc => {
+                    self.pending_action = Action::None;
+                    self.state = UIState::Main;
+                }
+                _ => {}
+            },
+
+            UIState::RestoreOrder(_) => match modal::yes_no_key(key.code, &mut self.restore_yes) {
+                Some(ButtonKey::Press(button)) => {
+                    let UIState::RestoreOrder(temporary) =
+                        std::mem::replace(&mut self.state, UIState::Main)
+                    else {
+                        return;
+                    };
+                    if button != 0 {
+                        self.keep_temporary_order();
+                        self.show_toast("Keeping the current boot order");
+                        return;
+                    }
+                    match self.restore_action(&temporary) {
+                        Ok(action) => {
+                            self.reorder_entries(&temporary.original);
+                            self.pending_action = action;
+                            self.review_pending();
+                        }
+                        Err(e) => self.show_invalid_order(e),
+                    }
+                }
+                // Asked again on the next run.
+                Some(_) => self.state = UIState::Main,
+                None => {}
+            },
+
+            UIState::DeferUnavailable(_) => {
+                match modal::yes_no_key(key.code, &mut self.apply_now_yes) {
+                    Some(ButtonKey::Press(0)) => {
+                        self.state = UIState::Main;
+                        let order = self.order_to_apply();
+                        self.set_order_and_review(&order);
+                    }
+                    Some(_) => {
+                        self.pending_action = Action::None;
+                        self.state = UIState::Main;
+                    }
+                    None => {}
+                }
+            }
+
+            UIState::Deferred => match modal::yes_no_key(key.code, &mut self.keep_deferred) {
+                Some(ButtonKey::Press(1)) => match self.read_only {
+                    Some(reason) => {
+                        self.state = UIState::Main;
+                        self.show_toast(format!("Read-only: {}", reason));
+                    }
+                    None => {
+                        self.pending_action = Action::CancelDeferred;
+                        self.confirm_writes();
+                    }
+                },
+                Some(_) => self.state = UIState::Main,
+                None => {}
+            },
+
+            UIState::FirmwareUpdate(ref next) => {
+                let next = next.clone();
+                match key.code {
+                    KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                        self.firmware_update_yes = !self.firmware_update_yes;
+                    }
+                    KeyCode::Enter if self.firmware_update_yes => {
+                        self.firmware_update_seen = true;
+                        match next {
+                            AfterFirmwareUpdate::Writes => self.confirm_writes(),
+                            AfterFirmwareUpdate::Countdown(target) => self.start_countdown(target),
+                            AfterFirmwareUpdate::RebootNow => self.perform_reboot(),
+                        }
+                    }
+                    KeyCode::Enter | KeyCode::Esc => {
+                        self.state = UIState::Main;
+                        match next {
+                            AfterFirmwareUpdate::Writes => self.pending_action = Action::None,
+                            AfterFirmwareUpdate::Countdown(target) if !target.changed => {
+                                self.show_toast("Not rebooting")
+                            }
+                            _ => self
+                                .show_toast("Not rebooting; the change is kept for the next boot"),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            UIState::ConfirmReboot(ref target) => {
+                // A zero timeout leaves no chance to pick another entry at
+                // boot; `t` sets one first and then reboots.
+                if self.timeout == Some(0)
+                    && matches!(key.code, KeyCode::Char('t') | KeyCode::Char('T'))
+                {
+                    self.reboot_after_timeout = Some(target.clone());
+                    self.pending_action = Action::SetTimeout(REBOOT_TIMEOUT);
+                    self.confirm_writes();
+                    return;
+                }
+                match modal::yes_no_key(key.code, &mut self.reboot_yes) {
+                    Some(ButtonKey::Press(0)) => self.start_countdown(target.clone()),
+                    Some(_) => {
+                        let target = target.clone();
+                        self.state = UIState::Main;
+                        self.reboot_postponed(&target);
+                    }
+                    None => {}
+                }
+            }
+
+            UIState::BootNextArmed(ref target) => match key.code {
+                KeyCode::Left | KeyCode::BackTab => {
+                    self.reboot_choice = match self.reboot_choice {
+                        RebootChoice::Now => RebootChoice::Later,
+                        RebootChoice::Countdown => RebootChoice::Now,
+                        RebootChoice::Later => RebootChoice::Countdown,
+                    };
+                }
+                KeyCode::Right | KeyCode::Tab => {
+                    self.reboot_choice = match self.reboot_choice {
+                        RebootChoice::Now => RebootChoice::Countdown,
+                        RebootChoice::Countdown => RebootChoice::Later,
+                        RebootChoice::Later => RebootChoice::Now,
+                    };
+                }
+                KeyCode::Enter => match self.reboot_choice {
+                    RebootChoice::Now => {
+                        if !self.warn_firmware_update(AfterFirmwareUpdate::RebootNow) {
+                            self.perform_reboot();
+                        }
+                    }
+                    RebootChoice::Countdown => {
+                        self.start_countdown(target.clone());
+                    }
+                    RebootChoice::Later => self.arm_for_later(),
+                },
+                KeyCode::Esc => self.arm_for_later(),
+                _ => {}
+            },
+
+            UIState::CountdownReboot { .. } => match key.code {
+                KeyCode::Esc => {
+                    if let UIState::CountdownReboot { target, .. } =
+                        std::mem::replace(&mut self.state, UIState::Main)
+                    {
+                        self.reboot_postponed(&target);
+                    }
+                }
+                KeyCode::Char('p') | KeyCode::Char('P') => self.toggle_countdown_pause(),
+                _ => {}
+            },
+
+            UIState::QuitConfirm => match modal::yes_no_key(key.code, &mut self.quit_yes) {
+                Some(ButtonKey::Press(0)) => self.quit_or_remind(),
+                Some(_) => self.state = UIState::Main,
+                None => {}
+            },
+
+            UIState::QuitBootNext(_) => {
+                match modal::button_key(key.code, self.quit_boot_next_choice, 3) {
+                    ButtonKey::Select(i) => self.quit_boot_next_choice = i,
+                    ButtonKey::Press(0) => self.should_quit = true,
+                    // Asks for the password only when sudo would.
+                    ButtonKey::Press(1) => {
+                        self.pending_action = Action::ClearBootNext;
+                        self.password.clear();
+                        self.state = self.expert_write_state();
+                    }
+                    ButtonKey::Press(_) | ButtonKey::Cancel => self.state = UIState::Main,
+                    ButtonKey::Ignored => {}
+                }
+            }
+
+            UIState::ConfirmDelete(ref id) => {
+                let id = id.clone();
+                // The name field takes the editing keys, `y` and `n`
+                // included; Tab still moves between the buttons.
+                if self.delete_phrase(&id).is_some() && self.delete_typed.handle_key(key) {
+                    return;
+                }
+                match modal::button_key(key.code, self.delete_choice, 2) {
+                    ButtonKey::Select(i) => self.delete_choice = i,
+                    // A placeholder has no variable for -B to delete; it only
+                    // leaves the draft order, which applying then writes.
+                    ButtonKey::Press(0)
+                        if self.delete_allowed(&id)
+                            && self.entries.iter().any(|e| e.id == id && e.unknown) =>
+                    {
+                        self.entries.retain(|e| e.id != id);
+                        self.draft.retain(|other| *other != id);
+                        self.clear_history();
+                        self.show_toast(format!(
+                            "Boot{} dropped from the boot order; apply to write it",
+                            id
+                        ));
+                        self.state = UIState::Main;
+                    }
+                    ButtonKey::Press(0) if self.delete_allowed(&id) => {
+                        self.pending_action = Action::Delete(id);
+                        self.password.clear();
+                        self.state = self.password_state();
+                    }
+                    // Disabled until the name matches.
+                    ButtonKey::Press(0) => {}
+                    ButtonKey::Press(_) | ButtonKey::Cancel => self.state = UIState::Main,
+                    ButtonKey::Ignored => {}
+                }
+            }
+
+            UIState::InstanceLocked(_) => match key.code {
+                KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
+                    self.read_only_yes = !self.read_only_yes;
+                }
+                KeyCode::Enter if self.read_only_yes => {
+                    self.read_only = Some(READ_ONLY_LOCKED);
+                    self.state = UIState::Main;
+                }
+                KeyCode::Enter | KeyCode::Esc => self.should_quit = true,
+                _ => {}
+            },
+
+            UIState::ProfilePicker => match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.profile_index = self.profile_index.saturating_sub(1);
+                }
+                KeyCode::Down | KeyCode::Char('j')
+                    if self.profile_index + 1 < self.config.profiles.len() =>
+                {
+                    self.profile_index += 1;
+                }
+                KeyCode::Enter => {
+                    let name = self
+                        .profile_names()
+                        .get(self.profile_index)
+                        .map(|n| n.to_string());
+                    match name {
+                        Some(name) => self.apply_profile(&name),
+                        None => self.state = UIState::Main,
+                    }
+                }
+                KeyCode::Esc => self.state = UIState::Main,
+                _ => {}
+            },
+
+            UIState::Welcome => {
+                self.saved_state.welcomed = true;
+                let _ = self.saved_state.save();
+                self.state = UIState::Main;
+            }
+
+            UIState::Details(_) | UIState::Warnings | UIState::Help => {
+                self.state = UIState::Main;
+            }
+
+            UIState::OrderModified { .. } => {
+                if let UIState::OrderModified { then, .. } =
+                    std::mem::replace(&mut self.state, UIState::Main)
+                {
+                    self.state = *then;
+                }
+            }
+
+            UIState::ErrorMessage(_) | UIState::NvramFull(_) => {
+                match modal::yes_no_key(key.code, &mut self.error_retry) {
+                    // Nothing to retry after a failed reboot or a rejected order.
+                    Some(ButtonKey::Press(0)) if !matches!(self.pending_action, Action::None) => {
+                        self.retry_pending()
+                    }
+                    Some(_) => {
+                        self.pending_action = Action::None;
+                        self.state = UIState::Main;
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    // While an entry is picked up, the movement keys carry it, Home and End
+    // take it to the top or bottom, Enter (or the move key again) drops it
+    // and Esc puts it back. Everything else waits.
+    fn handle_moving_key(&mut self, key: KeyEvent) {
+        let Some(moving) = &self.moving else {
+            return;
+        };
+        let panel = moving.panel;
+        let entries = self.panel_entries(panel);
+        let Some(from) = entries.iter().position(|e| e.id == moving.id) else {
+            self.moving = None;
+            return;
+        };
+        let last = entries.len() - 1;
+        let to = match key.code {
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Esc => return self.cancel_move(),
+            _ => match self.keymap.lookup(&key, |a| {
+                matches!(
+                    a,
+                    KeyAction::Up
+                        | KeyAction::Down
+                        | KeyAction::MoveUp
+                        | KeyAction::MoveDown
+                        | KeyAction::MoveMode
+                        | KeyAction::Confirm
+                )
+            }) {
+                Some(KeyAction::Up | KeyAction::MoveUp) => from.saturating_sub(1),
+                Some(KeyAction::Down | KeyAction::MoveDown) => (from + 1).min(last),
+                Some(KeyAction::MoveMode | KeyAction::Confirm) => return self.drop_move(),
+                _ => return,
+            },
+        };
+        let mut order = self.row_ids(panel);
+        let id = order.remove(from);
+        order.insert(to, id);
+        self.set_panel_order(panel, &order);
+        self.select_index(panel, to);
+    }
+
+    fn drop_move(&mut self) {
+        let Some(moving) = self.moving.take() else {
+            return;
+        };
+        if self.row_ids(moving.panel) != moving.original {
+            self.record_order(moving.panel, moving.original, Some(moving.id));
+        }
+    }
+
+    fn cancel_move(&mut self) {
+        let Some(moving) = self.moving.take() else {
+            return;
+        };
+        self.set_panel_order(moving.panel, &moving.original);
+        if let Some(i) = moving.original.iter().position(|id| *id == moving.id) {
+            self.select_index(moving.panel, i);
+        }
+    }
+
+    fn handle_main_key(&mut self, key: KeyEvent) {
+        // Back in the main view, a timeout-then-reboot was given up on.
+        self.reboot_after_timeout = None;
+        if self.moving.is_some() {
+            self.handle_moving_key(key);
+            return;
+        }
+        if self.config.type_ahead
+            && self.focus == Focus::BootOnce
+            && let KeyCode::Char(c) = key.code
+            && c.is_alphanumeric()
+            && !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            self.type_ahead(c, Instant::now());
+            return;
+        }
+
+        let Some(action) = self.keymap.lookup(&key, |a| self.applies(a)) else {
+            return;
+        };
+
+        if let Some(reason) = self.read_only
+            && matches!(
+                action,
+                KeyAction::MoveUp
+                    | KeyAction::MoveDown
+                    | KeyAction::MoveMode
+                    | KeyAction::SwapDefault
+                    | KeyAction::EditOrder
+                    | KeyAction::Undo
+                    | KeyAction::Redo
+                    | KeyAction::ResetOrder
+                    | KeyAction::Confirm
+                    | KeyAction::ApplyAndBootOnce
+                    | KeyAction::ApplyAtShutdown
+                    | KeyAction::BootPrevious
+                    | KeyAction::DeleteEntry
+                    | KeyAction::Profiles
+            )
+        {
+            self.show_toast(format!("Read-only: {}", reason));
+            return;
+        }
+
+        match action {
+            KeyAction::Quit => self.request_quit(),
+
+            KeyAction::NextPanel => self.focus_next(),
+
+            KeyAction::PrevPanel => self.focus_prev(),
+
+            KeyAction::Up => {
+                if let Some(i) = self.selected_index(self.focus)
+                    && i > 0
+                {
+                    self.select_index(self.focus, i - 1);
+                }
+            }
+
+            KeyAction::Down => {
+                if let Some(i) = self.selected_index(self.focus)
+                    && i + 1 < self.panel_entries(self.focus).len()
+                {
+                    self.select_index(self.focus, i + 1);
+                }
+            }
+
+            KeyAction::MoveUp => {
+                let focus = self.focus;
+                if let Some(i) = self.selected_index(focus)
+                    && i > 0
+                    && focus != Focus::BootOnce
+                {
+                    let mut order = self.row_ids(focus);
+                    self.record_reorder(focus, Some(order[i].clone()));
+                    self.mark_swapped(focus, i - 1);
+                    order.swap(i, i - 1);
+                    self.set_panel_order(focus, &order);
+                    self.select_index(focus, i - 1);
+                }
+            }
+
+            KeyAction::MoveDown => {
+                let focus = self.focus;
+                if let Some(i) = self.selected_index(focus)
+                    && i + 1 < self.panel_entries(focus).len()
+                    && focus != Focus::BootOnce
+                {
+                    let mut order = self.row_ids(focus);
+                    self.record_reorder(focus, Some(order[i].clone()));
+                    self.mark_swapped(focus, i + 1);
+                    order.swap(i, i + 1);
+                    self.set_panel_order(focus, &order);
+                    self.select_index(focus, i + 1);
+                }
+            }
+
+            KeyAction::MoveMode => {
+                let panel = self.focus;
+                if let Some(entry) = self.selected_entry(panel) {
+                    self.moving = Some(Moving {
+                        panel,
+                        id: entry.id.clone(),
+                        original: self.row_ids(panel),
+                    });
+                }
+            }
+
+            KeyAction::Confirm if !self.panel_entries(self.focus).is_empty() => {
+                self.pending_action = match self.focus {
+                    Focus::Priority => {
+                        let order = self.order_to_apply();
+                        if self.has_boot_order && order == self.boot_order {
+                            self.show_toast(
+                                "This order is already in effect; it applies from the next reboot",
+                            );
+                            return;
+                        }
+                        if self.pin_rest && self.is_dirty() && order == self.live_order {
+                            self.show_toast(
+                                "Nothing to apply with the rest pinned: move each entry that should change places",
+                            );
+                            return;
+                        }
+                        self.set_order_and_review(&order);
+                        return;
+                    }
+                    Focus::BootOnce => match self.selected_entry(Focus::BootOnce) {
+                        Some(e) if e.unknown => return self.show_toast(UNKNOWN_BOOT_ONCE),
+                        Some(e) => Action::BootOnce(e.id.clone()),
+                        None => return,
+                    },
+                    Focus::Extra(kind) => match self.extra_list(kind) {
+                        Some(list) => Action::SetExtraOrder(kind, list.draft_order()),
+                        None => return,
+                    },
+                };
+                self.review_pending();
+            }
+
+            KeyAction::ApplyAndBootOnce if self.is_focused(Focus::Priority) => {
+                let Some(e) = self.selected_entry(Focus::BootOnce) else {
+                    return;
+                };
+                if e.unknown {
+                    return self.show_toast(UNKNOWN_BOOT_ONCE);
+                }
+                let boot_once = Action::BootOnce(e.id.clone());
+                match self.set_order_action(&self.order_to_apply()) {
+                    Ok(set_order) => {
+                        self.pending_action = Action::Sequence(vec![set_order, boot_once]);
+                        self.review_pending();
+                    }
+                    Err(e) => self.show_invalid_order(e),
+                }
+            }
+
+            KeyAction::ApplyAtShutdown => self.defer_order(),
+
+            KeyAction::Reboot => self.request_reboot(),
+
+            KeyAction::DeferredChange => {
+                if self.deferred().is_some() {
+                    self.keep_deferred = true;
+                    self.state = UIState::Deferred;
+                } else {
+                    self.show_toast("No change is deferred to shutdown");
+                }
+            }
+
+            KeyAction::ToggleIds => {
+                self.show_ids = !self.show_ids;
+                self.saved_state.show_ids = Some(self.show_ids);
+                let _ = self.saved_state.save();
+            }
+
+            KeyAction::ToggleFirmwareEntries => {
+                let hide = !self.hiding_firmware_entries;
+                self.set_firmware_entries_hidden(hide);
+                if hide && self.hidden_entries().is_empty() {
+                    self.show_toast("No network, shell or diagnostic entries to hide");
+                }
+            }
+
+            KeyAction::ToggleGroups => {
+                self.grouped = !self.grouped;
+                self.saved_state.grouped = self.grouped;
+                let _ = self.saved_state.save();
+            }
+
+            // Only the working order changes; applying it is the usual Enter.
+            KeyAction::SwapDefault => match self.swap_default() {
+                Ok(name) => self.show_toast(format!("{} is now first", name)),
+                Err(e) => self.show_toast(e),
+            },
+
+            KeyAction::PinRest => {
+                self.pin_rest = !self.pin_rest;
+                self.show_toast(if self.pin_rest {
+                    "Pin the rest: only the entries you moved change places"
+                } else {
+                    "Pin the rest off: the whole order is applied"
+                });
+            }
+
+            KeyAction::Undo => {
+                let message = if self.step_history(true) {
+                    "Undone"
+                } else {
+                    "Nothing to undo"
+                };
+                self.show_toast(message);
+            }
+
+            KeyAction::Redo => {
+                let message = if self.step_history(false) {
+                    "Redone"
+                } else {
+                    "Nothing to redo"
+                };
+                self.show_toast(message);
+            }
+
+            KeyAction::ResetOrder => {
+                let message = if self.reset_order(self.focus) {
+                    "Back to the order in effect; undo brings the draft back"
+                } else {
+                    "Nothing to reset: the order is the one in effect"
+                };
+                self.show_toast(message);
+            }
+
+            KeyAction::Refresh if self.reads_need_root && !self.is_dirty() => self.read_with_sudo(),
+            KeyAction::Refresh => match self.refresh() {
+                Ok(()) if self.order_drift.is_some() => {
+                    self.show_toast("BootOrder changed since it was applied")
+                }
+                Ok(()) => self.show_toast("Boot entries refreshed"),
+                Err(e) => self.show_toast(e),
+            },
+
+            KeyAction::DismissBanner => self.dismiss_banner(),
+            KeyAction::EditOrder => self.order_to_edit = Some(self.order_text()),
+            KeyAction::Warnings => self.state = UIState::Warnings,
+
+            KeyAction::BootPrevious => match self.previous_boot.clone() {
+                Some(id) => {
+                    self.pending_action = Action::BootOnce(id);
+                    self.review_pending();
+                }
+                None => self.show_toast("No previous boot recorded"),
+            },
+
+            KeyAction::Profiles => {
+                if self.config.profiles.is_empty() {
+                    self.show_toast("No profiles configured; add [profiles.<name>] to the config");
+                } else {
+                    self.profile_index = self.profile_index.min(self.config.profiles.len() - 1);
+                    self.state = UIState::ProfilePicker;
+                }
+            }
+
+            // Always confirmed, expert mode or not.
+            KeyAction::DeleteEntry => {
+                if let Some(id) = self.selected_entry(self.focus).map(|e| e.id.clone()) {
+                    self.delete_choice = 1;
+                    self.delete_typed.clear();
+                    self.state = UIState::ConfirmDelete(id);
+                }
+            }
+
+            KeyAction::Details => {
+                if let Some(e) = self.selected_entry(self.focus) {
+                    self.state = UIState::Details(e.clone());
+                }
+            }
+
+            KeyAction::Help => {
+                self.state = UIState::Help;
+            }
+
+            _ => {}
+        }
+    }
+}
+
+// Builds apps from made-up entries for the tests of this and other
+// modules; nothing here runs efibootmgr.
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use crossterm::event::KeyEventKind;
+
+    pub fn entry(id: &str, name: &str) -> BootEntry {
+        BootEntry {
+            id: id.to_string(),
+            name: name.to_string(),
+            active: true,
+            device_path: String::new(),
+            unknown: false,
+        }
+    }
+
+    // Fedora (running), Windows and a USB stick, in that order.
+    pub fn entries() -> Vec<BootEntry> {
+        vec![
+            entry("0001", "Fedora"),
+            entry("0002", "Windows Boot Manager"),
+            entry("0003", "UEFI: USB"),
+        ]
+    }
+
+    pub fn app_with(config: Config, entries: Vec<BootEntry>, order: &[&str]) -> App {
+        let status = BootStatus {
+            current: entries.first().map(|e| e.id.clone()),
+            order: order.iter().map(|id| id.to_string()).collect(),
+            ..BootStatus::default()
+        };
+        let keymap = Keymap::new(&config.keys).expect("valid keys");
+        let state = State {
+            welcomed: true,
+            ..State::default()
+        };
+        App::new(config, keymap, state, entries, status, Vec::new())
+    }
+
+    pub fn app() -> App {
+        app_with(Config::default(), entries(), &["0001", "0002", "0003"])
+    }
+
+    pub fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Press)
+    }
+
+    pub fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            app.handle_key(key(KeyCode::Char(c)));
+        }
+    }
+
+    // The Priority panel's rows, as IDs.
+    pub fn order(app: &App) -> Vec<&str> {
+        app.rows().iter().map(|e| e.id.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn an_unchanged_order_has_no_shifts() {
+        let order = ["0001", "0002", "0003"];
+        assert_eq!(position_shifts(&ids(&order), &ids(&order)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn a_swap_shifts_both_entries() {
+        let shifts = position_shifts(
+            &ids(&["0001", "0002", "0003"]),
+            &ids(&["0002", "0001", "0003"]),
+        );
+        assert_eq!(shifts, [1, -1, 0]);
+    }
+
+    #[test]
+    fn moving_to_the_top_shifts_the_ones_it_passed() {
+        let shifts = position_shifts(
+            &ids(&["0001", "0002", "0003", "0004"]),
+            &ids(&["0004", "0001", "0002", "0003"]),
+        );
+        assert_eq!(shifts, [3, -1, -1, -1]);
+    }
+
+    #[test]
+    fn a_reversed_order_shifts_by_distance() {
+        let shifts = position_shifts(
+            &ids(&["0001", "0002", "0003"]),
+            &ids(&["0003", "0002", "0001"]),
+        );
+        assert_eq!(shifts, [2, 0, -2]);
+    }
+
+    #[test]
+    fn uppercase_keys_work_in_the_main_view() {
+        let mut app = app();
+        press(&mut app, "J");
+        assert_eq!(app.selected_entry(Focus::Priority).unwrap().id, "0002");
+        press(&mut app, "U");
+        assert_eq!(order(&app), ["0002", "0001", "0003"]);
+    }
+
+    #[test]
+    fn the_password_field_keeps_case() {
+        let mut app = app();
+        app.state = UIState::AskPassword;
+        press(&mut app, "QjK");
+        assert_eq!(app.password.text(), "QjK");
+        assert!(!app.should_quit);
+        assert!(matches!(app.state, UIState::AskPassword));
+    }
+
+    fn focus_after(app: &mut App, code: KeyCode) -> Focus {
+        app.handle_key(key(code));
+        app.focus
+    }
+
+    #[test]
+    fn tab_cycles_two_panels_both_ways() {
+        let mut app = app();
+        assert_eq!(focus_after(&mut app, KeyCode::Tab), Focus::BootOnce);
+        assert_eq!(focus_after(&mut app, KeyCode::Tab), Focus::Priority);
+        assert_eq!(focus_after(&mut app, KeyCode::BackTab), Focus::BootOnce);
+        assert_eq!(focus_after(&mut app, KeyCode::BackTab), Focus::Priority);
+    }
+
+    #[test]
+    fn tab_cycles_three_panels_both_ways() {
+        let mut app = app();
+        let drivers = vec![entry("0000", "Some driver")];
+        app.extra_lists = vec![EntryList::new(EntryKind::Driver, drivers)];
+        let driver = Focus::Extra(EntryKind::Driver);
+        assert_eq!(focus_after(&mut app, KeyCode::Tab), Focus::BootOnce);
+        assert_eq!(focus_after(&mut app, KeyCode::Tab), driver);
+        assert_eq!(focus_after(&mut app, KeyCode::Tab), Focus::Priority);
+        assert_eq!(focus_after(&mut app, KeyCode::BackTab), driver);
+        assert_eq!(focus_after(&mut app, KeyCode::BackTab), Focus::BootOnce);
+    }
+
+    #[test]
+    fn new_entries_count_as_unmoved() {
+        let shifts = position_shifts(&ids(&["0001"]), &ids(&["0009", "0001"]));
+        assert_eq!(shifts, [0, -1]);
+    }
+
+    fn selected_id(app: &App, panel: Focus) -> Option<&str> {
+        app.selected_entry(panel).map(|e| e.id.as_str())
+    }
+
+    #[test]
+    fn a_deleted_selection_falls_back_to_the_same_row() {
+        let mut app = app();
+        app.select_index(Focus::Priority, 1);
+        app.entries.retain(|e| e.id != "0002");
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0003"));
+
+        // With the last row gone, the one above it takes over.
+        app.select_index(Focus::Priority, 1);
+        app.entries.retain(|e| e.id != "0003");
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0001"));
+    }
+
+    #[test]
+    fn the_selection_follows_its_entry_through_a_refresh() {
+        let mut app = app();
+        app.select_index(Focus::Priority, 0);
+        app.select_index(Focus::BootOnce, 1);
+        let status = BootStatus {
+            order: ids(&["0003", "0002", "0001"]),
+            ..BootStatus::default()
+        };
+        app.take_entries(entries(), status);
+        assert_eq!(order(&app), ["0003", "0002", "0001"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0001"));
+        assert_eq!(app.selected_index(Focus::Priority), Some(2));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+    }
+
+    #[test]
+    fn an_empty_list_has_no_selection() {
+        let mut app = app();
+        app.select_index(Focus::Priority, 2);
+        app.entries.clear();
+        assert_eq!(app.selected_index(Focus::Priority), None);
+        assert!(app.selected_entry(Focus::Priority).is_none());
+        // Keys on the empty list don't panic.
+        press(&mut app, "jkud");
+        assert!(app.selected_entry(Focus::Priority).is_none());
+    }
+
+    #[test]
+    fn without_a_boot_order_nothing_counts_as_changed() {
+        let mut app = app_with(Config::default(), entries(), &[]);
+        assert!(!app.has_boot_order);
+        assert!(!app.is_dirty());
+        assert!(app.moved_offsets().iter().all(|&shift| shift == 0));
+
+        press(&mut app, "jd");
+        assert!(app.is_dirty());
+        assert_eq!(app.order_to_apply(), ids(&["0001", "0003", "0002"]));
+    }
+
+    // Knows one partition; every other GUID is gone.
+    struct OnePartition(std::cell::Cell<usize>);
+
+    impl PartitionResolver for OnePartition {
+        fn resolve(&self, guid: &str) -> PartitionStatus {
+            self.0.set(self.0.get() + 1);
+            if guid != "8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b" {
+                return PartitionStatus::Missing;
+            }
+            PartitionStatus::Found(partition::PartitionInfo {
+                device: "nvme0n1p1".to_string(),
+                size: None,
+                fstype: Some("vfat".to_string()),
+                mountpoint: None,
+            })
+        }
+    }
+
+    #[test]
+    fn partitions_are_resolved_once_per_guid() {
+        let on = |guid: &str| format!("HD(1,GPT,{},0x800,0x100000)/File(\\EFI\\x.efi)", guid);
+        let mut entries = entries();
+        entries[0].device_path = on("8F7A2C1E-5B3D-4E6F-9A0B-1C2D3E4F5A6B");
+        entries[1].device_path = on("8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b");
+        entries[2].device_path = on("00000000-0000-0000-0000-000000000000");
+        let mut app = app_with(Config::default(), entries, &["0001", "0002", "0003"]);
+        let resolver = OnePartition(std::cell::Cell::new(0));
+        app.resolve_partitions(&resolver);
+
+        assert_eq!(resolver.0.get(), 2);
+        assert!(app.device_state(&app.entries[0]) == DeviceState::Present);
+        assert_eq!(app.group_of(&app.entries[1]), "nvme0n1");
+        // A USB stick that isn't there is unplugged rather than gone.
+        assert!(app.device_state(&app.entries[2]) == DeviceState::Unplugged);
+        let mut old = entry("0005", "Old Linux");
+        old.device_path = on("00000000-0000-0000-0000-000000000000");
+        assert!(app.device_state(&old) == DeviceState::Missing);
+        // Without a path there is nothing to look up.
+        assert!(app.partition_status(&entry("0004", "Shell")).is_none());
+    }
+
+    #[test]
+    fn short_lists_never_scroll() {
+        assert_eq!(ensure_visible(3, None, 0, 10), 0);
+        assert_eq!(ensure_visible(0, Some(0), 1, 10), 0);
+        // Exactly fits.
+        assert_eq!(ensure_visible(0, Some(9), 10, 10), 0);
+        assert_eq!(ensure_visible(5, Some(0), 10, 10), 0);
+    }
+
+    #[test]
+    fn long_lists_keep_a_row_around_the_selection() {
+        // Three times the panel height.
+        assert_eq!(ensure_visible(0, Some(0), 30, 10), 0);
+        assert_eq!(ensure_visible(0, Some(8), 30, 10), 0);
+        assert_eq!(ensure_visible(0, Some(9), 30, 10), 1);
+        assert_eq!(ensure_visible(10, Some(15), 30, 10), 10);
+        assert_eq!(ensure_visible(10, Some(10), 30, 10), 9);
+        assert_eq!(ensure_visible(0, Some(29), 30, 10), 20);
+        // The offset never goes past the end, even with no selection.
+        assert_eq!(ensure_visible(25, None, 30, 10), 20);
+        // A one-row panel has no room for a margin.
+        assert_eq!(ensure_visible(0, Some(5), 30, 1), 5);
+    }
+
+    #[test]
+    fn each_panel_keeps_its_scroll_offset() {
+        let many: Vec<BootEntry> = (1..=30)
+            .map(|i| entry(&format!("{:04X}", i), "Entry"))
+            .collect();
+        let mut app = app_with(Config::default(), many, &[]);
+        app.select_index(Focus::Priority, 20);
+        app.scroll_to_selection(Focus::Priority, 10);
+        assert_eq!(app.list_state(Focus::Priority).offset(), 12);
+
+        app.handle_key(key(KeyCode::Tab));
+        app.scroll_to_selection(Focus::BootOnce, 10);
+        assert_eq!(app.list_state(Focus::BootOnce).offset(), 0);
+        app.handle_key(key(KeyCode::Tab));
+        app.scroll_to_selection(Focus::Priority, 10);
+        assert_eq!(app.list_state(Focus::Priority).offset(), 12);
+    }
+
+    #[test]
+    fn nothing_written_says_so() {
+        assert_eq!(app().exit_summary(), ["no changes made"]);
+    }
+
+    #[test]
+    fn repeated_orders_collapse_into_first_and_last() {
+        let mut app = app();
+        app.changes = vec![
+            Change::Order {
+                kind: None,
+                old: ids(&["0001", "0002", "0003"]),
+                new: ids(&["0002", "0001", "0003"]),
+            },
+            Change::BootNext("0003".to_string()),
+            Change::Order {
+                kind: None,
+                old: ids(&["0002", "0001", "0003"]),
+                new: ids(&["0003", "0002", "0001"]),
+            },
+            Change::Order {
+                kind: Some(EntryKind::Driver),
+                old: ids(&["0000", "0001"]),
+                new: ids(&["0001", "0000"]),
+            },
+        ];
+        assert_eq!(
+            app.exit_summary(),
+            [
+                "BootOrder changed from Fedora, Windows Boot Manager, UEFI: USB \
+                 to UEFI: USB, Windows Boot Manager, Fedora",
+                "DriverOrder changed from 0000,0001 to 0001,0000",
+                "BootNext set to UEFI: USB",
+            ]
+        );
+    }
+
+    #[test]
+    fn every_kind_of_change_is_summarised() {
+        let mut app = app();
+        app.changes = vec![
+            Change::Timeout(5),
+            Change::Deactivated("0002".to_string()),
+            Change::Activated("0002".to_string()),
+            Change::Deleted("Old Linux".to_string()),
+            Change::Deferred(ids(&["0002", "0001"])),
+            Change::DeferralCancelled,
+            Change::BootNextCleared,
+            Change::BootNext("00FF".to_string()),
+        ];
+        assert_eq!(
+            app.exit_summary(),
+            [
+                "Timeout set to 5s",
+                "deactivated Windows Boot Manager",
+                "activated Windows Boot Manager",
+                "deleted Old Linux",
+                "BootOrder deferred to the next shutdown: Windows Boot Manager, Fedora",
+                "deferred change cancelled",
+                "BootNext cleared",
+                "BootNext set to Boot00FF",
+            ]
+        );
+    }
+
+    #[test]
+    fn boot_to_reaches_inactive_entries_outside_the_order() {
+        let mut entries = entries();
+        let mut recovery = entry("0004", "Recovery");
+        recovery.active = false;
+        entries.push(recovery);
+        let mut app = app_with(Config::default(), entries, &["0001", "0002", "0003"]);
+        assert_eq!(app.panel_entries(Focus::BootOnce).len(), 4);
+
+        app.handle_key(key(KeyCode::Tab));
+        app.select_index(Focus::BootOnce, 3);
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::ConfirmInactive(ref id) if id == "0004"));
+
+        // Proceed anyway, without activating it.
+        app.handle_key(key(KeyCode::Tab));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::ConfirmBootOnce));
+        assert!(app.pending_action == Action::BootOnce("0004".to_string()));
+        let (args, change) = app.command_for(&app.pending_action);
+        assert!(args.ends_with(&ids(&["-n", "0004"])), "{:?}", args);
+        assert!(matches!(change, Change::BootNext(ref id) if id == "0004"));
+    }
+
+    #[test]
+    fn auth_prompts_are_told_from_errors() {
+        assert!(is_auth_prompt("Please touch the device."));
+        assert!(is_auth_prompt(
+            "Place your finger on the fingerprint reader"
+        ));
+        assert!(is_auth_prompt("Enter PIN for 'YubiKey':"));
+        assert!(is_auth_prompt("Insert your security key"));
+        assert!(!is_auth_prompt("sudo: 3 incorrect password attempts"));
+        // Whole words only.
+        assert!(!is_auth_prompt("Sorry, try again (spinning)."));
+        assert!(!is_auth_prompt("efibootmgr: untouched"));
+    }
+
+    fn grouped_app() -> App {
+        let on_disk = "HD(1,GPT,8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b,0x800,0x1000)";
+        let mut entries = vec![
+            entry("0001", "Fedora"),
+            entry("0002", "Windows Boot Manager"),
+            entry("0003", "UEFI: USB"),
+            entry("0004", "PXE"),
+            entry("0005", "Shell"),
+        ];
+        entries[0].device_path = on_disk.to_string();
+        entries[1].device_path = on_disk.to_string();
+        entries[3].device_path = "PciRoot(0x0)/MAC(001122334455,0)".to_string();
+        let mut app = app_with(
+            Config::default(),
+            entries,
+            &["0001", "0002", "0003", "0004", "0005"],
+        );
+        app.resolve_partitions(&OnePartition(std::cell::Cell::new(0)));
+        press(&mut app, "G");
+        app
+    }
+
+    #[test]
+    fn groups_start_a_header_per_run() {
+        let app = grouped_app();
+        assert!(app.grouped);
+        assert_eq!(
+            app.group_headers(Focus::Priority),
+            [
+                Some("nvme0n1".to_string()),
+                None,
+                Some("USB / removable".to_string()),
+                Some("Network".to_string()),
+                Some("Other".to_string()),
+            ]
+        );
+        // Boot To is never grouped.
+        assert!(
+            app.group_headers(Focus::BootOnce)
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn display_rows_count_the_headers_above() {
+        let app = grouped_app();
+        let headers = app.group_headers(Focus::Priority);
+        let rows: Vec<usize> = (0..5).map(|i| display_row(&headers, i)).collect();
+        assert_eq!(rows, [1, 2, 4, 6, 8]);
+        assert_eq!(display_row(&[None, None], 1), 1);
+    }
+
+    #[test]
+    fn moving_across_a_header_swaps_the_flat_order() {
+        let mut app = grouped_app();
+        press(&mut app, "jd");
+        assert_eq!(order(&app), ["0001", "0003", "0002", "0004", "0005"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0002"));
+        assert_eq!(
+            app.group_headers(Focus::Priority)[..3],
+            [
+                Some("nvme0n1".to_string()),
+                Some("USB / removable".to_string()),
+                Some("nvme0n1".to_string()),
+            ]
+        );
+
+        // Selection steps over headers: one key press, one entry.
+        press(&mut app, "j");
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0004"));
+        press(&mut app, "u");
+        assert_eq!(order(&app), ["0001", "0003", "0004", "0002", "0005"]);
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0001", "0003", "0004", "0002", "0005"])
+        );
+    }
+
+    #[test]
+    fn scrolling_counts_header_rows() {
+        let mut app = grouped_app();
+        app.select_index(Focus::Priority, 4);
+        // Nine rows with the headers; the last entry is the last row.
+        app.scroll_to_selection(Focus::Priority, 4);
+        assert_eq!(app.list_state(Focus::Priority).offset(), 5);
+    }
+
+    #[test]
+    fn boot_to_starts_past_the_running_entry() {
+        let entries = entries();
+        let start =
+            |setting, current, last_used| initial_boot_to(&entries, setting, current, last_used);
+        assert_eq!(start(BootToInitial::First, Some("0001"), None), 0);
+        assert_eq!(start(BootToInitial::FirstNonCurrent, Some("0001"), None), 1);
+        assert_eq!(start(BootToInitial::FirstNonCurrent, Some("0002"), None), 0);
+        assert_eq!(start(BootToInitial::FirstNonCurrent, None, None), 0);
+        assert_eq!(
+            start(BootToInitial::LastUsed, Some("0001"), Some("0003")),
+            2
+        );
+        // A last-used entry that is gone falls back to the first other one.
+        assert_eq!(
+            start(BootToInitial::LastUsed, Some("0001"), Some("0009")),
+            1
+        );
+    }
+
+    #[test]
+    fn boot_to_with_nothing_else_starts_at_the_top() {
+        let single = [entry("0001", "Fedora")];
+        for setting in [BootToInitial::FirstNonCurrent, BootToInitial::LastUsed] {
+            assert_eq!(initial_boot_to(&single, setting, Some("0001"), None), 0);
+        }
+        assert_eq!(
+            initial_boot_to(&[], BootToInitial::FirstNonCurrent, None, None),
+            0
+        );
+
+        // Every other entry a placeholder: nothing better than the first.
+        let entries = [entry("0001", "Fedora"), BootEntry::placeholder("0002")];
+        let start = initial_boot_to(&entries, BootToInitial::FirstNonCurrent, Some("0001"), None);
+        assert_eq!(start, 0);
+    }
+
+    #[test]
+    fn the_app_starts_boot_to_on_the_configured_entry() {
+        let app = app();
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+        let config = Config {
+            boot_to_initial: BootToInitial::First,
+            ..Config::default()
+        };
+        let app = app_with(config, entries(), &["0001", "0002", "0003"]);
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0001"));
+    }
+
+    fn set_order(app: &App, order: &[&str]) -> Result<Vec<String>, String> {
+        match app.set_order_action(&ids(order))? {
+            Action::SetOrder(order) => Ok(EntryId::to_strings(&order)),
+            _ => Err("not a SetOrder".to_string()),
+        }
+    }
+
+    #[test]
+    fn set_order_normalizes_ids() {
+        let app = app();
+        assert_eq!(
+            set_order(&app, &["0003", "0001", "0002"]),
+            Ok(ids(&["0003", "0001", "0002"]))
+        );
+        // Lowercase hex from a profile or an edited order is written in
+        // uppercase.
+        let app = app_with(
+            Config::default(),
+            vec![entry("000A", "Fedora"), entry("0001", "Windows")],
+            &["000A", "0001"],
+        );
+        assert_eq!(
+            set_order(&app, &["0001", "000a"]),
+            Ok(ids(&["0001", "000A"]))
+        );
+    }
+
+    #[test]
+    fn set_order_names_every_bad_id() {
+        let app = app();
+        assert_eq!(
+            set_order(&app, &["0001", "01", "0001", "0009", "zzzz"]),
+            Err(
+                "Invalid boot order: malformed '01', duplicate 0001, unknown 0009, \
+                 malformed 'zzzz'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn an_invalid_order_stops_before_the_password() {
+        // As if a parser bug let a bad ID through.
+        let mut entries = entries();
+        entries[2].id = "3".to_string();
+        let mut app = app_with(Config::default(), entries, &["0001", "0002"]);
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::ErrorMessage(ref e) if e.contains("malformed '3'")));
+        assert!(app.pending_action == Action::None);
+    }
+
+    fn echoed(order: &str) -> String {
+        format!(
+            "BootCurrent: 0001\nBootOrder: {}\nBoot0001* Fedora\n",
+            order
+        )
+    }
+
+    #[test]
+    fn a_kept_order_verifies_quietly() {
+        let mut app = app();
+        let requested = ids(&["0002", "0001", "0003"]);
+        let actual = app.verify_order(requested.clone(), &echoed("0002,0001,0003"));
+        assert_eq!(actual, requested);
+        assert!(app.modified_order.is_none());
+    }
+
+    #[test]
+    fn a_reordered_result_is_reported() {
+        let mut app = app();
+        app.changes.push(Change::Order {
+            kind: None,
+            old: ids(&["0001", "0002", "0003"]),
+            new: ids(&["0002", "0001", "0003"]),
+        });
+        let requested = ids(&["0002", "0001", "0003"]);
+        let actual = app.verify_order(requested.clone(), &echoed("0001,0002,0003"));
+        assert_eq!(actual, ids(&["0001", "0002", "0003"]));
+        assert_eq!(app.modified_order, Some((requested, actual.clone())));
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        // The exit summary tells what the firmware kept, not what was asked.
+        assert!(matches!(app.changes.last(), Some(Change::Order { new, .. }) if *new == actual));
+    }
+
+    #[test]
+    fn a_truncated_result_is_reported() {
+        let mut app = app();
+        let requested = ids(&["0003", "0002", "0001"]);
+        let actual = app.verify_order(requested.clone(), &echoed("0003"));
+        assert_eq!(actual, ids(&["0003"]));
+        assert_eq!(app.modified_order, Some((requested, actual)));
+        // Entries the firmware dropped go to the end, by ID.
+        assert_eq!(order(&app), ["0003", "0001", "0002"]);
+    }
+
+    fn esc(app: &mut App) {
+        app.handle_key(key(KeyCode::Esc));
+    }
+
+    #[test]
+    fn esc_quits_a_clean_session() {
+        let mut app = app();
+        esc(&mut app);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn esc_with_changes_asks_and_esc_again_stays() {
+        let mut app = app();
+        press(&mut app, "d");
+        esc(&mut app);
+        assert!(matches!(app.state, UIState::QuitConfirm));
+        esc(&mut app);
+        assert!(matches!(app.state, UIState::Main));
+        assert!(!app.should_quit);
+        assert!(app.is_dirty());
+    }
+
+    #[test]
+    fn esc_goes_back_one_level() {
+        let mut app = app();
+        press(&mut app, "?");
+        assert!(matches!(app.state, UIState::Help));
+        esc(&mut app);
+        assert!(matches!(app.state, UIState::Main));
+
+        // An error after a write goes back to the list, not to the password.
+        app.state = UIState::ErrorMessage("efibootmgr failed".to_string());
+        esc(&mut app);
+        assert!(matches!(app.state, UIState::Main));
+        assert!(!app.should_quit);
+
+        app.state = UIState::PasswordError;
+        esc(&mut app);
+        assert!(!matches!(app.state, UIState::PasswordError));
+        assert!(!app.should_quit);
+    }
+
+    fn safe_app() -> App {
+        let config = Config {
+            safe_apply: true,
+            ..Config::default()
+        };
+        app_with(config, entries(), &["0001", "0002", "0003"])
+    }
+
+    #[test]
+    fn safe_apply_arms_the_old_default() {
+        let mut app = safe_app();
+        let new = ids(&["0002", "0001", "0003"]);
+        let action = app.order_action(&new).unwrap();
+        assert!(
+            action
+                == Action::Sequence(vec![
+                    app.set_order_action(&new).unwrap(),
+                    Action::BootOnce("0001".to_string()),
+                ])
+        );
+        // The safety net goes with the order write, and only with it.
+        let Action::Sequence(steps) = action else {
+            unreachable!()
+        };
+        let prepared = app.prepare_step(steps[1].clone(), VecDeque::new());
+        assert!(prepared.safety.is_none());
+        let prepared = app.prepare_step(steps[0].clone(), VecDeque::new());
+        let safety = prepared.safety.unwrap();
+        assert_eq!(safety.old_order, ids(&["0001", "0002", "0003"]));
+        assert_eq!(safety.new_order, new);
+    }
+
+    #[test]
+    fn safe_apply_is_left_out_when_it_has_nothing_to_guard() {
+        let mut app = safe_app();
+        // The order already in effect.
+        let same = ids(&["0001", "0002", "0003"]);
+        assert!(matches!(app.order_action(&same), Ok(Action::SetOrder(_))));
+
+        // Going back to the saved order is the revert itself.
+        app.saved_state.safe_apply = Some(SafeApply {
+            old_order: ids(&["0003", "0001", "0002"]),
+            new_order: same,
+            deadline: u64::MAX,
+        });
+        let back = ids(&["0003", "0001", "0002"]);
+        assert!(matches!(app.order_action(&back), Ok(Action::SetOrder(_))));
+
+        let mut plain = testing::app();
+        let new = ids(&["0002", "0001", "0003"]);
+        assert!(matches!(plain.order_action(&new), Ok(Action::SetOrder(_))));
+    }
+
+    #[test]
+    fn revert_needs_a_pending_safe_apply() {
+        let mut app = safe_app();
+        assert!(app.revert_action().is_err());
+        app.saved_state.safe_apply = Some(SafeApply {
+            old_order: ids(&["0002", "0001", "0003"]),
+            new_order: ids(&["0001", "0002", "0003"]),
+            deadline: u64::MAX,
+        });
+        assert!(
+            app.revert_action().unwrap()
+                == app
+                    .set_order_action(&ids(&["0002", "0001", "0003"]))
+                    .unwrap()
+        );
+    }
+
+    #[test]
+    fn an_expired_safe_apply_queues_the_revert() {
+        let mut app = safe_app();
+        app.saved_state.safe_apply = Some(SafeApply {
+            old_order: ids(&["0002", "0001", "0003"]),
+            new_order: ids(&["0001", "0002", "0003"]),
+            deadline: u64::MAX,
+        });
+        assert!(!app.check_safe_apply());
+        assert!(app.pending_action == Action::None);
+
+        app.saved_state.safe_apply.as_mut().unwrap().deadline = state::unix_now() - 1;
+        assert!(app.check_safe_apply());
+        assert_eq!(order(&app), ["0002", "0001", "0003"]);
+        assert!(
+            app.pending_action
+                == app
+                    .set_order_action(&ids(&["0002", "0001", "0003"]))
+                    .unwrap()
+        );
+        assert!(!matches!(app.state, UIState::Main));
+    }
+
+    fn ctrl(app: &mut App, c: char) {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_order_and_selection() {
+        let mut app = app();
+        // Fedora down twice, then USB (now second) to the top.
+        press(&mut app, "dd");
+        assert_eq!(order(&app), ["0002", "0003", "0001"]);
+        press(&mut app, "ku");
+        assert_eq!(order(&app), ["0003", "0002", "0001"]);
+
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0002", "0003", "0001"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0003"));
+        ctrl(&mut app, 'z');
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0001"));
+        assert!(!app.is_dirty());
+        // Nothing further back.
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+
+        ctrl(&mut app, 'y');
+        ctrl(&mut app, 'y');
+        ctrl(&mut app, 'y');
+        assert_eq!(order(&app), ["0003", "0002", "0001"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0003"));
+    }
+
+    #[test]
+    fn undo_follows_the_panel_it_happened_in() {
+        let mut app = app();
+        press(&mut app, "jd");
+        app.handle_key(key(KeyCode::Tab));
+        assert_eq!(app.focus, Focus::BootOnce);
+        ctrl(&mut app, 'z');
+        assert_eq!(app.focus, Focus::Priority);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0002"));
+    }
+
+    #[test]
+    fn a_new_move_drops_the_redo_steps() {
+        let mut app = app();
+        press(&mut app, "d");
+        ctrl(&mut app, 'z');
+        press(&mut app, "jd");
+        ctrl(&mut app, 'y');
+        assert_eq!(order(&app), ["0001", "0003", "0002"]);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut app = app();
+        for _ in 0..UNDO_LIMIT + 10 {
+            press(&mut app, "d");
+            press(&mut app, "u");
+        }
+        assert_eq!(app.undo.len(), UNDO_LIMIT);
+    }
+
+    fn type_ahead_app() -> App {
+        let config = Config {
+            type_ahead: true,
+            ..Config::default()
+        };
+        let entries = vec![
+            entry("0001", "Fedora"),
+            entry("0002", "Windows Boot Manager"),
+            entry("0003", "Fedora Rescue"),
+            entry("0004", "Firmware Settings"),
+        ];
+        let mut app = app_with(config, entries, &["0001", "0002", "0003", "0004"]);
+        app.focus = Focus::BootOnce;
+        app
+    }
+
+    fn ms(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn type_ahead_extends_the_prefix_while_typing() {
+        let mut app = type_ahead_app();
+        let start = Instant::now();
+        assert!(app.type_ahead('f', start));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0001"));
+        assert!(app.type_ahead('i', ms(start, 300)));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0004"));
+    }
+
+    #[test]
+    fn type_ahead_starts_over_after_a_pause() {
+        let mut app = type_ahead_app();
+        let start = Instant::now();
+        app.type_ahead('f', start);
+        app.type_ahead('e', ms(start, 600));
+        // 700 ms after the last key, "w" is a new search, not "few".
+        assert!(app.type_ahead('w', ms(start, 1300)));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+        // Each key restarts the clock.
+        app.type_ahead('f', ms(start, 2000));
+        app.type_ahead('e', ms(start, 2650));
+        app.type_ahead('d', ms(start, 3300));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0001"));
+    }
+
+    #[test]
+    fn type_ahead_recovers_from_a_mistyped_letter() {
+        let mut app = type_ahead_app();
+        let start = Instant::now();
+        app.type_ahead('f', start);
+        // No "fw"; "w" on its own finds Windows.
+        assert!(app.type_ahead('W', ms(start, 100)));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+        // Nothing starts with "x": the selection stays.
+        assert!(!app.type_ahead('x', ms(start, 200)));
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+    }
+
+    #[test]
+    fn type_ahead_only_takes_keys_in_boot_to() {
+        let mut app = type_ahead_app();
+        // 'q' would quit, 'j' would move down.
+        press(&mut app, "fq");
+        assert!(!app.should_quit);
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0001"));
+        app.focus = Focus::Priority;
+        press(&mut app, "j");
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0002"));
+
+        let mut off = app_with(Config::default(), entries(), &["0001", "0002", "0003"]);
+        off.focus = Focus::BootOnce;
+        off.select_index(Focus::BootOnce, 0);
+        press(&mut off, "w");
+        assert_eq!(selected_id(&off, Focus::BootOnce), Some("0001"));
+    }
+
+    fn pinned(original: &str, working: &str, moved: &str) -> String {
+        let split = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        pin_untouched(&split(original), &split(working), &split(moved)).join(" ")
+    }
+
+    #[test]
+    fn pinning_with_nothing_moved_keeps_the_original() {
+        assert_eq!(pinned("A B C D", "D C B A", ""), "A B C D");
+    }
+
+    #[test]
+    fn pinning_swaps_entries_across_an_untouched_block() {
+        assert_eq!(pinned("A B C D E", "D B C A E", "A D"), "D B C A E");
+        // However far the working order moved the untouched block.
+        assert_eq!(pinned("A B C D E", "B C A D E", "A D"), "A B C D E");
+        assert_eq!(pinned("A B C D E", "B C D A E", "D A"), "D B C A E");
+    }
+
+    #[test]
+    fn pinning_a_single_moved_entry_leaves_it_in_place() {
+        assert_eq!(pinned("A B C D E", "A D B C E", "D"), "A B C D E");
+    }
+
+    #[test]
+    fn pinning_permutes_moved_entries_among_their_own_slots() {
+        // A, C and F hold slots 1, 3 and 6; working wants F, A, C.
+        assert_eq!(pinned("A B C D E F", "F B D A E C", "A C F"), "F B A D E C");
+        // Several untouched blocks between the moved entries.
+        assert_eq!(
+            pinned("A P1 P2 B P3 P4 C", "C B A P1 P2 P3 P4", "A B C"),
+            "C P1 P2 B P3 P4 A"
+        );
+    }
+
+    fn pxe_app() -> App {
+        let entries = vec![
+            entry("0001", "Fedora"),
+            entry("0002", "Windows Boot Manager"),
+            entry("0003", "PXE IPv4"),
+            entry("0004", "PXE IPv6"),
+            entry("0005", "PXE HTTP"),
+        ];
+        let mut app = app_with(
+            Config::default(),
+            entries,
+            &["0001", "0002", "0003", "0004", "0005"],
+        );
+        app.config.warn_windows_first = false;
+        app.pin_rest = true;
+        app
+    }
+
+    #[test]
+    fn pin_rest_applies_a_swap_made_with_move_keys() {
+        let mut app = pxe_app();
+        press(&mut app, "d");
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0001", "0003", "0004", "0005"])
+        );
+
+        let mut app = pxe_app();
+        press(&mut app, "ju");
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0001", "0003", "0004", "0005"])
+        );
+    }
+
+    #[test]
+    fn pin_rest_keeps_untouched_entries_in_their_slots() {
+        let mut app = pxe_app();
+        // Windows down past the first PXE entry and back up to the top:
+        // the PXE entries it passed end where they started.
+        press(&mut app, "jduu");
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0001", "0003", "0004", "0005"])
+        );
+        // The last PXE entry moved up one swaps with its neighbour only.
+        press(&mut app, "jjjju");
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0001", "0003", "0005", "0004"])
+        );
+    }
+
+    #[test]
+    fn p_toggles_pin_rest() {
+        let mut app = app();
+        press(&mut app, "P");
+        assert!(app.pin_rest);
+        press(&mut app, "P");
+        assert!(!app.pin_rest);
+    }
+
+    #[test]
+    fn pin_rest_leaves_entries_a_picked_up_entry_passes() {
+        let mut app = pxe_app();
+        // Fedora carried to the bottom, Windows (now first) to the middle.
+        app.handle_key(key(KeyCode::Char('m')));
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Enter));
+        app.select_index(Focus::Priority, 0);
+        press(&mut app, "m");
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(order(&app), ["0003", "0004", "0002", "0005", "0001"]);
+        // Only Fedora and Windows moved, so they trade slots.
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0001", "0003", "0004", "0005"])
+        );
+    }
+
+    fn firmware_app() -> App {
+        let mut shell = entry("0004", "UEFI: Built-in EFI Shell");
+        shell.device_path = "Fv(7cb8bdc9-f8eb-4f34-aaea-3ee4af6516a1)/FvFile(7c04a583)".to_string();
+        let mut pxe = entry("0005", "MBA v1.0.0 (PXE IPv4)");
+        pxe.device_path =
+            "PciRoot(0x0)/MAC(c8d9d2aabbcc,0)/IPv4(0.0.0.0:0<->0.0.0.0:0,0,0)".to_string();
+        let mut entries = entries();
+        entries.extend([shell, pxe]);
+        let mut app = app_with(
+            Config::default(),
+            entries,
+            &["0001", "0004", "0002", "0005", "0003"],
+        );
+        app.set_firmware_entries_hidden(true);
+        app
+    }
+
+    #[test]
+    fn hidden_firmware_entries_keep_their_place_in_the_order() {
+        let mut app = firmware_app();
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        assert_eq!(
+            app.order_to_apply(),
+            ids(&["0002", "0004", "0001", "0005", "0003"])
+        );
+    }
+
+    #[test]
+    fn h_reveals_hidden_firmware_entries() {
+        let mut app = firmware_app();
+        press(&mut app, "H");
+        assert_eq!(order(&app), ["0001", "0004", "0002", "0005", "0003"]);
+        press(&mut app, "H");
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+    }
+
+    // Applies what the Priority panel would, as if efibootmgr wrote it and
+    // echoed it back. Returns the order written.
+    fn apply_draft(app: &mut App) -> Vec<String> {
+        let order = app.order_to_apply();
+        let action = app.set_order_action(&order).unwrap();
+        app.step_succeeded(&action, &echoed(&order.join(",")));
+        order
+    }
+
+    fn hidden_ids(app: &App) -> Vec<&str> {
+        app.hidden_entries().iter().map(|e| e.id.as_str()).collect()
+    }
+
+    #[test]
+    fn moves_change_the_draft_not_the_entries() {
+        let mut app = app();
+        press(&mut app, "d");
+        assert_eq!(order(&app), ["0002", "0001", "0003"]);
+        assert_eq!(app.draft_order(), ids(&["0002", "0001", "0003"]));
+        assert_eq!(app.live_order, ids(&["0001", "0002", "0003"]));
+        let fetched: Vec<&str> = app.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(fetched, ["0001", "0002", "0003"]);
+    }
+
+    #[test]
+    fn nothing_is_dirty_right_after_an_apply() {
+        let mut app = app();
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        assert!(app.is_dirty());
+        let written = apply_draft(&mut app);
+        assert_eq!(written, ids(&["0002", "0001", "0003"]));
+        assert!(!app.is_dirty());
+        assert_eq!(app.live_order, written);
+        assert_eq!(app.moved_offsets(), [0, 0, 0]);
+        assert!(!app.reset_order(Focus::Priority));
+
+        // The next move is measured from the order just applied.
+        press(&mut app, "d");
+        assert_eq!(order(&app), ["0002", "0003", "0001"]);
+        assert!(app.is_dirty());
+        assert_eq!(app.moved_offsets(), [0, 1, -1]);
+    }
+
+    #[test]
+    fn reset_and_undo_go_back_to_the_live_order() {
+        let mut app = app();
+        press(&mut app, "dd");
+        assert_eq!(order(&app), ["0002", "0003", "0001"]);
+        ctrl(&mut app, 'u');
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        assert!(!app.is_dirty());
+        ctrl(&mut app, 'u');
+        assert_eq!(
+            toast(&app),
+            "Nothing to reset: the order is the one in effect"
+        );
+
+        // Undo brings the draft back, and each move undone steps towards
+        // the live order again.
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0002", "0003", "0001"]);
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0002", "0001", "0003"]);
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        assert!(!app.is_dirty());
+    }
+
+    #[test]
+    fn a_pinned_apply_takes_the_written_order_as_the_draft() {
+        let mut app = pxe_app();
+        app.handle_key(key(KeyCode::Char('m')));
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Enter));
+        app.select_index(Focus::Priority, 0);
+        press(&mut app, "m");
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Enter));
+        assert_eq!(order(&app), ["0003", "0004", "0002", "0005", "0001"]);
+
+        // Only the two moved entries trade places; the panel shows what
+        // was written, not the draft that asked for it.
+        let written = apply_draft(&mut app);
+        assert_eq!(written, ids(&["0002", "0001", "0003", "0004", "0005"]));
+        assert_eq!(order(&app), ["0002", "0001", "0003", "0004", "0005"]);
+        assert!(!app.is_dirty());
+        assert_eq!(app.order_to_apply(), app.live_order);
+    }
+
+    #[test]
+    fn an_apply_with_hidden_entries_keeps_them_in_place() {
+        let mut app = firmware_app();
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        let written = apply_draft(&mut app);
+        assert_eq!(written, ids(&["0002", "0004", "0001", "0005", "0003"]));
+        assert!(!app.is_dirty());
+        assert_eq!(order(&app), ["0002", "0001", "0003"]);
+        assert_eq!(hidden_ids(&app), ["0004", "0005"]);
+
+        // Shown again, they are where the firmware has them.
+        press(&mut app, "H");
+        assert_eq!(order(&app), ["0002", "0004", "0001", "0005", "0003"]);
+        assert!(!app.is_dirty());
+    }
+
+    fn temporary(boot_id: Option<&str>) -> TemporaryOrder {
+        TemporaryOrder {
+            original: ids(&["0001", "0002", "0003"]),
+            target: "0003".to_string(),
+            boot_id: boot_id.map(String::from),
+        }
+    }
+
+    #[test]
+    fn a_restore_is_due_after_a_reboot() {
+        let t = temporary(Some("boot-a"));
+        assert!(!t.restore_due(Some("boot-a"), Some("0001")));
+        assert!(t.restore_due(Some("boot-b"), Some("0001")));
+        // Without boot IDs, booting the target is the sign.
+        let t = temporary(None);
+        assert!(!t.restore_due(Some("boot-a"), Some("0001")));
+        assert!(t.restore_due(None, Some("0003")));
+    }
+
+    // Booted once into the USB stick by reordering, and since rebooted.
+    fn temporarily_reordered() -> App {
+        let mut app = app();
+        app.boot_order = ids(&["0003", "0001", "0002"]);
+        app.saved_state.temporary_order = Some(temporary(Some("an earlier boot")));
+        app
+    }
+
+    #[test]
+    fn a_temporary_reorder_records_the_order_to_restore() {
+        let mut app = app();
+        app.config.reboot_cmd = Some(Vec::new());
+        app.step_succeeded(&Action::BootOnceByReorder("0003".to_string()), "");
+        assert_eq!(app.boot_order, ids(&["0003", "0001", "0002"]));
+        let recorded = app.saved_state.temporary_order.clone().unwrap();
+        assert_eq!(recorded.original, ids(&["0001", "0002", "0003"]));
+        assert_eq!(recorded.target, "0003");
+
+        // A second one still goes back to the order from before the first.
+        app.step_succeeded(&Action::BootOnceByReorder("0002".to_string()), "");
+        let recorded = app.saved_state.temporary_order.clone().unwrap();
+        assert_eq!(recorded.original, ids(&["0001", "0002", "0003"]));
+        assert_eq!(recorded.target, "0002");
+    }
+
+    #[test]
+    fn the_restore_is_offered_and_applied() {
+        let mut app = temporarily_reordered();
+        assert!(app.check_temporary_order());
+        assert!(matches!(app.state, UIState::RestoreOrder(_)));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(&app.pending_action,
+            Action::SetOrder(o) if EntryId::to_strings(o) == ["0001", "0002", "0003"]));
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+
+        // Once written, there's nothing left to restore.
+        app.step_succeeded(&app.pending_action.clone(), &echoed("0001,0002,0003"));
+        assert!(app.saved_state.temporary_order.is_none());
+        assert!(!app.check_temporary_order());
+    }
+
+    #[test]
+    fn declining_the_restore_keeps_the_order_for_good() {
+        let mut app = temporarily_reordered();
+        app.check_temporary_order();
+        press(&mut app, "n");
+        assert!(matches!(app.state, UIState::Main));
+        assert!(app.pending_action == Action::None);
+        assert!(app.saved_state.temporary_order.is_none());
+        assert!(!app.check_temporary_order());
+    }
+
+    #[test]
+    fn dismissing_the_restore_asks_again_next_time() {
+        let mut app = temporarily_reordered();
+        app.check_temporary_order();
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        assert!(app.saved_state.temporary_order.is_some());
+        assert!(app.check_temporary_order());
+    }
+
+    #[test]
+    fn no_restore_before_the_reboot_or_once_already_back() {
+        let mut app = temporarily_reordered();
+        app.saved_state.temporary_order = Some(temporary(state::boot_id().as_deref()));
+        app.saved_state.boot_current = Some("0001".to_string());
+        assert!(!app.check_temporary_order());
+        assert!(app.saved_state.temporary_order.is_some());
+
+        let mut app = temporarily_reordered();
+        app.boot_order = ids(&["0001", "0002", "0003"]);
+        assert!(!app.check_temporary_order());
+        assert!(app.saved_state.temporary_order.is_none());
+    }
+
+    #[test]
+    fn the_restore_leaves_out_deleted_entries() {
+        let mut app = temporarily_reordered();
+        app.entries.retain(|e| e.id != "0002");
+        let action = app.restore_action(&temporary(None)).unwrap();
+        assert!(
+            matches!(action, Action::SetOrder(o) if EntryId::to_strings(&o) == ["0001", "0003"])
+        );
+    }
+
+    // Finishes a timeout write the way efibootmgr reported it.
+    fn finish_timeout(app: &mut App, output: CommandOutput) {
+        let (_tx, events) = std::sync::mpsc::channel();
+        let job = Job {
+            events,
+            prepared: app.prepare_step(Action::SetTimeout(3), VecDeque::new()),
+            started: Instant::now(),
+            prompt: None,
+            cancelled: false,
+        };
+        app.finish_job(job, output).unwrap();
+    }
+
+    const PATH_WARNING: &str = "Could not parse device path: Invalid argument";
+
+    #[test]
+    fn warnings_from_a_successful_write_are_kept() {
+        let mut app = app();
+        finish_timeout(
+            &mut app,
+            CommandOutput {
+                success: true,
+                stderr: PATH_WARNING.to_string(),
+                ..CommandOutput::default()
+            },
+        );
+        assert!(!is_failure(&app.state));
+        assert_eq!(app.command_warnings.as_deref(), Some(PATH_WARNING));
+
+        // A second write adds its own.
+        finish_timeout(
+            &mut app,
+            CommandOutput {
+                success: true,
+                stderr: "efibootmgr: another warning".to_string(),
+                ..CommandOutput::default()
+            },
+        );
+        assert_eq!(
+            app.command_warnings.as_deref(),
+            Some("Could not parse device path: Invalid argument\nefibootmgr: another warning")
+        );
+        // A clean one adds nothing.
+        finish_timeout(&mut app, CommandOutput::succeeded());
+        assert!(
+            app.command_warnings
+                .as_deref()
+                .unwrap()
+                .ends_with("another warning")
+        );
+    }
+
+    #[test]
+    fn stderr_of_a_failed_write_is_the_error_not_a_warning() {
+        let mut app = app();
+        finish_timeout(&mut app, CommandOutput::failed(PATH_WARNING));
+        assert!(matches!(&app.state, UIState::ErrorMessage(m) if m == PATH_WARNING));
+        assert!(app.command_warnings.is_none());
+    }
+
+    #[test]
+    fn warnings_open_with_o_and_go_with_x() {
+        let mut app = app();
+        // Nothing to show yet.
+        press(&mut app, "o");
+        assert!(matches!(app.state, UIState::Main));
+
+        app.command_warnings = Some(PATH_WARNING.to_string());
+        press(&mut app, "o");
+        assert!(matches!(app.state, UIState::Warnings));
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        press(&mut app, "x");
+        assert!(app.command_warnings.is_none());
+    }
+
+    fn toast(app: &App) -> &str {
+        app.toast
+            .as_ref()
+            .map_or("", |(message, _)| message.as_str())
+    }
+
+    // Windows moved first and written, so the reboot is offered.
+    fn order_applied() -> App {
+        let mut app = app();
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        let action = app.set_order_action(&app.order_to_apply()).unwrap();
+        app.pending_action = action.clone();
+        app.state = app.step_succeeded(&action, &echoed("0002,0001,0003"));
+        assert!(matches!(app.state, UIState::ConfirmReboot(_)));
+        app
+    }
+
+    #[test]
+    fn cancelling_the_countdown_after_an_order_change() {
+        let mut app = order_applied();
+        app.reboot_yes = true;
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::CountdownReboot { .. }));
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        assert_eq!(
+            toast(&app),
+            "Reboot cancelled; the new order is applied: Windows Boot Manager from the next reboot on"
+        );
+        // The applied order is the live one now: nothing to apply again.
+        assert!(!app.is_dirty());
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::Main));
+        assert_eq!(
+            toast(&app),
+            "This order is already in effect; it applies from the next reboot"
+        );
+    }
+
+    #[test]
+    fn declining_the_reboot_after_an_order_change() {
+        let mut app = order_applied();
+        press(&mut app, "n");
+        assert!(matches!(app.state, UIState::Main));
+        assert!(toast(&app).starts_with("Reboot cancelled; the new order is applied"));
+    }
+
+    #[test]
+    fn cancelling_the_countdown_after_boot_once() {
+        let mut app = app();
+        let action = Action::BootOnce("0002".to_string());
+        app.pending_action = action.clone();
+        app.state = app.step_succeeded(&action, "");
+        assert!(matches!(app.state, UIState::BootNextArmed(_)));
+        app.reboot_choice = RebootChoice::Countdown;
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::CountdownReboot { .. }));
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        assert_eq!(
+            toast(&app),
+            "Reboot cancelled; BootNext stays armed: Windows Boot Manager on next reboot"
+        );
+        assert_eq!(app.boot_next.as_deref(), Some("0002"));
+    }
+
+    #[test]
+    fn start_at_focuses_and_selects() {
+        let mut app = app();
+        app.start_at(Some(Focus::BootOnce), Some("windows"));
+        assert_eq!(app.focus, Focus::BootOnce);
+        assert_eq!(selected_id(&app, Focus::BootOnce), Some("0002"));
+        assert!(app.toast.is_none());
+
+        // Either works alone; an ID selects in the focused panel.
+        let mut app = testing::app();
+        app.start_at(None, Some("Boot0003"));
+        assert_eq!(app.focus, Focus::Priority);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0003"));
+        let mut app = testing::app();
+        app.start_at(Some(Focus::BootOnce), None);
+        assert_eq!(app.focus, Focus::BootOnce);
+    }
+
+    #[test]
+    fn a_selector_that_matches_nothing_starts_normally() {
+        let mut app = testing::app();
+        let before = selected_id(&app, Focus::BootOnce).map(String::from);
+        app.start_at(Some(Focus::BootOnce), Some("macos"));
+        assert_eq!(app.focus, Focus::BootOnce);
+        assert_eq!(selected_id(&app, Focus::BootOnce), before.as_deref());
+        assert_eq!(
+            toast(&app),
+            "--select didn't match: No entry matches \"macos\""
+        );
+
+        // Several matches are no match either.
+        let mut app = testing::app();
+        app.start_at(None, Some("o"));
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0001"));
+        assert!(toast(&app).contains("matches several entries"));
+    }
+
+    fn armed_app(boot_next: Option<&str>, current: &str) -> App {
+        let mut app = app();
+        app.saved_state.armed_boot_next = Some(ArmedBootNext {
+            target: "0002".to_string(),
+            armed_at: 0,
+            boot_id: Some("an earlier boot".to_string()),
+        });
+        app.boot_next = boot_next.map(String::from);
+        app.boot_current = Some(current.to_string());
+        app
+    }
+
+    #[test]
+    fn startup_reports_what_became_of_boot_next() {
+        let mut app = armed_app(Some("0002"), "0001");
+        assert_eq!(app.check_armed_boot_next(), None);
+        assert!(app.saved_state.armed_boot_next.is_some());
+
+        let mut app = armed_app(None, "0002");
+        assert_eq!(
+            app.check_armed_boot_next().as_deref(),
+            Some("Previous one-shot boot into Windows Boot Manager completed")
+        );
+        // Reported once.
+        assert!(app.saved_state.armed_boot_next.is_none());
+        assert_eq!(app.check_armed_boot_next(), None);
+
+        let mut app = armed_app(None, "0001");
+        let message = app.check_armed_boot_next().unwrap();
+        assert!(message.starts_with("The one-shot boot into Windows Boot Manager armed "));
+        assert!(message.ends_with(" was cleared without being used"));
+    }
+
+    // Runs `step` and has it finish with `output`.
+    fn finish_step(app: &mut App, step: Action, output: CommandOutput) {
+        let (_tx, events) = std::sync::mpsc::channel();
+        app.pending_action = step.clone();
+        let job = Job {
+            events,
+            prepared: app.prepare_step(step, VecDeque::new()),
+            started: Instant::now(),
+            prompt: None,
+            cancelled: false,
+        };
+        app.finish_job(job, output).unwrap();
+    }
+
+    // Runs `step` and has sudo reject the password.
+    fn reject_password(app: &mut App, step: Action) {
+        finish_step(app, step, CommandOutput::failed(efi::INCORRECT_PASSWORD));
+    }
+
+    #[test]
+    fn a_rejected_password_keeps_the_command_as_built() {
+        let mut app = app();
+        press(&mut app, "d");
+        let step = app.set_order_action(&app.order_to_apply()).unwrap();
+        let args = app.prepare_step(step.clone(), VecDeque::new()).args;
+        reject_password(&mut app, step.clone());
+        assert!(matches!(app.state, UIState::PasswordError));
+
+        // Moving entries meanwhile doesn't change what the retry runs.
+        press(&mut app, "d");
+        let retry = app.password_retry.as_ref().unwrap();
+        assert!(retry.action == step);
+        assert_eq!(retry.prepared.args, args);
+        assert_eq!(retry.prepared.order, ["0001", "0002", "0003"]);
+    }
+
+    // The only test pointing efivars at a directory of its own: the base is
+    // set once per process.
+    #[test]
+    fn a_retry_writes_nothing_once_the_boot_setup_changed() {
+        let dir = std::env::temp_dir().join(format!("swiftboot-retry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        efivars::set_base(&dir);
+        let boot_order = dir.join("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c");
+        let put_order = |order: &[u16]| {
+            let mut data = vec![0x07, 0, 0, 0];
+            data.extend(order.iter().flat_map(|id| id.to_le_bytes()));
+            std::fs::write(&boot_order, data).unwrap();
+        };
+        let read_order = || std::fs::read(&boot_order).unwrap()[4..].to_vec();
+
+        let mut app = app();
+        app.reads_efivarfs = true;
+        app.efivarfs_writes = true;
+        press(&mut app, "d");
+        let step = app.set_order_action(&app.order_to_apply()).unwrap();
+
+        // Something else reorders the entries while the password is retyped.
+        put_order(&[3, 1, 2]);
+        reject_password(&mut app, step.clone());
+        app.state = UIState::Processing;
+        app.execute_pending().unwrap();
+        assert!(
+            matches!(&app.state, UIState::ErrorMessage(m) if m.starts_with("The boot setup changed"))
+        );
+        assert!(app.pending_action == Action::None);
+        assert!(app.password_retry.is_none());
+        assert_eq!(read_order(), [3, 0, 1, 0, 2, 0]);
+
+        // Unchanged, the retry goes ahead with the command as first built.
+        put_order(&[1, 2, 3]);
+        reject_password(&mut app, step);
+        app.state = UIState::Processing;
+        app.execute_pending().unwrap();
+        while app.is_running() {
+            std::thread::sleep(Duration::from_millis(5));
+            app.execute_pending().unwrap();
+        }
+        assert_eq!(read_order(), [2, 0, 1, 0, 3, 0]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // Applies a move and stops at the password prompt.
+    fn asking_password() -> App {
+        let mut app = app();
+        app.config.warn_windows_first = false;
+        press(&mut app, "d");
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::AskPassword));
+        app
+    }
+
+    #[test]
+    fn an_empty_password_is_not_sent() {
+        let mut app = asking_password();
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::AskPassword));
+        assert!(app.password_empty);
+
+        // Typing clears the warning.
+        press(&mut app, "x");
+        assert!(!app.password_empty);
+        app.handle_key(key(KeyCode::Backspace));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.password_empty);
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        assert!(!app.password_empty);
+    }
+
+    #[test]
+    fn a_password_of_spaces_is_sent_as_typed() {
+        let mut app = asking_password();
+        press(&mut app, "  ");
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::Processing));
+        assert_eq!(app.password.text(), "  ");
+        assert!(!app.password_empty);
+    }
+
+    #[test]
+    fn the_written_order_is_checked_against_the_echo() {
+        let mut app = app();
+        app.config.expert_mode = true;
+        press(&mut app, "d");
+        let step = app.set_order_action(&app.order_to_apply()).unwrap();
+        // The firmware put Fedora back in front.
+        finish_step(
+            &mut app,
+            step,
+            CommandOutput {
+                success: true,
+                stdout: echoed("0001,0002,0003"),
+                ..CommandOutput::default()
+            },
+        );
+        let UIState::OrderModified {
+            requested, actual, ..
+        } = &app.state
+        else {
+            panic!("the rewrite isn't reported");
+        };
+        assert_eq!(requested, &ids(&["0002", "0001", "0003"]));
+        assert_eq!(actual, &ids(&["0001", "0002", "0003"]));
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+    }
+
+    #[test]
+    fn only_writes_need_room_in_nvram() {
+        for action in [
+            Action::SetOrder(Vec::new()),
+            Action::BootOnce("0002".to_string()),
+            Action::BootOnceByReorder("0002".to_string()),
+            Action::SetTimeout(5),
+            Action::Activate("0002".to_string()),
+            Action::Deactivate("0002".to_string()),
+        ] {
+            assert!(action.writes_variable());
+        }
+        for action in [
+            Action::Delete("0002".to_string()),
+            Action::DeferOrder(Vec::new()),
+            Action::CancelDeferred,
+            Action::ClearBootNext,
+            Action::ReadEntries,
+            Action::None,
+        ] {
+            assert!(!action.writes_variable());
+        }
+    }
+
+    #[test]
+    fn a_full_nvram_gets_its_own_error() {
+        let mut app = app();
+        press(&mut app, "d");
+        let step = app.set_order_action(&app.order_to_apply()).unwrap();
+        finish_step(
+            &mut app,
+            step,
+            CommandOutput::failed("Could not set BootOrder: No space left on device"),
+        );
+        assert!(
+            matches!(&app.state, UIState::NvramFull(m) if m.ends_with("No space left on device"))
+        );
+        assert!(is_failure(&app.state));
+        // Nothing was written, so the draft is still there to retry.
+        assert!(app.is_dirty());
+        app.handle_key(key(KeyCode::Esc));
+        assert!(matches!(app.state, UIState::Main));
+        assert!(app.pending_action == Action::None);
+    }
+
+    // Picks up the entry at `index` in the Priority panel.
+    fn pick_up(app: &mut App, index: usize) {
+        app.select_index(Focus::Priority, index);
+        app.handle_key(key(KeyCode::Char('m')));
+        assert!(app.moving.is_some());
+    }
+
+    #[test]
+    fn a_carried_entry_drops_at_the_top() {
+        let mut app = pxe_app();
+        pick_up(&mut app, 3);
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Up));
+        app.handle_key(key(KeyCode::Up));
+        // Past the top it stays there.
+        app.handle_key(key(KeyCode::Up));
+        assert_eq!(order(&app), ["0004", "0001", "0002", "0003", "0005"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0004"));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.moving.is_none());
+        assert!(app.is_dirty());
+
+        // The whole move is one undo step.
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+        assert!(!app.is_dirty());
+        ctrl(&mut app, 'y');
+        assert_eq!(order(&app), ["0004", "0001", "0002", "0003", "0005"]);
+    }
+
+    #[test]
+    fn a_carried_entry_drops_at_the_bottom() {
+        let mut app = pxe_app();
+        pick_up(&mut app, 1);
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Down));
+        // `m` drops as well as Enter.
+        app.handle_key(key(KeyCode::Char('m')));
+        assert!(app.moving.is_none());
+        assert_eq!(order(&app), ["0001", "0003", "0004", "0005", "0002"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0002"));
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0002"));
+    }
+
+    #[test]
+    fn cancelling_a_move_puts_everything_back() {
+        let mut app = pxe_app();
+        pick_up(&mut app, 0);
+        app.handle_key(key(KeyCode::Down));
+        app.handle_key(key(KeyCode::Down));
+        assert_eq!(order(&app), ["0002", "0003", "0001", "0004", "0005"]);
+        app.handle_key(key(KeyCode::Esc));
+        assert!(app.moving.is_none());
+        assert!(!app.should_quit);
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+        assert_eq!(selected_id(&app, Focus::Priority), Some("0001"));
+        assert!(!app.is_dirty());
+        // Nothing to undo.
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+    }
+
+    #[test]
+    fn a_carried_entry_only_takes_move_keys() {
+        let mut app = pxe_app();
+        pick_up(&mut app, 2);
+        press(&mut app, "qsPr");
+        assert!(!app.should_quit);
+        assert!(app.moving.is_some());
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+        // Dropped where it was picked up, nothing is recorded.
+        app.handle_key(key(KeyCode::Enter));
+        assert!(!app.is_dirty());
+        ctrl(&mut app, 'z');
+        assert_eq!(order(&app), ["0001", "0002", "0003", "0004", "0005"]);
+    }
+
+    #[test]
+    fn deleting_is_confirmed_even_in_expert_mode() {
+        let mut app = app();
+        app.config.expert_mode = true;
+        app.handle_key(key(KeyCode::Delete));
+        assert!(matches!(&app.state, UIState::ConfirmDelete(id) if id == "0001"));
+        // Cancel is highlighted, so Enter alone deletes nothing.
+        assert_eq!(app.delete_choice, 1);
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::Main));
+        assert!(app.pending_action == Action::None);
+
+        app.handle_key(key(KeyCode::Delete));
+        press(&mut app, "y");
+        assert!(app.pending_action == Action::Delete("0001".to_string()));
+        assert!(matches!(app.state, UIState::AskPassword));
+    }
+
+    #[test]
+    fn type_name_keeps_delete_disabled_until_the_name_matches() {
+        let mut app = app();
+        app.config.confirm_destructive = ConfirmDestructive::TypeName;
+        app.select_index(Focus::Priority, 1);
+        app.handle_key(key(KeyCode::Delete));
+        assert_eq!(app.delete_phrase("0002").as_deref(), Some("Wind"));
+
+        // `y` is typed into the field rather than pressing Delete.
+        press(&mut app, "y");
+        assert_eq!(app.delete_typed.text(), "y");
+        assert!(matches!(app.state, UIState::ConfirmDelete(_)));
+        app.handle_key(key(KeyCode::Backspace));
+
+        app.handle_key(key(KeyCode::Tab));
+        assert_eq!(app.delete_choice, 0);
+        press(&mut app, "win");
+        assert!(!app.delete_allowed("0002"));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(matches!(app.state, UIState::ConfirmDelete(_)));
+
+        // Any case will do.
+        press(&mut app, "D");
+        assert!(app.delete_allowed("0002"));
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.pending_action == Action::Delete("0002".to_string()));
+    }
+
+    #[test]
+    fn the_phrase_to_type_follows_the_shown_name() {
+        let mut app = app();
+        assert!(app.delete_phrase("0001").is_none());
+        assert!(app.delete_allowed("0001"));
+
+        app.config.confirm_destructive = ConfirmDestructive::TypeName;
+        app.config
+            .aliases
+            .insert("0002".to_string(), "  Win".to_string());
+        assert_eq!(app.delete_phrase("0001").as_deref(), Some("Fedo"));
+        // Shorter than four characters once trimmed: all of it.
+        assert_eq!(app.delete_phrase("0002").as_deref(), Some("Win"));
+        // A fresh popup starts empty.
+        app.delete_typed = TextInput::from("fedo".to_string());
+        app.handle_key(key(KeyCode::Delete));
+        assert!(app.delete_typed.is_empty());
+        assert!(!app.delete_allowed("0001"));
+    }
+
+    // BootOrder keeps 0010, which efibootmgr lists no entry for.
+    fn unknown_app() -> App {
+        app_with(
+            Config::default(),
+            entries(),
+            &["0001", "0010", "0002", "0003"],
+        )
+    }
+
+    #[test]
+    fn high_ids_outside_the_order_are_written_as_parsed() {
+        let text = "\
+BootOrder: 2001,0000
+Boot0000* Windows Boot Manager
+BootffFF* UEFI: PXE IPv4
+Boot2001* Fedora
+Boot000a* Recovery
+";
+        let entries = efi::parse_entries(text, "Boot");
+        let status = efi::parse_status(text);
+        let boot_order: Vec<&str> = status.order.iter().map(String::as_str).collect();
+        let mut app = app_with(Config::default(), entries, &boot_order);
+        assert_eq!(order(&app), ["2001", "0000", "000A", "FFFF"]);
+
+        // The PXE entry, last by ID, to the top.
+        app.select_index(Focus::Priority, 3);
+        press(&mut app, "uuu");
+        let action = app.set_order_action(&app.order_to_apply()).unwrap();
+        let (args, _) = app.command_for(&action);
+        assert!(
+            args.ends_with(&ids(&["-o", "FFFF,2001,0000,000A"])),
+            "{:?}",
+            args
+        );
+    }
+
+    #[test]
+    fn an_unknown_id_survives_the_round_trip() {
+        let mut app = unknown_app();
+        assert_eq!(order(&app), ["0001", "0010", "0002", "0003"]);
+        assert!(app.entries[1].unknown);
+
+        // Windows to the top, past the unknown entry.
+        app.select_index(Focus::Priority, 2);
+        press(&mut app, "uu");
+        let order = app.order_to_apply();
+        assert_eq!(order, ids(&["0002", "0001", "0010", "0003"]));
+        let action = app.set_order_action(&order).unwrap();
+        assert!(matches!(
+            action,
+            Action::SetOrder(o) if EntryId::to_strings(&o) == ["0002", "0001", "0010", "0003"]
+        ));
+
+        // Pinned, it keeps its slot while Fedora and USB trade theirs.
+        let mut app = unknown_app();
+        app.pin_rest = true;
+        press(&mut app, "m");
+        app.handle_key(key(KeyCode::End));
+        app.handle_key(key(KeyCode::Enter));
+        app.select_index(Focus::Priority, 2);
+        press(&mut app, "m");
+        app.handle_key(key(KeyCode::Home));
+        app.handle_key(key(KeyCode::Enter));
+        let order = app.order_to_apply();
+        assert_eq!(order, ids(&["0003", "0010", "0002", "0001"]));
+        assert!(app.set_order_action(&order).is_ok());
+    }
+
+    #[test]
+    fn an_unknown_entry_cant_be_booted_once() {
+        let mut app = unknown_app();
+        app.focus = Focus::BootOnce;
+        let index = app.entries.iter().position(|e| e.unknown).unwrap();
+        app.select_index(Focus::BootOnce, index);
+        app.handle_key(key(KeyCode::Enter));
+        assert!(app.pending_action == Action::None);
+        assert_eq!(toast(&app), UNKNOWN_BOOT_ONCE);
+    }
+
+    #[test]
+    fn deleting_an_unknown_entry_only_drops_it_from_the_draft() {
+        let mut app = unknown_app();
+        app.select_index(Focus::Priority, 1);
+        app.handle_key(key(KeyCode::Delete));
+        press(&mut app, "y");
+        assert!(matches!(app.state, UIState::Main));
+        assert!(app.pending_action == Action::None);
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        assert!(app.is_dirty());
+        assert_eq!(app.order_to_apply(), ids(&["0001", "0002", "0003"]));
+    }
+}
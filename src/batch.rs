@@ -0,0 +1,498 @@
+use crate::app::{self, Action, App, UIState};
+use crate::plain;
+use crate::text_input::TextInput;
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use swiftboot::efi::{self, BootEntry, EntryId};
+use swiftboot::efivars;
+
+pub struct Options {
+    pub dry_run: bool,
+    pub continue_on_error: bool,
+    // Prints the sudo password, for when there's no terminal to ask on.
+    pub askpass: Option<PathBuf>,
+}
+
+// One line of a batch script. Entries are named by Boot#### ID (with or
+// without the "Boot" prefix) or by name; see `find_entry`.
+pub enum BatchCommand {
+    Order(Vec<String>),
+    Once(String),
+    Timeout(u16),
+    Activate(String),
+    Deactivate(String),
+    Delete(String),
+}
+
+const VERBS: &str = "order <entries...>, once <entry>, timeout <seconds>, \
+                     activate <entry>, deactivate <entry>, delete <entry>";
+
+// Blank lines and lines starting with `#` are skipped.
+pub fn parse_line(line: &str) -> Result<Option<BatchCommand>, String> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return Ok(None);
+    }
+    let words = split_words(line)?;
+    let Some((verb, args)) = words.split_first() else {
+        return Ok(None);
+    };
+
+    let entry = |usage: &str| match args {
+        [arg] => Ok(arg.clone()),
+        _ => Err(format!("Usage: {}", usage)),
+    };
+    let command = match verb.to_ascii_lowercase().as_str() {
+        "order" => match args {
+            [] => return Err("Usage: order <entries...>".to_string()),
+            _ => BatchCommand::Order(args.to_vec()),
+        },
+        "once" => BatchCommand::Once(entry("once <entry>")?),
+        "timeout" => match args {
+            [seconds] => BatchCommand::Timeout(parse_seconds(seconds)?),
+            _ => return Err("Usage: timeout <seconds>".to_string()),
+        },
+        "activate" => BatchCommand::Activate(entry("activate <entry>")?),
+        "deactivate" => BatchCommand::Deactivate(entry("deactivate <entry>")?),
+        "delete" => BatchCommand::Delete(entry("delete <entry>")?),
+        other => {
+            return Err(format!(
+                "Unknown command \"{}\"; expected one of: {}",
+                other, VERBS
+            ));
+        }
+    };
+    Ok(Some(command))
+}
+
+fn parse_seconds(arg: &str) -> Result<u16, String> {
+    arg.parse()
+        .map_err(|_| format!("\"{}\" is not a number of seconds (0-65535)", arg))
+}
+
+// Whitespace-separated words; double quotes keep a name with spaces
+// together, e.g. "Windows Boot Manager".
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+// An entry by Boot#### ID (with or without the "Boot" prefix) or by name,
+// skipping `deleted`. An ID must exist; a name matches exactly (ignoring
+// case) or as the only entry containing it.
+pub fn find_entry(app: &App, reference: &str, deleted: &[String]) -> Result<String, String> {
+    let entries: Vec<_> = app
+        .entries
+        .iter()
+        .filter(|e| !deleted.contains(&e.id))
+        .collect();
+
+    let id = reference
+        .get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("boot"))
+        .map_or(reference, |_| &reference[4..]);
+    if let Some(id) = EntryId::parse(id) {
+        return entries
+            .iter()
+            .find(|e| e.id == id.as_str())
+            .map(|e| e.id.clone())
+            .ok_or_else(|| format!("No Boot{} entry", id.as_str()));
+    }
+
+    let wanted = reference.to_lowercase();
+    let names = |e: &BootEntry| [app.display_name(e).to_lowercase(), e.name.to_lowercase()];
+    if let Some(e) = entries.iter().find(|e| names(e).contains(&wanted)) {
+        return Ok(e.id.clone());
+    }
+    let matches: Vec<_> = entries
+        .iter()
+        .filter(|e| names(e).iter().any(|n| n.contains(&wanted)))
+        .collect();
+    match matches.as_slice() {
+        [e] => Ok(e.id.clone()),
+        [] => Err(format!("No entry matches \"{}\"", reference)),
+        several => Err(format!(
+            "\"{}\" matches several entries: {}",
+            reference,
+            several
+                .iter()
+                .map(|e| app.display_name(e))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// The boot setup as the script leaves it so far, so each line is checked
+// against the effect of the lines before it.
+struct Plan<'a> {
+    app: &'a App,
+    order: Vec<String>,
+    deleted: Vec<String>,
+}
+
+impl Plan<'_> {
+    fn entry(&self, reference: &str) -> Result<String, String> {
+        find_entry(self.app, reference, &self.deleted)
+    }
+
+    fn resolve(&mut self, command: &BatchCommand) -> Result<(Action, String), String> {
+        let app = self.app;
+        match command {
+            // Like `order` in plain mode: the named entries first, then the
+            // rest in their current relative order.
+            BatchCommand::Order(references) => {
+                let mut order = Vec::new();
+                for reference in references {
+                    let id = self.entry(reference)?;
+                    if order.contains(&id) {
+                        return Err(format!("{} is listed more than once", reference));
+                    }
+                    order.push(id);
+                }
+                let rest: Vec<String> = self
+                    .order
+                    .iter()
+                    .filter(|id| !order.contains(id))
+                    .cloned()
+                    .collect();
+                order.extend(rest);
+                let action = app.set_order_action(&order)?;
+                let description = format!("boot order {}", app.order_names(&order));
+                self.order = order;
+                Ok((action, description))
+            }
+            BatchCommand::Once(reference) => {
+                let id = self.entry(reference)?;
+                let description = format!("boot {} once", app.entry_name(&id));
+                Ok((Action::BootOnce(id), description))
+            }
+            BatchCommand::Timeout(seconds) => Ok((
+                Action::SetTimeout(*seconds),
+                format!("timeout {}s", seconds),
+            )),
+            BatchCommand::Activate(reference) => {
+                let id = self.entry(reference)?;
+                let description = format!("activate {}", app.entry_name(&id));
+                Ok((Action::Activate(id), description))
+            }
+            BatchCommand::Deactivate(reference) => {
+                let id = self.entry(reference)?;
+                let description = format!("deactivate {}", app.entry_name(&id));
+                Ok((Action::Deactivate(id), description))
+            }
+            BatchCommand::Delete(reference) => {
+                let id = self.entry(reference)?;
+                let description = format!("delete {}", app.entry_name(&id));
+                self.order.retain(|other| other != &id);
+                self.deleted.push(id.clone());
+                Ok((Action::Delete(id), description))
+            }
+        }
+    }
+}
+
+// `swiftboot batch`: reads the whole script from stdin and checks every
+// line against the live entries before anything is written, then applies
+// the lines in order under a single password.
+pub fn run(
+    app: &mut App,
+    options: &Options,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !options.dry_run {
+        plain::check_writable(app)?;
+    }
+
+    let mut steps = Vec::new();
+    let mut failures = 0;
+    let mut plan = Plan {
+        app,
+        order: app.order_to_apply(),
+        deleted: Vec::new(),
+    };
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        let resolved =
+            parse_line(&line).and_then(|command| command.map(|c| plan.resolve(&c)).transpose());
+        match resolved {
+            Ok(Some((action, description))) => steps.push((i + 1, action, description)),
+            Ok(None) => {}
+            Err(e) if options.continue_on_error => {
+                writeln!(out, "line {}: {}", i + 1, e)?;
+                failures += 1;
+            }
+            Err(e) => return Err(format!("line {}: {}", i + 1, e).into()),
+        }
+    }
+
+    if options.dry_run {
+        for (line, _, description) in &steps {
+            writeln!(out, "line {}: {}", line, description)?;
+        }
+        writeln!(out, "Dry run: nothing was written.")?;
+        return finish(failures);
+    }
+    if steps.is_empty() {
+        return finish(failures);
+    }
+
+    if let Some(warning) = steps
+        .iter()
+        .find_map(|(_, action, _)| app::low_nvram_warning(action))
+    {
+        writeln!(out, "warning: {}", warning)?;
+    }
+    let password = password(app, options)?;
+    for (line, action, description) in steps {
+        app.pending_action = match action {
+            // Safe apply covers scripted orders too.
+            Action::SetOrder(order) => app.order_action(&EntryId::to_strings(&order))?,
+            action => action,
+        };
+        app.password = TextInput::from(password.clone());
+        plain::wait_for_pending(app, out)?;
+        app.password.clear();
+
+        let result = match std::mem::replace(&mut app.state, UIState::Main) {
+            UIState::PasswordError => return Err("Incorrect password".into()),
+            UIState::ErrorMessage(message) => Err(message),
+            UIState::NvramFull(message) => Err(format!("{}\n{}", message, efi::NVRAM_FULL_ADVICE)),
+            UIState::OrderModified { actual, .. } => {
+                writeln!(
+                    out,
+                    "line {}: warning: the firmware kept the order {} instead",
+                    line,
+                    actual.join(", ")
+                )?;
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => writeln!(out, "line {}: {}", line, description)?,
+            Err(e) if options.continue_on_error => {
+                writeln!(out, "line {}: {} failed: {}", line, description, e)?;
+                failures += 1;
+            }
+            Err(e) => return Err(format!("line {}: {} failed: {}", line, description, e).into()),
+        }
+    }
+    finish(failures)
+}
+
+fn finish(failures: usize) -> Result<(), Box<dyn std::error::Error>> {
+    match failures {
+        0 => Ok(()),
+        1 => Err("1 line failed".into()),
+        n => Err(format!("{} lines failed", n).into()),
+    }
+}
+
+// Asked for once, up front. stdin carries the script, so the password comes
+// from the terminal or from the askpass program.
+fn password(app: &App, options: &Options) -> Result<String, Box<dyn std::error::Error>> {
+    if app.efivarfs_writes || efivars::is_root() || efi::sudo_cached() {
+        return Ok(String::new());
+    }
+    let password = match &options.askpass {
+        Some(program) => {
+            let output = Command::new(program)
+                .stdin(Stdio::null())
+                .stderr(Stdio::inherit())
+                .output()
+                .map_err(|e| format!("Can't run {}: {}", program.display(), e))?;
+            if !output.status.success() {
+                return Err(format!("{} failed ({})", program.display(), output.status).into());
+            }
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end_matches(['\r', '\n'])
+                .to_string()
+        }
+        None => plain::read_hidden(&mut io::stderr(), "Sudo password: ").map_err(|e| {
+            format!(
+                "Can't ask for the password ({}); use --askpass or run as root",
+                e
+            )
+        })?,
+    };
+    if password.is_empty() {
+        return Err("No password given".into());
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing::app;
+    use std::io::Cursor;
+
+    fn options(continue_on_error: bool) -> Options {
+        Options {
+            dry_run: true,
+            continue_on_error,
+            askpass: None,
+        }
+    }
+
+    fn dry_run(script: &str, continue_on_error: bool) -> (Result<(), String>, String) {
+        let mut app = app();
+        let mut out = Vec::new();
+        let result = run(
+            &mut app,
+            &options(continue_on_error),
+            &mut Cursor::new(script),
+            &mut out,
+        )
+        .map_err(|e| e.to_string());
+        (result, String::from_utf8(out).unwrap())
+    }
+
+    fn error(line: &str) -> String {
+        parse_line(line).err().expect("line should not parse")
+    }
+
+    #[test]
+    fn parses_every_verb() {
+        assert!(matches!(
+            parse_line("order 0002 \"Windows Boot Manager\" Boot0001"),
+            Ok(Some(BatchCommand::Order(ids)))
+                if ids == ["0002", "Windows Boot Manager", "Boot0001"]
+        ));
+        assert!(
+            matches!(parse_line("once 0003"), Ok(Some(BatchCommand::Once(id))) if id == "0003")
+        );
+        assert!(matches!(
+            parse_line("TIMEOUT 5"),
+            Ok(Some(BatchCommand::Timeout(5)))
+        ));
+        assert!(matches!(
+            parse_line("activate 1"),
+            Ok(Some(BatchCommand::Activate(_)))
+        ));
+        assert!(matches!(
+            parse_line("deactivate 1"),
+            Ok(Some(BatchCommand::Deactivate(_)))
+        ));
+        assert!(matches!(
+            parse_line("  delete 1  "),
+            Ok(Some(BatchCommand::Delete(_)))
+        ));
+    }
+
+    #[test]
+    fn skips_blank_and_comment_lines() {
+        assert!(matches!(parse_line(""), Ok(None)));
+        assert!(matches!(parse_line("   \t"), Ok(None)));
+        assert!(matches!(parse_line("# order 0002"), Ok(None)));
+        assert!(matches!(parse_line("   # indented"), Ok(None)));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(error("reboot now").starts_with("Unknown command \"reboot\""));
+        assert_eq!(error("order"), "Usage: order <entries...>");
+        assert_eq!(error("once"), "Usage: once <entry>");
+        assert_eq!(error("once 0001 0002"), "Usage: once <entry>");
+        assert_eq!(error("timeout"), "Usage: timeout <seconds>");
+        assert_eq!(error("delete"), "Usage: delete <entry>");
+        assert_eq!(
+            error("timeout -1"),
+            "\"-1\" is not a number of seconds (0-65535)"
+        );
+        assert_eq!(
+            error("timeout 65536"),
+            "\"65536\" is not a number of seconds (0-65535)"
+        );
+        assert_eq!(error("once \"Windows Boot"), "Unterminated quote");
+    }
+
+    #[test]
+    fn quotes_keep_names_together() {
+        assert_eq!(
+            split_words("once \"Windows Boot Manager\"").unwrap(),
+            ["once", "Windows Boot Manager"]
+        );
+        // An empty quoted word is still a word.
+        assert_eq!(split_words("once \"\"").unwrap(), ["once", ""]);
+    }
+
+    #[test]
+    fn finds_entries_by_id_or_name() {
+        let app = app();
+        assert_eq!(find_entry(&app, "0002", &[]), Ok("0002".to_string()));
+        assert_eq!(find_entry(&app, "boot0002", &[]), Ok("0002".to_string()));
+        assert_eq!(find_entry(&app, "fedora", &[]), Ok("0001".to_string()));
+        assert_eq!(find_entry(&app, "windows", &[]), Ok("0002".to_string()));
+        assert_eq!(
+            find_entry(&app, "0009", &[]),
+            Err("No Boot0009 entry".to_string())
+        );
+        assert_eq!(
+            find_entry(&app, "0001", &["0001".to_string()]),
+            Err("No Boot0001 entry".to_string())
+        );
+        assert_eq!(
+            find_entry(&app, "macos", &[]),
+            Err("No entry matches \"macos\"".to_string())
+        );
+        assert!(
+            find_entry(&app, "o", &[])
+                .unwrap_err()
+                .starts_with("\"o\" matches several entries")
+        );
+    }
+
+    #[test]
+    fn dry_run_describes_each_line() {
+        let (result, out) = dry_run("# setup\ntimeout 3\n\nonce usb\n", false);
+        assert_eq!(result, Ok(()));
+        assert!(out.contains("line 2: timeout 3s"));
+        assert!(out.contains("line 4: boot UEFI: USB once"));
+        assert!(out.contains("Dry run: nothing was written."));
+    }
+
+    #[test]
+    fn a_malformed_line_stops_the_script() {
+        let (result, out) = dry_run("timeout 3\nbogus\ntimeout x\n", false);
+        assert!(result.unwrap_err().starts_with("line 2: Unknown command"));
+        assert!(!out.contains("Dry run"));
+    }
+
+    #[test]
+    fn continue_on_error_reports_every_bad_line() {
+        let (result, out) = dry_run("bogus\ntimeout 3\ntimeout x\n", true);
+        assert_eq!(result, Err("2 lines failed".to_string()));
+        assert!(out.contains("line 1: Unknown command \"bogus\""));
+        assert!(out.contains("line 2: timeout 3s"));
+        assert!(out.contains("line 3: \"x\" is not a number of seconds"));
+    }
+
+    #[test]
+    fn lines_see_earlier_deletes() {
+        let (result, out) = dry_run("delete 0003\nonce 0003\n", true);
+        assert_eq!(result, Err("1 line failed".to_string()));
+        assert!(out.contains("line 2: No Boot0003 entry"));
+    }
+}
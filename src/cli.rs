@@ -0,0 +1,336 @@
+use crate::config::{BootOnceMethod, Config};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use swiftboot::efi;
+
+// Shared by `--help` and the man page.
+pub const FILES: &[(&str, &str)] = &[
+    (
+        "$XDG_CONFIG_HOME/swiftboot/config.toml",
+        "Configuration (default ~/.config/swiftboot/config.toml)",
+    ),
+    (
+        "$XDG_STATE_HOME/swiftboot/state.toml",
+        "Remembered settings (default ~/.local/state/swiftboot/state.toml)",
+    ),
+    (
+        "$XDG_STATE_HOME/swiftboot/captures/",
+        "Raw efibootmgr output kept with --capture, for `swiftboot report`",
+    ),
+    ("$XDG_RUNTIME_DIR/swiftboot.lock", "Single-instance lock"),
+    (
+        "$XDG_RUNTIME_DIR/swiftboot.sock",
+        "Socket of `swiftboot daemon`",
+    ),
+];
+
+pub const ENVIRONMENT: &[(&str, &str)] = &[
+    (
+        "XDG_CONFIG_HOME, XDG_STATE_HOME, XDG_RUNTIME_DIR",
+        "Override the locations above",
+    ),
+    (
+        "SWIFTBOOT_EFIBOOTMGR",
+        "efibootmgr binary to run (overrides efibootmgr_path)",
+    ),
+    (
+        "VISUAL, EDITOR",
+        "Editor for editing the boot order as text",
+    ),
+    ("PATH", "Used to find efibootmgr and sudo"),
+];
+
+fn after_help() -> String {
+    let table = |rows: &[(&str, &str)]| {
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        rows.iter()
+            .map(|(name, text)| format!("  {:<width$}  {}", name, text))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    format!(
+        "Files:\n{}\n\nEnvironment:\n{}",
+        table(FILES),
+        table(ENVIRONMENT)
+    )
+}
+
+#[derive(Parser)]
+#[command(
+    about = "TUI for managing UEFI boot order and one-time boots",
+    after_help = after_help(),
+    disable_version_flag = true
+)]
+pub struct Cli {
+    /// Use a line-based interface instead of the full-screen TUI (implied when
+    /// stdout is not a terminal). Friendly to screen readers and scripts.
+    #[arg(long)]
+    pub plain: bool,
+
+    /// Draw below the prompt instead of on the alternate screen, so the
+    /// final state stays in the terminal scrollback.
+    #[arg(long)]
+    pub no_alt_screen: bool,
+
+    /// Don't print the summary of changes on exit
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Also show the Driver and SysPrep load orders, for firmware with
+    /// custom drivers or recovery tooling.
+    #[arg(long)]
+    pub show_all: bool,
+
+    /// How to boot an entry once, overriding `boot_once_method` in the
+    /// config. `reorder-temporarily` is for firmware that ignores BootNext.
+    #[arg(long, value_enum, value_name = "METHOD")]
+    pub boot_once_method: Option<BootOnceMethod>,
+
+    /// Expert mode: skip the confirmation popups, and the password prompt
+    /// while sudo needs none. Same as `expert_mode = true`.
+    #[arg(long, global = true)]
+    pub yes: bool,
+
+    /// Start with this panel focused, e.g. for a hotkey that opens
+    /// swiftboot ready to boot something once.
+    #[arg(long, value_enum, value_name = "PANEL")]
+    pub focus: Option<StartPanel>,
+
+    /// Start with the selection on this entry: a Boot#### ID or a name, as
+    /// in batch mode ("windows" finds "Windows Boot Manager").
+    #[arg(long, value_name = "ENTRY")]
+    pub select: Option<String>,
+
+    /// Only show the boot setup; every change is disabled. Implied when
+    /// efibootmgr is missing but efivarfs can be read.
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// How the boot variables are read and written. `efivarfs` goes through
+    /// /sys/firmware/efi/efivars directly, without efibootmgr or sudo, and
+    /// needs root.
+    #[arg(long, value_enum, default_value_t = Backend::Efibootmgr)]
+    pub backend: Backend,
+
+    /// Start with the default configuration if the config file is invalid,
+    /// instead of refusing to run.
+    #[arg(long)]
+    pub ignore_config_errors: bool,
+
+    /// Print the key bindings in effect, after the `[keys]` overrides in
+    /// the config file
+    #[arg(long)]
+    pub print_keys: bool,
+
+    /// Read the boot entries as at startup, print how long each efibootmgr
+    /// run and the parsing of its output took, and exit
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Keep the last few raw `efibootmgr -v` outputs for `swiftboot
+    /// report`. Same as `debug_capture = true`.
+    #[arg(long)]
+    pub capture: bool,
+
+    /// Print version, build and efibootmgr information
+    #[arg(short = 'V', long, action = ArgAction::SetTrue)]
+    pub version: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Apply a boot order profile from the config file
+    ApplyProfile {
+        /// Profile name, as in [profiles.<name>]
+        name: String,
+    },
+    /// Swap the first two entries of the boot order, making the second one
+    /// the default
+    SwapDefault,
+    /// Keep a boot order applied with safe_apply: the new default booted fine
+    Confirm,
+    /// Restore the boot order saved by the last safe apply
+    Revert,
+    /// Show the boot order change deferred to the next shutdown
+    Deferred {
+        /// Cancel it, leaving the current boot order as it is
+        #[arg(long)]
+        cancel: bool,
+    },
+    /// Reboot into the default entry, or the armed BootNext, without
+    /// changing anything. Asks first unless --yes, then counts down.
+    Reboot,
+    /// Run commands read from stdin, one per line: order <entries...>,
+    /// once <entry>, timeout <seconds>, activate <entry>, deactivate <entry>
+    /// and delete <entry>. Entries are Boot#### IDs or names. Every line is
+    /// checked before any is applied, and the password is asked once.
+    Batch {
+        /// Check the commands and show what they would do, without writing
+        #[arg(long)]
+        dry_run: bool,
+        /// Carry on past a failing line instead of stopping at it
+        #[arg(long)]
+        continue_on_error: bool,
+        /// Program that prints the sudo password, for when there's no
+        /// terminal to ask on
+        #[arg(long, value_name = "PROGRAM")]
+        askpass: Option<PathBuf>,
+    },
+    /// Print the current, default and next boot entry, for scripts and
+    /// status bars. Exits non-zero when the system isn't UEFI.
+    Status {
+        #[arg(long, value_enum, default_value_t = StatusFormat::Plain)]
+        format: StatusFormat,
+    },
+    /// Serve list, status, boot-once and set-order requests on a Unix
+    /// socket, for desktop widgets. Only the same user can connect; writes
+    /// are authorized through pkexec.
+    Daemon {
+        /// Socket to listen on [default: $XDG_RUNTIME_DIR/swiftboot.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+    /// Send a request to a running `swiftboot daemon`
+    Client {
+        /// Socket the daemon listens on [default: $XDG_RUNTIME_DIR/swiftboot.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        request: ClientCommand,
+    },
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Print the latest captured efibootmgr output, how swiftboot parsed it,
+    /// the configuration and version information, for pasting into a bug
+    /// report. Disk GUIDs, serial numbers and MAC addresses are replaced
+    /// with placeholders.
+    Report {
+        /// Leave the disk GUIDs, serial numbers and MAC addresses in
+        #[arg(long)]
+        no_redact: bool,
+    },
+    /// Print a man page in roff format, generated from the same options,
+    /// settings and key bindings the program uses
+    GenerateMan,
+}
+
+#[derive(Subcommand)]
+pub enum ClientCommand {
+    /// List the boot entries in boot order
+    List,
+    /// Print the current, default and next boot entry
+    Status,
+    /// Boot an entry once on the next reboot
+    Once {
+        /// Boot#### ID or name
+        entry: String,
+    },
+    /// Put the given entries first in the boot order
+    Order {
+        /// Boot#### IDs or names
+        #[arg(required = true)]
+        entries: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Validate the config file and print the effective configuration
+    Check,
+    /// Write a commented config file with the default settings
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum StartPanel {
+    Priority,
+    BootOnce,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Backend {
+    Efibootmgr,
+    Efivarfs,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum StatusFormat {
+    /// A single line of key=value pairs
+    Plain,
+    /// A compact JSON object
+    Json,
+    /// SWIFTBOOT_* shell assignments, quoted for `eval`
+    Sh,
+}
+
+impl Cli {
+    // Command-line flags take precedence over the config file.
+    pub fn apply_to(&self, config: &mut Config) {
+        if self.show_all {
+            config.show_all = true;
+        }
+        if self.yes {
+            config.expert_mode = true;
+        }
+        if self.capture {
+            config.debug_capture = true;
+        }
+        if let Some(method) = self.boot_once_method {
+            config.boot_once_method = method;
+        }
+    }
+}
+
+// Handled by hand rather than by clap so efibootmgr is only queried when
+// the version is actually asked for.
+pub fn version_text() -> String {
+    let efibootmgr = efi::efibootmgr_command(&["--version"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .next()
+                .map(|l| l.trim().to_string())
+        })
+        .unwrap_or_else(|| "not found".to_string());
+
+    format!(
+        "swiftboot {} ({} {})\nefibootmgr: {}",
+        env!("CARGO_PKG_VERSION"),
+        env!("SWIFTBOOT_GIT_HASH"),
+        env!("SWIFTBOOT_BUILD_DATE"),
+        efibootmgr
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("swiftboot").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn focus_and_select_parse() {
+        let cli = parse(&["--focus", "boot-once", "--select", "windows"]);
+        assert!(cli.focus == Some(StartPanel::BootOnce));
+        assert_eq!(cli.select.as_deref(), Some("windows"));
+        assert!(parse(&["--focus", "priority"]).focus == Some(StartPanel::Priority));
+        let cli = parse(&[]);
+        assert!(cli.focus.is_none() && cli.select.is_none());
+        assert!(Cli::try_parse_from(["swiftboot", "--focus", "drivers"]).is_err());
+    }
+}
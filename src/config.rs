@@ -0,0 +1,455 @@
+use crate::keymap::{KeyAction, Keymap};
+use crate::schedule::Schedule;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::PathBuf,
+};
+use swiftboot::efi::EFIBOOTMGR_ENV;
+
+// A day; a safety net armed for longer is forgotten about.
+const MAX_SAFE_APPLY_MINUTES: u64 = 24 * 60;
+// A minute; any read that slow is worth mentioning.
+const MAX_SLOW_READ_MS: u64 = 60_000;
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RebootChoice {
+    Now,
+    Countdown,
+    Later,
+}
+
+// How the Boot To panel boots an entry once. Some firmware ignores
+// BootNext; reordering works there too, at the cost of putting the old
+// order back afterwards.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BootOnceMethod {
+    #[serde(rename = "bootnext")]
+    #[value(name = "bootnext")]
+    BootNext,
+    // Put the entry first in BootOrder and restore the order on the next
+    // run after that boot; see `state::TemporaryOrder`.
+    ReorderTemporarily,
+    Ask,
+}
+
+// Which entry the Boot To panel starts on.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BootToInitial {
+    First,
+    // Skips the entry that is running now; booting into it once again is
+    // rarely what anyone wants.
+    FirstNonCurrent,
+    // The entry last booted once with swiftboot.
+    LastUsed,
+}
+
+// How deleting a boot entry is confirmed: a button, or typing the start of
+// the entry's name first, like deleting a repository on GitHub.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfirmDestructive {
+    Button,
+    TypeName,
+}
+
+// A named boot order, e.g. `[profiles.gaming] order = ["0002", "0001"]`.
+// Entries it doesn't mention keep their relative order after the listed ones.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub order: Vec<String>,
+    // When to suggest it at startup; it is never applied on its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggest: Option<Schedule>,
+}
+
+// Unknown keys are rejected so typos don't go unnoticed.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub show_ids: bool,
+    pub confirm_boot_once: bool,
+    // Which button is highlighted after BootNext has been armed.
+    pub after_boot_once: RebootChoice,
+    pub boot_to_initial: BootToInitial,
+    pub boot_once_method: BootOnceMethod,
+    // Advanced mode: also show and reorder Driver#### and SysPrep#### entries.
+    pub show_all: bool,
+    // Also list network boot, the EFI shell, diagnostics and the like; see
+    // `BootEntry::is_firmware_internal`. `H` toggles it at runtime.
+    pub show_firmware_entries: bool,
+    // The efibootmgr binary, a name looked up in PATH or a full path.
+    pub efibootmgr_path: String,
+    // Added to every efibootmgr invocation, before its own arguments.
+    pub efibootmgr_extra_args: Vec<String>,
+    // Reading the entries taking longer than this shows how long it took in
+    // the status bar; 0 never does.
+    pub slow_read_ms: u64,
+    // Keep the last few raw `efibootmgr -v` outputs in the state directory
+    // for `swiftboot report`; see `report::save_captures`.
+    pub debug_capture: bool,
+    // Apply boot orders with a safety net; see `state::SafeApply`.
+    pub safe_apply: bool,
+    pub safe_apply_minutes: u64,
+    // Warn when an apply puts Windows ahead of a Linux entry.
+    pub warn_windows_first: bool,
+    pub confirm_destructive: ConfirmDestructive,
+    // How to reboot; None tries logind and then sudo, an empty list never
+    // reboots. See `efi::reboot`.
+    pub reboot_cmd: Option<Vec<String>>,
+    // Letter and digit keys in the Boot To panel jump to the entry whose
+    // name starts with what was typed, instead of their bindings.
+    pub type_ahead: bool,
+    // Skip the confirmations (and the password when sudo needs none): boot
+    // once arms BootNext and starts the countdown, an order is applied and
+    // only reported.
+    pub expert_mode: bool,
+    // Position numbers in front of the entries; see `ui::position_column`.
+    pub show_numbers: bool,
+    // Friendlier display names keyed by Boot#### ID, e.g. "0002" = "Windows".
+    pub aliases: HashMap<String, String>,
+    // Replacement key lists per action, e.g. `help = ["?", "F1", "h"]`.
+    pub keys: HashMap<KeyAction, Vec<String>>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            show_ids: false,
+            confirm_boot_once: true,
+            after_boot_once: RebootChoice::Countdown,
+            boot_to_initial: BootToInitial::FirstNonCurrent,
+            boot_once_method: BootOnceMethod::BootNext,
+            show_all: false,
+            show_firmware_entries: false,
+            efibootmgr_path: "efibootmgr".to_string(),
+            efibootmgr_extra_args: Vec::new(),
+            slow_read_ms: 2000,
+            debug_capture: false,
+            safe_apply: false,
+            safe_apply_minutes: 15,
+            warn_windows_first: true,
+            confirm_destructive: ConfirmDestructive::Button,
+            reboot_cmd: None,
+            type_ahead: false,
+            expert_mode: false,
+            show_numbers: true,
+            aliases: HashMap::new(),
+            keys: HashMap::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+// `swiftboot config check`: by the time this runs the file has been loaded
+// and validated, so all that's left is to show the result.
+pub fn check(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    match config_path() {
+        Some(path) if path.exists() => println!("# {} is valid.", path.display()),
+        Some(path) => println!("# {} does not exist; using defaults.", path.display()),
+        None => println!("# No config path (HOME is not set); using defaults."),
+    }
+    println!("# Effective configuration (defaults + file + command line):\n");
+    print!("{}", toml::to_string_pretty(config)?);
+    Ok(())
+}
+
+// Comments for `config init` and the man page, keyed by field name. The values themselves
+// come from `Config::default()` so the template can't drift from it.
+pub const FIELD_DOCS: &[(&str, &str)] = &[
+    ("show_ids", "Show the Boot#### ID column by default"),
+    (
+        "confirm_boot_once",
+        "Ask \"Boot once into: ...?\" before the password prompt in the Boot To panel",
+    ),
+    (
+        "after_boot_once",
+        "Highlighted choice after arming a boot-once entry: \"now\", \"countdown\" or \"later\"",
+    ),
+    (
+        "boot_to_initial",
+        "Entry the Boot To panel starts on: \"first\", \"first-non-current\" (skip the running system) or \"last-used\"",
+    ),
+    (
+        "boot_once_method",
+        "How to boot an entry once: \"bootnext\", \"reorder-temporarily\" (for firmware that ignores BootNext: put it first and restore the order on the next run) or \"ask\"",
+    ),
+    (
+        "show_all",
+        "Also show the Driver and SysPrep orders (same as --show-all)",
+    ),
+    (
+        "show_firmware_entries",
+        "List network boot (PXE), EFI shell, diagnostics and setup entries; hidden ones keep their place in every order written",
+    ),
+    (
+        "efibootmgr_path",
+        "The efibootmgr binary: a name looked up in PATH or a full path ($SWIFTBOOT_EFIBOOTMGR overrides it)",
+    ),
+    (
+        "efibootmgr_extra_args",
+        "Extra arguments added to every efibootmgr invocation",
+    ),
+    (
+        "slow_read_ms",
+        "Show how long reading the boot entries took in the status bar once it takes longer than this many milliseconds (up to 60000); 0 never shows it",
+    ),
+    (
+        "debug_capture",
+        "Keep the last 3 raw `efibootmgr -v` outputs in the state directory for `swiftboot report` (same as --capture)",
+    ),
+    (
+        "safe_apply",
+        "Keep the previous boot order and arm BootNext to the previous default when applying; run `swiftboot confirm` after a good boot",
+    ),
+    (
+        "safe_apply_minutes",
+        "Minutes to wait for `swiftboot confirm` before the previous order is restored, 1 to 1440",
+    ),
+    (
+        "warn_windows_first",
+        "Warn before applying an order that puts Windows first with a Linux entry below it",
+    ),
+    (
+        "confirm_destructive",
+        "How deleting an entry is confirmed: \"button\", or \"type-name\" to type the first 4 characters of its name before the Delete button works",
+    ),
+    (
+        "reboot_cmd",
+        "Command that reboots, e.g. [\"doas\", \"reboot\"]; unset tries systemctl, loginctl, then sudo; [] never reboots",
+    ),
+    (
+        "type_ahead",
+        "In the Boot To panel, typing letters and digits jumps to the entry whose name starts with them (they no longer trigger their key bindings there)",
+    ),
+    (
+        "expert_mode",
+        "Skip the confirmation popups: Boot To arms BootNext and starts the reboot countdown, an applied order is only reported (same as --yes)",
+    ),
+    (
+        "show_numbers",
+        "Show position numbers in the Boot Priority and Boot To panels; Boot To shows each entry's place in BootOrder, or – when it isn't in it",
+    ),
+    (
+        "aliases",
+        "Friendlier names for entries, keyed by Boot#### ID, e.g. \"0002\" = \"Windows\"",
+    ),
+    (
+        "keys",
+        "Replacement key lists per action, e.g. help = [\"?\", \"F1\"]",
+    ),
+    (
+        "profiles",
+        "Named boot orders to switch between, e.g. [profiles.gaming] order = [\"0002\", \"0001\"]",
+    ),
+];
+
+// Settings that are unset by default, so serializing the defaults skips
+// them. The template shows them commented out, with an example value.
+const UNSET_EXAMPLES: &[(&str, &str)] = &[("reboot_cmd", "[\"systemctl\", \"reboot\"]")];
+
+pub fn template() -> Result<String, Box<dyn std::error::Error>> {
+    let defaults = toml::to_string(&Config::default())?;
+    let mut out =
+        String::from("# swiftboot configuration. Every setting is shown with its default.\n");
+    let mut unset = UNSET_EXAMPLES.iter();
+
+    for line in defaults.lines() {
+        // Tables come last; the unset settings go just before them.
+        if line.starts_with('[') {
+            for (field, example) in unset.by_ref() {
+                let doc = FIELD_DOCS.iter().find(|(f, _)| f == field).map(|(_, d)| d);
+                out.push_str(&format!(
+                    "\n# {}\n# {} = {}\n",
+                    doc.unwrap_or(&""),
+                    field,
+                    example
+                ));
+            }
+        }
+        let key = line
+            .trim_start_matches('[')
+            .split([' ', '=', ']'])
+            .next()
+            .unwrap_or_default();
+        if let Some((_, doc)) = FIELD_DOCS.iter().find(|(field, _)| *field == key) {
+            if !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            out.push_str(&format!("# {}\n", doc));
+        }
+        if key == "keys" {
+            let actions = KeyAction::ALL.iter().map(|a| a.name()).collect::<Vec<_>>();
+            out.push_str(&format!("# Actions: {}\n", actions.join(", ")));
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+// `swiftboot config init`
+pub fn init(force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path().ok_or("Cannot locate the config directory (HOME is not set)")?;
+    if path.exists() && !force {
+        return Err(format!(
+            "{} already exists (use --force to overwrite it)",
+            path.display()
+        )
+        .into());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, template()?)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("swiftboot").join("config.toml"))
+}
+
+impl Config {
+    pub fn load() -> Result<Config, Box<dyn std::error::Error>> {
+        let Some(path) = config_path() else {
+            return Ok(Config::default());
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text)
+                .map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e).into()),
+        }
+    }
+
+    // Values the types allow but swiftboot can't work with are errors too,
+    // rather than surprises later.
+    fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|e| e.to_string())?;
+        if !(1..=MAX_SAFE_APPLY_MINUTES).contains(&config.safe_apply_minutes) {
+            return Err(format!(
+                "safe_apply_minutes must be between 1 and {}, not {}",
+                MAX_SAFE_APPLY_MINUTES, config.safe_apply_minutes
+            ));
+        }
+        if config.slow_read_ms > MAX_SLOW_READ_MS {
+            return Err(format!(
+                "slow_read_ms must be at most {} (0 turns it off), not {}",
+                MAX_SLOW_READ_MS, config.slow_read_ms
+            ));
+        }
+        if config.efibootmgr_path.trim().is_empty() {
+            return Err("efibootmgr_path must not be empty".to_string());
+        }
+        Ok(config)
+    }
+
+    // Loads the config file and checks the key bindings it overrides.
+    pub fn load_validated() -> Result<(Config, Keymap), Box<dyn std::error::Error>> {
+        let config = Config::load()?;
+        let keymap = Keymap::new(&config.keys).map_err(|e| {
+            let path = config_path().unwrap_or_default();
+            format!("Invalid [keys] in {}: {}", path.display(), e)
+        })?;
+        Ok((config, keymap))
+    }
+
+    // The efibootmgr binary to run: the environment variable wins over the
+    // config file.
+    pub fn efibootmgr(&self) -> String {
+        std::env::var(EFIBOOTMGR_ENV)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| self.efibootmgr_path.clone())
+    }
+
+    pub fn alias_for(&self, id: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(id))
+            .map(|(_, alias)| alias.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> String {
+        Config::parse(text).err().expect("an error")
+    }
+
+    #[test]
+    fn an_empty_file_is_the_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.safe_apply_minutes, 15);
+        assert_eq!(config.slow_read_ms, 2000);
+        assert!(config.confirm_boot_once);
+    }
+
+    #[test]
+    fn the_template_parses() {
+        let config = Config::parse(&template().unwrap()).unwrap();
+        assert_eq!(config.efibootmgr_path, "efibootmgr");
+    }
+
+    #[test]
+    fn settings_are_read() {
+        let config = Config::parse(
+            "safe_apply = true\nsafe_apply_minutes = 5\n\
+             [aliases]\n\"0002\" = \"Windows\"\n\
+             [profiles.gaming]\norder = [\"0002\"]\n",
+        )
+        .unwrap();
+        assert!(config.safe_apply);
+        assert_eq!(config.safe_apply_minutes, 5);
+        assert_eq!(config.alias_for("0002"), Some("Windows"));
+        assert_eq!(config.profiles["gaming"].order, ["0002"]);
+    }
+
+    #[test]
+    fn malformed_toml_is_an_error() {
+        assert!(Config::parse("show_ids = ").is_err());
+        assert!(Config::parse("[aliases").is_err());
+        assert!(Config::parse("show_ids = \"yes\"").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_named() {
+        let message = error("countdown_secnds = 3");
+        assert!(message.contains("countdown_secnds"), "{}", message);
+        let message = error("[profiles.work]\norder = []\nsugest = \"x\"");
+        assert!(message.contains("sugest"), "{}", message);
+    }
+
+    #[test]
+    fn out_of_range_values_are_errors() {
+        assert_eq!(
+            error("safe_apply_minutes = 0"),
+            "safe_apply_minutes must be between 1 and 1440, not 0"
+        );
+        assert!(Config::parse("safe_apply_minutes = 1441").is_err());
+        assert!(Config::parse("safe_apply_minutes = 1440").is_ok());
+        // Unsigned fields reject negative numbers while parsing.
+        assert!(Config::parse("safe_apply_minutes = -5").is_err());
+
+        assert!(Config::parse("slow_read_ms = 0").is_ok());
+        assert_eq!(
+            error("slow_read_ms = 3600000"),
+            "slow_read_ms must be at most 60000 (0 turns it off), not 3600000"
+        );
+        assert!(Config::parse("efibootmgr_path = \" \"").is_err());
+    }
+}
@@ -0,0 +1,607 @@
+use crate::app::{Action, App};
+use crate::batch;
+use crate::config::Config;
+use crate::keymap::Keymap;
+use crate::lock::{self, LockStatus};
+use crate::state::State;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use swiftboot::efi::{self, EntryId};
+use swiftboot::efivars;
+
+// pkexec's exit status when the authentication dialog was dismissed or
+// the user isn't allowed.
+const PKEXEC_DISMISSED: i32 = 126;
+const PKEXEC_NOT_AUTHORIZED: i32 = 127;
+
+// How long a client may take to send its request or read the answer before
+// it is dropped, so a stuck one can't hold up the others.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// One JSON object per line in each direction, e.g.
+// {"cmd":"boot-once","entry":"windows"} answered by
+// {"ok":true,"message":"The next boot will start Windows Boot Manager."}.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum Request {
+    List,
+    Status,
+    BootOnce { entry: String },
+    SetOrder { entries: Vec<String> },
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    // `list`: every entry, in boot order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<Entry>>,
+    // `status`: entry names; left out when there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    pub name: String,
+    pub active: bool,
+}
+
+impl Response {
+    fn failed(error: impl Into<String>) -> Response {
+        Response {
+            error: Some(error.into()),
+            ..Response::default()
+        }
+    }
+
+    fn done(message: String) -> Response {
+        Response {
+            ok: true,
+            message: Some(message),
+            ..Response::default()
+        }
+    }
+}
+
+pub fn socket_path(socket: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if let Some(socket) = socket {
+        return Ok(socket.to_path_buf());
+    }
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join("swiftboot.sock"))
+        .ok_or_else(|| "XDG_RUNTIME_DIR is not set; pass --socket".into())
+}
+
+// `swiftboot daemon`: answers requests on `socket` until killed. Every
+// request reads the boot setup afresh; writes go through pkexec, so a
+// desktop session asks for authorization with its own dialog.
+pub fn serve(
+    socket: &Path,
+    config: Config,
+    keymap: Keymap,
+) -> Result<(), Box<dyn std::error::Error>> {
+    efi::configure(&config.efibootmgr(), &config.efibootmgr_extra_args)?;
+
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(format!("A daemon is already listening on {}", socket.display()).into());
+        }
+        // Left behind by a daemon that was killed.
+        fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)
+        .map_err(|e| format!("Can't listen on {}: {}", socket.display(), e))?;
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    eprintln!("swiftboot: listening on {}", socket.display());
+
+    let owner = unsafe { libc::geteuid() };
+    accept(&listener, owner, CLIENT_TIMEOUT, |request| {
+        respond(request, &config, &keymap)
+    });
+    Ok(())
+}
+
+// One request per connection and one connection at a time, so two writes
+// never overlap; `timeout` keeps a client that goes quiet from blocking
+// the ones after it. Only clients running as `owner` are answered.
+fn accept(
+    listener: &UnixListener,
+    owner: u32,
+    timeout: Duration,
+    respond: impl Fn(Request) -> Response,
+) {
+    for stream in listener.incoming() {
+        let result =
+            stream.and_then(|mut stream| handle_client(&mut stream, owner, timeout, &respond));
+        if let Err(e) = result {
+            eprintln!("swiftboot: {}", e);
+        }
+    }
+}
+
+fn handle_client(
+    stream: &mut UnixStream,
+    owner: u32,
+    timeout: Duration,
+    respond: impl Fn(Request) -> Response,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    // The socket is private already; this also covers a --socket path in a
+    // shared directory.
+    if peer_uid(stream)? != owner {
+        return send(stream, &Response::failed("Permission denied"));
+    }
+
+    let mut line = String::new();
+    BufReader::new(&*stream).read_line(&mut line)?;
+    let response = match serde_json::from_str(&line) {
+        Ok(request) => respond(request),
+        Err(e) => Response::failed(format!("Bad request: {}", e)),
+    };
+    send(stream, &response)
+}
+
+fn send(stream: &mut UnixStream, response: &Response) -> io::Result<()> {
+    let mut line = serde_json::to_string(response)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+fn respond(request: Request, config: &Config, keymap: &Keymap) -> Response {
+    let (entries, status) = match efi::fetch_overview() {
+        Ok(overview) => overview,
+        Err(e) => return Response::failed(e.to_string()),
+    };
+    let app = App::new(
+        config.clone(),
+        keymap.clone(),
+        State::load(),
+        entries,
+        status,
+        Vec::new(),
+    );
+    answer(request, &app, write)
+}
+
+// The answer to `request` from the boot setup in `app`. Changes are made
+// by running efibootmgr with the given arguments through `write`.
+fn answer(request: Request, app: &App, write: impl Fn(&[&str]) -> Result<(), String>) -> Response {
+    let result = match request {
+        Request::List => Ok(Response {
+            ok: true,
+            entries: Some(
+                app.entries
+                    .iter()
+                    .map(|e| Entry {
+                        id: e.id.clone(),
+                        name: app.display_name(e).to_string(),
+                        active: e.active,
+                    })
+                    .collect(),
+            ),
+            ..Response::default()
+        }),
+        Request::Status => Ok(Response {
+            ok: true,
+            current: app.boot_current.as_ref().map(|id| app.entry_name(id)),
+            default: app.boot_order.first().map(|id| app.entry_name(id)),
+            next: app.boot_next_name(),
+            ..Response::default()
+        }),
+        Request::BootOnce { entry } => boot_once(app, &entry, &write),
+        Request::SetOrder { entries } => set_order(app, &entries, &write),
+    };
+    result.unwrap_or_else(Response::failed)
+}
+
+fn boot_once(
+    app: &App,
+    reference: &str,
+    write: impl Fn(&[&str]) -> Result<(), String>,
+) -> Result<Response, String> {
+    let id = batch::find_entry(app, reference, &[])?;
+    write(&["-n", &id])?;
+    Ok(Response::done(format!(
+        "The next boot will start {}.",
+        app.entry_name(&id)
+    )))
+}
+
+// As in batch mode: the named entries first, the rest after them in their
+// current order.
+fn set_order(
+    app: &App,
+    references: &[String],
+    write: impl Fn(&[&str]) -> Result<(), String>,
+) -> Result<Response, String> {
+    if references.is_empty() {
+        return Err("No entries given".to_string());
+    }
+    let mut order = Vec::new();
+    for reference in references {
+        let id = batch::find_entry(app, reference, &[])?;
+        if order.contains(&id) {
+            return Err(format!("{} is listed more than once", reference));
+        }
+        order.push(id);
+    }
+    let rest: Vec<String> = app
+        .draft_order()
+        .into_iter()
+        .filter(|id| !order.contains(id))
+        .collect();
+    order.extend(rest);
+    let Action::SetOrder(ids) = app.set_order_action(&order)? else {
+        unreachable!("set_order_action only returns SetOrder");
+    };
+    write(&["-o", &EntryId::join(&ids)])?;
+    // What was written, which can differ from what was asked for, e.g.
+    // with the rest pinned.
+    Ok(Response::done(format!(
+        "Boot order: {}",
+        app.order_names(&EntryId::to_strings(&ids))
+    )))
+}
+
+// Runs efibootmgr with `args` as root: directly when the daemon is root,
+// otherwise through pkexec.
+fn write(args: &[&str]) -> Result<(), String> {
+    // Held until the write is done, like any other writer.
+    let _lock = match lock::acquire() {
+        LockStatus::HeldBy(_) => return Err("Another swiftboot instance is running".to_string()),
+        LockStatus::Acquired(lock) => Some(lock),
+        LockStatus::Unavailable => None,
+    };
+    let mut command = if efivars::is_root() {
+        efi::efibootmgr_command(args)
+    } else {
+        let mut command = Command::new("pkexec");
+        command.args(efi::efibootmgr_args(args));
+        command
+    };
+    let output = command.output().map_err(|e| {
+        format!(
+            "Can't run {}: {}",
+            command.get_program().to_string_lossy(),
+            e
+        )
+    })?;
+    match output.status.code() {
+        Some(0) => Ok(()),
+        Some(PKEXEC_DISMISSED | PKEXEC_NOT_AUTHORIZED) if !efivars::is_root() => {
+            Err("Authorization was dismissed or denied".to_string())
+        }
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(if stderr.is_empty() {
+                format!("efibootmgr failed ({})", output.status)
+            } else {
+                stderr
+            })
+        }
+    }
+}
+
+// Sends `request` to the daemon on `socket` and reads its answer.
+fn ask(socket: &Path, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        format!(
+            "Can't reach the daemon at {} ({}); start it with `swiftboot daemon`",
+            socket.display(),
+            e
+        )
+    })?;
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut answer = String::new();
+    BufReader::new(&stream).read_line(&mut answer)?;
+    Ok(serde_json::from_str(&answer)
+        .map_err(|e| format!("Unexpected answer from the daemon: {}", e))?)
+}
+
+// `swiftboot client`: sends one request to the daemon and prints the answer.
+pub fn client(socket: &Path, request: &Request) -> Result<(), Box<dyn std::error::Error>> {
+    print_answer(&mut io::stdout().lock(), socket, request)
+}
+
+fn print_answer(
+    out: &mut impl Write,
+    socket: &Path,
+    request: &Request,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = ask(socket, request)?;
+    if !response.ok {
+        return Err(response
+            .error
+            .unwrap_or_else(|| "The daemon reported an error".to_string())
+            .into());
+    }
+
+    match request {
+        Request::List => {
+            for entry in response.entries.unwrap_or_default() {
+                let inactive = if entry.active { "" } else { " (inactive)" };
+                writeln!(out, "Boot{}  {}{}", entry.id, entry.name, inactive)?;
+            }
+        }
+        Request::Status => {
+            let text = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+            writeln!(
+                out,
+                "current={} default={} next={}",
+                text(response.current),
+                text(response.default),
+                text(response.next)
+            )?;
+        }
+        Request::BootOnce { .. } | Request::SetOrder { .. } => {
+            if let Some(message) = response.message {
+                writeln!(out, "{}", message)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+    use std::{
+        io::Read,
+        sync::{Arc, Mutex},
+        thread,
+        time::Instant,
+    };
+
+    // A daemon on a fresh socket for clients running as `owner`, answering
+    // with `respond` instead of the real boot setup.
+    fn daemon_for(
+        name: &str,
+        owner: u32,
+        respond: impl Fn(Request) -> Response + Send + 'static,
+    ) -> PathBuf {
+        let socket =
+            std::env::temp_dir().join(format!("swiftboot-{}-{}.sock", name, std::process::id()));
+        let _ = fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        thread::spawn(move || accept(&listener, owner, Duration::from_millis(200), respond));
+        socket
+    }
+
+    fn daemon(name: &str, respond: impl Fn(Request) -> Response + Send + 'static) -> PathBuf {
+        daemon_for(name, unsafe { libc::geteuid() }, respond)
+    }
+
+    // A daemon answering from the test fixture's entries, recording the
+    // efibootmgr arguments it would write with.
+    fn fixture_daemon(name: &str) -> (PathBuf, Arc<Mutex<Vec<String>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&written);
+        let socket = daemon(name, move |request| {
+            let app = testing::app();
+            answer(request, &app, |args: &[&str]| {
+                log.lock().unwrap().push(args.join(" "));
+                Ok(())
+            })
+        });
+        (socket, written)
+    }
+
+    // What `swiftboot client` prints for `request`, or its error.
+    fn client_output(socket: &Path, request: Request) -> Result<String, String> {
+        let mut out = Vec::new();
+        print_answer(&mut out, socket, &request).map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn fake(request: Request) -> Response {
+        match request {
+            Request::List => Response {
+                ok: true,
+                entries: Some(vec![Entry {
+                    id: "0001".to_string(),
+                    name: "Fedora".to_string(),
+                    active: true,
+                }]),
+                ..Response::default()
+            },
+            Request::Status => Response {
+                ok: true,
+                current: Some("Fedora".to_string()),
+                ..Response::default()
+            },
+            Request::BootOnce { entry } => Response::done(format!("once {}", entry)),
+            Request::SetOrder { entries } => Response::failed(format!("no {}", entries.join(","))),
+        }
+    }
+
+    #[test]
+    fn requests_round_trip_over_the_socket() {
+        let socket = daemon("round-trip", fake);
+        let list = ask(&socket, &Request::List).unwrap();
+        assert!(list.ok);
+        let entries = list.entries.unwrap();
+        assert_eq!(entries[0].id, "0001");
+        assert_eq!(entries[0].name, "Fedora");
+
+        let status = ask(&socket, &Request::Status).unwrap();
+        assert_eq!(status.current.as_deref(), Some("Fedora"));
+        assert!(status.next.is_none());
+
+        let once = Request::BootOnce {
+            entry: "windows".to_string(),
+        };
+        assert_eq!(
+            ask(&socket, &once).unwrap().message.as_deref(),
+            Some("once windows")
+        );
+        let order = Request::SetOrder {
+            entries: vec!["1".to_string(), "2".to_string()],
+        };
+        let refused = ask(&socket, &order).unwrap();
+        assert!(!refused.ok);
+        assert_eq!(refused.error.as_deref(), Some("no 1,2"));
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn a_bad_request_is_answered_with_an_error() {
+        let socket = daemon("bad-request", fake);
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        stream.write_all(b"{\"cmd\":\"reboot\"}\n").unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        let response: Response = serde_json::from_str(&answer).unwrap();
+        assert!(!response.ok);
+        assert!(response.error.unwrap().starts_with("Bad request:"));
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn a_silent_client_does_not_block_the_next() {
+        let socket = daemon("silent", fake);
+        let start = Instant::now();
+        let _silent = UnixStream::connect(&socket).unwrap();
+        assert!(ask(&socket, &Request::Status).unwrap().ok);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn one_request_per_connection() {
+        let socket = daemon("one-request", fake);
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        stream
+            .write_all(b"{\"cmd\":\"status\"}\n{\"cmd\":\"list\"}\n")
+            .unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        // Answered once, then the connection is closed.
+        assert_eq!(answer.lines().count(), 1);
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn the_client_prints_what_the_daemon_reads() {
+        let (socket, written) = fixture_daemon("fixture-read");
+        assert_eq!(
+            client_output(&socket, Request::List).unwrap(),
+            "Boot0001  Fedora\nBoot0002  Windows Boot Manager\nBoot0003  UEFI: USB\n"
+        );
+        assert_eq!(
+            client_output(&socket, Request::Status).unwrap(),
+            "current=Fedora default=Fedora next=-\n"
+        );
+        assert!(written.lock().unwrap().is_empty());
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn the_client_prints_what_the_daemon_wrote() {
+        let (socket, written) = fixture_daemon("fixture-write");
+        let once = Request::BootOnce {
+            entry: "windows".to_string(),
+        };
+        assert_eq!(
+            client_output(&socket, once).unwrap(),
+            "The next boot will start Windows Boot Manager.\n"
+        );
+        // Named entries first, the rest after them in their order.
+        let order = Request::SetOrder {
+            entries: vec!["usb".to_string(), "Boot0002".to_string()],
+        };
+        assert_eq!(
+            client_output(&socket, order).unwrap(),
+            "Boot order: UEFI: USB, Windows Boot Manager, Fedora\n"
+        );
+        assert_eq!(*written.lock().unwrap(), ["-n 0002", "-o 0003,0002,0001"]);
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn the_client_reports_what_the_daemon_refused() {
+        let (socket, written) = fixture_daemon("fixture-refuse");
+        let unknown = Request::BootOnce {
+            entry: "Boot0009".to_string(),
+        };
+        assert_eq!(
+            client_output(&socket, unknown).unwrap_err(),
+            "No Boot0009 entry"
+        );
+        let twice = Request::SetOrder {
+            entries: vec!["fedora".to_string(), "Boot0001".to_string()],
+        };
+        assert_eq!(
+            client_output(&socket, twice).unwrap_err(),
+            "Boot0001 is listed more than once"
+        );
+        let empty = Request::SetOrder {
+            entries: Vec::new(),
+        };
+        assert_eq!(
+            client_output(&socket, empty).unwrap_err(),
+            "No entries given"
+        );
+        assert!(written.lock().unwrap().is_empty());
+        let _ = fs::remove_file(socket);
+    }
+
+    #[test]
+    fn other_users_are_turned_away() {
+        let other = unsafe { libc::geteuid() } + 1;
+        let socket = daemon_for("other-user", other, fake);
+        assert_eq!(
+            client_output(&socket, Request::Status).unwrap_err(),
+            "Permission denied"
+        );
+        let _ = fs::remove_file(socket);
+    }
+}
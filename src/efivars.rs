@@ -0,0 +1,666 @@
+use crate::efi::{BootEntry, BootStatus, CommandOutput, SudoEvent};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+// Direct access to the boot variables through efivarfs: reads for when
+// efibootmgr isn't available, and writes for `--backend efivarfs`, which
+// needs root and replaces efibootmgr and sudo altogether.
+
+const EFIVARS: &str = "/sys/firmware/efi/efivars";
+static BASE: OnceLock<PathBuf> = OnceLock::new();
+// EFI_GLOBAL_VARIABLE, the vendor GUID of the Boot* variables.
+const GLOBAL: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+// LOAD_OPTION_ACTIVE in an EFI_LOAD_OPTION's attributes.
+const LOAD_OPTION_ACTIVE: u32 = 0x1;
+
+// NON_VOLATILE | BOOTSERVICE_ACCESS | RUNTIME_ACCESS, for variables that
+// don't exist yet; existing ones keep their own.
+const DEFAULT_ATTRIBUTES: u32 = 0x7;
+
+// FS_IMMUTABLE_FL from linux/fs.h. efivarfs sets it on most variables so a
+// stray `rm` can't brick the machine.
+const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+
+// Points every read and write at `dir` instead of the real efivarfs, e.g.
+// a directory laid out like it for tests. Only the first call counts.
+pub fn set_base(dir: &Path) {
+    let _ = BASE.set(dir.to_path_buf());
+}
+
+fn base() -> &'static Path {
+    BASE.get().map_or(Path::new(EFIVARS), PathBuf::as_path)
+}
+
+pub fn readable() -> bool {
+    fs::read_dir(base()).is_ok()
+}
+
+// Whether the variables themselves can be read, not just listed: some
+// distros make the files readable by root alone.
+pub fn values_readable() -> bool {
+    read_var("BootOrder")
+        .or_else(|_| read_var("BootCurrent"))
+        .is_ok()
+}
+
+// Below this much free space, a write may not fit: firmware appends each
+// new copy of a variable and only reclaims the old ones at the next boot.
+const LOW_SPACE: u64 = 8 * 1024;
+
+// The variable store's size and free space in bytes.
+#[derive(Clone, Copy)]
+pub struct NvramSpace {
+    pub free: u64,
+    pub total: u64,
+}
+
+impl NvramSpace {
+    // From statvfs figures. efivarfs reports the firmware's own
+    // QueryVariableInfo numbers since Linux 6.8; older kernels report zero
+    // blocks, which says nothing, so that is None.
+    pub fn from_statvfs(blocks: u64, free_blocks: u64, block_size: u64) -> Option<NvramSpace> {
+        (blocks > 0 && block_size > 0).then(|| NvramSpace {
+            free: free_blocks.min(blocks) * block_size,
+            total: blocks * block_size,
+        })
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.free < LOW_SPACE
+    }
+}
+
+// None when efivarfs isn't mounted or the kernel doesn't report the space.
+pub fn nvram_space() -> Option<NvramSpace> {
+    let path = std::ffi::CString::new(base().as_os_str().as_encoded_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    NvramSpace::from_statvfs(
+        stat.f_blocks as u64,
+        stat.f_bfree as u64,
+        stat.f_frsize as u64,
+    )
+}
+
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+fn var_path(name: &str) -> PathBuf {
+    base().join(format!("{}-{}", name, GLOBAL))
+}
+
+// A variable's data, without the four attribute bytes efivarfs prepends.
+fn read_var(name: &str) -> io::Result<Vec<u8>> {
+    let data = fs::read(var_path(name))?;
+    Ok(data.get(4..).unwrap_or_default().to_vec())
+}
+
+// BootOrder-style contents: an array of little-endian u16s.
+fn parse_ids(data: &[u8]) -> Option<Vec<u16>> {
+    data.len().is_multiple_of(2).then(|| {
+        data.chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect()
+    })
+}
+
+fn encode_ids(ids: &[String]) -> Result<Vec<u8>, String> {
+    ids.iter()
+        .map(|id| {
+            u16::from_str_radix(id, 16)
+                .map(u16::to_le_bytes)
+                .map_err(|_| format!("'{}' is not a Boot#### number", id))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|ids| ids.concat())
+}
+
+fn read_u16(name: &str) -> Option<u16> {
+    let data = read_var(name).ok()?;
+    Some(u16::from_le_bytes(data.get(..2)?.try_into().ok()?))
+}
+
+pub fn read_status() -> BootStatus {
+    let order = read_var("BootOrder")
+        .ok()
+        .and_then(|data| parse_ids(&data))
+        .map(|ids| ids.iter().map(|n| format!("{:04X}", n)).collect())
+        .unwrap_or_default();
+    BootStatus {
+        current: read_u16("BootCurrent").map(|n| format!("{:04X}", n)),
+        order,
+        invalid_order: Vec::new(),
+        next: read_u16("BootNext").map(|n| format!("{:04X}", n)),
+        timeout: read_u16("Timeout"),
+    }
+}
+
+// The Boot#### (or Driver####, SysPrep####) entries, sorted by ID.
+pub fn read_entries(prefix: &str) -> Result<Vec<BootEntry>, Box<dyn std::error::Error>> {
+    let suffix = format!("-{}", GLOBAL);
+    let mut entries = Vec::new();
+    for dirent in fs::read_dir(base())? {
+        let file_name = dirent?.file_name();
+        let Some(id) = file_name
+            .to_str()
+            .and_then(|n| n.strip_prefix(prefix)?.strip_suffix(&suffix))
+            .filter(|id| id.len() == 4 && id.chars().all(|c| c.is_ascii_hexdigit()))
+        else {
+            continue;
+        };
+        let id = id.to_ascii_uppercase();
+        if let Some(entry) = read_var(&format!("{}{}", prefix, id))
+            .ok()
+            .and_then(|data| parse_load_option(&id, &data))
+        {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(entries)
+}
+
+// EFI_LOAD_OPTION: attributes, the device path length, the description
+// as a NUL-terminated UCS-2 string, then the device path itself.
+fn parse_load_option(id: &str, data: &[u8]) -> Option<BootEntry> {
+    let attributes = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+    let path_len = u16::from_le_bytes(data.get(4..6)?.try_into().ok()?) as usize;
+    let (name, name_len) = ucs2(data.get(6..)?);
+    let path_start = 6 + name_len;
+    let path = data
+        .get(path_start..path_start + path_len)
+        .unwrap_or_default();
+
+    Some(BootEntry {
+        id: id.to_string(),
+        name,
+        active: attributes & LOAD_OPTION_ACTIVE != 0,
+        device_path: device_path_text(path),
+        unknown: false,
+    })
+}
+
+// Decodes up to the first NUL; also returns the bytes used, NUL included.
+fn ucs2(data: &[u8]) -> (String, usize) {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    let used = ((units.len() + 1) * 2).min(data.len());
+    (String::from_utf16_lossy(&units), used)
+}
+
+// Renders a device path the way `efibootmgr -v` does for the nodes the
+// rest of swiftboot looks at: HD, File, USB, CDROM and the network ones.
+// Other nodes are written as `Path(type,subtype)`.
+fn device_path_text(mut data: &[u8]) -> String {
+    let mut text = String::new();
+    while data.len() >= 4 {
+        let (kind, subtype) = (data[0], data[1]);
+        let len = u16::from_le_bytes([data[2], data[3]]) as usize;
+        if len < 4 || len > data.len() {
+            break;
+        }
+        let body = &data[4..len];
+        data = &data[len..];
+
+        let node = match (kind, subtype) {
+            // End of the whole path, or of one instance.
+            (0x7f, 0xff) => break,
+            (0x7f, _) => {
+                text.push(',');
+                continue;
+            }
+            (1, 1) if body.len() >= 2 => format!("Pci(0x{:x},0x{:x})", body[1], body[0]),
+            (2, 1) if body.len() >= 8 => {
+                let hid = u32::from_le_bytes(body[..4].try_into().unwrap());
+                let uid = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                // PNP0A03 and PNP0A08, the PCI root bridges.
+                if hid == 0x0a03_41d0 || hid == 0x0a08_41d0 {
+                    format!("PciRoot(0x{:x})", uid)
+                } else {
+                    format!("Acpi(0x{:x},0x{:x})", hid, uid)
+                }
+            }
+            (3, 5) if body.len() >= 2 => format!("USB({},{})", body[0], body[1]),
+            (3, 11) if body.len() >= 33 => {
+                let mac: String = body[..6].iter().map(|b| format!("{:02x}", b)).collect();
+                format!("MAC({},{})", mac, body[32])
+            }
+            (3, 12) => "IPv4()".to_string(),
+            (3, 13) => "IPv6()".to_string(),
+            (3, 24) => format!("Uri({})", String::from_utf8_lossy(body)),
+            (4, 1) if body.len() >= 38 => hard_drive(body),
+            (4, 2) if body.len() >= 4 => format!(
+                "CDROM({})",
+                u32::from_le_bytes(body[..4].try_into().unwrap())
+            ),
+            (4, 4) => format!("File({})", ucs2(body).0),
+            _ => format!("Path({},{})", kind, subtype),
+        };
+        if !text.is_empty() && !text.ends_with(',') {
+            text.push('/');
+        }
+        text.push_str(&node);
+    }
+    text
+}
+
+// HD(partition,GPT|MBR,signature,start,size)
+fn hard_drive(body: &[u8]) -> String {
+    let number = u32::from_le_bytes(body[..4].try_into().unwrap());
+    let start = u64::from_le_bytes(body[4..12].try_into().unwrap());
+    let size = u64::from_le_bytes(body[12..20].try_into().unwrap());
+    let signature = &body[20..36];
+    match body[37] {
+        2 => format!(
+            "HD({},GPT,{},0x{:x},0x{:x})",
+            number,
+            guid(signature),
+            start,
+            size
+        ),
+        1 => format!(
+            "HD({},MBR,0x{:x},0x{:x},0x{:x})",
+            number,
+            u32::from_le_bytes(signature[..4].try_into().unwrap()),
+            start,
+            size
+        ),
+        _ => format!("HD({},0x{:x},0x{:x})", number, start, size),
+    }
+}
+
+// GUIDs are stored with the first three fields little-endian.
+fn guid(b: &[u8]) -> String {
+    format!(
+        "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{}",
+        b[3],
+        b[2],
+        b[1],
+        b[0],
+        b[5],
+        b[4],
+        b[7],
+        b[6],
+        b[8],
+        b[9],
+        b[10..16]
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect::<String>()
+    )
+}
+
+// A change written straight to efivarfs.
+pub enum Write {
+    // BootOrder, DriverOrder or SysPrepOrder.
+    Order(&'static str, Vec<String>),
+    Next(String),
+    Timeout(u16),
+    // Sets or clears LOAD_OPTION_ACTIVE on Boot####.
+    Active(String, bool),
+    // Removes Boot####, and its ID from BootOrder.
+    Delete(String),
+    // Removes BootNext.
+    ClearNext,
+}
+
+// Runs the write on a thread and reports like `efi::spawn_sudo_command`, so
+// the app drives both the same way.
+pub fn spawn_write(change: Write) -> Receiver<SudoEvent> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let event = match write(&change) {
+            Ok(()) => SudoEvent::Done(CommandOutput::succeeded()),
+            Err(e) => SudoEvent::Done(CommandOutput::failed(e)),
+        };
+        let _ = tx.send(event);
+    });
+    rx
+}
+
+// The current contents have to read back and parse before anything is
+// overwritten: if they don't, something else is going on and writing
+// blind could leave the firmware with garbage. A variable that doesn't
+// exist yet is simply created.
+fn write(change: &Write) -> Result<(), String> {
+    let (name, data) = match change {
+        Write::Order(name, ids) => {
+            check_current(name, |data| parse_ids(data).is_some())?;
+            (name.to_string(), encode_ids(ids)?)
+        }
+        Write::Next(id) => {
+            check_current("BootNext", |data| data.len() == 2)?;
+            (
+                "BootNext".to_string(),
+                encode_ids(std::slice::from_ref(id))?,
+            )
+        }
+        Write::Timeout(seconds) => {
+            check_current("Timeout", |data| data.len() == 2)?;
+            ("Timeout".to_string(), seconds.to_le_bytes().to_vec())
+        }
+        Write::Active(id, active) => {
+            let (name, mut data) = read_load_option(id)?;
+            if *active {
+                data[0] |= LOAD_OPTION_ACTIVE as u8;
+            } else {
+                data[0] &= !(LOAD_OPTION_ACTIVE as u8);
+            }
+            (name, data)
+        }
+        Write::Delete(id) => return delete(id),
+        Write::ClearNext => return remove_var("BootNext"),
+    };
+
+    write_var(&name, &data).map_err(|e| format!("Writing {} failed: {}", name, e))?;
+    match read_var(&name) {
+        Ok(written) if written == data => Ok(()),
+        _ => Err(format!("{} reads back differently after writing", name)),
+    }
+}
+
+fn read_load_option(id: &str) -> Result<(String, Vec<u8>), String> {
+    let name = format!("Boot{}", id);
+    let data = read_var(&name).map_err(|e| format!("Can't read {}: {}", name, e))?;
+    if parse_load_option(id, &data).is_none() {
+        return Err(format!(
+            "{} isn't a valid load option; not changing it",
+            name
+        ));
+    }
+    Ok((name, data))
+}
+
+// Takes the ID out of BootOrder first, so the order never names an entry
+// that's gone, then removes the entry itself.
+fn delete(id: &str) -> Result<(), String> {
+    let (name, _) = read_load_option(id)?;
+    let order = read_var("BootOrder").ok().and_then(|data| parse_ids(&data));
+    if let Some(order) = order {
+        let kept: Vec<String> = order
+            .iter()
+            .map(|n| format!("{:04X}", n))
+            .filter(|other| other != id)
+            .collect();
+        if kept.len() < order.len() {
+            write(&Write::Order("BootOrder", kept))?;
+        }
+    }
+
+    remove_var(&name)
+}
+
+// A variable that is already gone is fine.
+fn remove_var(name: &str) -> Result<(), String> {
+    let path = var_path(name);
+    match File::open(&path)
+        .and_then(|file| clear_immutable(&file))
+        .and_then(|_| fs::remove_file(&path))
+    {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(format!("Removing {} failed: {}", name, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_current(name: &str, parses: impl Fn(&[u8]) -> bool) -> Result<(), String> {
+    match read_var(name) {
+        Ok(data) if parses(&data) => Ok(()),
+        Ok(_) => Err(format!(
+            "{} has unexpected contents; not overwriting it",
+            name
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Can't read {}: {}", name, e)),
+    }
+}
+
+// efivarfs wants the attributes and the data in a single write.
+fn write_var(name: &str, data: &[u8]) -> io::Result<()> {
+    let path = var_path(name);
+    let attributes = match fs::read(&path) {
+        Ok(old) if old.len() >= 4 => u32::from_le_bytes(old[..4].try_into().unwrap()),
+        _ => DEFAULT_ATTRIBUTES,
+    };
+    let was_immutable = path.exists() && clear_immutable(&File::open(&path)?)?;
+
+    let mut contents = attributes.to_le_bytes().to_vec();
+    contents.extend_from_slice(data);
+    // Every write replaces the whole variable; there's nothing to truncate.
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+    file.write_all(&contents)?;
+    // Put the protection back, as libefivar does. The data is written by
+    // now, so failing to doesn't fail the write.
+    if was_immutable {
+        let _ = set_flags(&file, get_flags(&file)? | FS_IMMUTABLE_FL);
+    }
+    Ok(())
+}
+
+// Returns whether the flag was set, so a write can restore it.
+fn clear_immutable(file: &File) -> io::Result<bool> {
+    let flags = get_flags(file)?;
+    if flags & FS_IMMUTABLE_FL == 0 {
+        return Ok(false);
+    }
+    set_flags(file, flags & !FS_IMMUTABLE_FL)?;
+    Ok(true)
+}
+
+fn get_flags(file: &File) -> io::Result<libc::c_int> {
+    let mut flags: libc::c_int = 0;
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(flags)
+}
+
+fn set_flags(file: &File, flags: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Load options assembled by hand to the UEFI spec's EFI_LOAD_OPTION
+    // layout, as efivarfs returns them after the attributes: a Fedora-style
+    // entry, and an inactive Windows Boot Manager-style one with optional
+    // data after the device path. The GUIDs and LBAs are made up; these are
+    // not dumps from a real machine.
+    const FEDORA: &[u8] = &[
+        0x01, 0x00, 0x00, 0x00, 0x62, 0x00, 0x46, 0x00, 0x65, 0x00, 0x64, 0x00, 0x6f, 0x00, 0x72,
+        0x00, 0x61, 0x00, 0x00, 0x00, 0x04, 0x01, 0x2a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1e,
+        0x2c, 0x7a, 0x8f, 0x3d, 0x5b, 0x6f, 0x4e, 0x9a, 0x0b, 0x1c, 0x2d, 0x3e, 0x4f, 0x5a, 0x6b,
+        0x02, 0x02, 0x04, 0x04, 0x34, 0x00, 0x5c, 0x00, 0x45, 0x00, 0x46, 0x00, 0x49, 0x00, 0x5c,
+        0x00, 0x66, 0x00, 0x65, 0x00, 0x64, 0x00, 0x6f, 0x00, 0x72, 0x00, 0x61, 0x00, 0x5c, 0x00,
+        0x73, 0x00, 0x68, 0x00, 0x69, 0x00, 0x6d, 0x00, 0x78, 0x00, 0x36, 0x00, 0x34, 0x00, 0x2e,
+        0x00, 0x65, 0x00, 0x66, 0x00, 0x69, 0x00, 0x00, 0x00, 0x7f, 0xff, 0x04, 0x00,
+    ];
+    const WINDOWS: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x74, 0x00, 0x57, 0x00, 0x69, 0x00, 0x6e, 0x00, 0x64, 0x00, 0x6f,
+        0x00, 0x77, 0x00, 0x73, 0x00, 0x20, 0x00, 0x42, 0x00, 0x6f, 0x00, 0x6f, 0x00, 0x74, 0x00,
+        0x20, 0x00, 0x4d, 0x00, 0x61, 0x00, 0x6e, 0x00, 0x61, 0x00, 0x67, 0x00, 0x65, 0x00, 0x72,
+        0x00, 0x00, 0x00, 0x04, 0x01, 0x2a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3d, 0x2c, 0x1b,
+        0x0a, 0x5f, 0x4e, 0x71, 0x60, 0x82, 0x93, 0xa4, 0xb5, 0xc6, 0xd7, 0xe8, 0xf9, 0x02, 0x02,
+        0x04, 0x04, 0x46, 0x00, 0x5c, 0x00, 0x45, 0x00, 0x46, 0x00, 0x49, 0x00, 0x5c, 0x00, 0x4d,
+        0x00, 0x69, 0x00, 0x63, 0x00, 0x72, 0x00, 0x6f, 0x00, 0x73, 0x00, 0x6f, 0x00, 0x66, 0x00,
+        0x74, 0x00, 0x5c, 0x00, 0x42, 0x00, 0x6f, 0x00, 0x6f, 0x00, 0x74, 0x00, 0x5c, 0x00, 0x62,
+        0x00, 0x6f, 0x00, 0x6f, 0x00, 0x74, 0x00, 0x6d, 0x00, 0x67, 0x00, 0x66, 0x00, 0x77, 0x00,
+        0x2e, 0x00, 0x65, 0x00, 0x66, 0x00, 0x69, 0x00, 0x00, 0x00, 0x7f, 0xff, 0x04, 0x00, 0x57,
+        0x49, 0x4e, 0x44, 0x4f, 0x57, 0x53, 0x00, 0x01, 0x00, 0x00, 0x00, 0x88, 0x00, 0x00, 0x00,
+    ];
+    // BootOrder 0001,0002,000A,2001.
+    const BOOT_ORDER: &[u8] = &[0x01, 0x00, 0x02, 0x00, 0x0a, 0x00, 0x01, 0x20];
+
+    fn strings(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn boot_order_round_trips() {
+        assert_eq!(parse_ids(BOOT_ORDER), Some(vec![0x1, 0x2, 0xa, 0x2001]));
+        let ids = strings(&["0001", "0002", "000A", "2001"]);
+        assert_eq!(encode_ids(&ids).unwrap(), BOOT_ORDER);
+        // Lowercase hex encodes the same.
+        assert_eq!(
+            encode_ids(&strings(&["0001", "0002", "000a", "2001"])).unwrap(),
+            BOOT_ORDER
+        );
+        assert_eq!(parse_ids(&[]), Some(vec![]));
+        assert_eq!(encode_ids(&[]).unwrap(), Vec::<u8>::new());
+    }
+
+    // Composed, parsed and composed again, the bytes don't change.
+    fn recomposed(ids: &[String]) -> Vec<u8> {
+        let data = encode_ids(ids).unwrap();
+        let parsed: Vec<String> = parse_ids(&data)
+            .unwrap()
+            .iter()
+            .map(|n| format!("{:04X}", n))
+            .collect();
+        assert_eq!(encode_ids(&parsed).unwrap(), data);
+        data
+    }
+
+    #[test]
+    fn boot_order_and_boot_next_recompose_byte_for_byte() {
+        for ids in [
+            &["0001", "0002", "000A", "2001", "FFFF"][..],
+            &["0000"],
+            &["fffe", "0003"],
+            &[],
+        ] {
+            let data = recomposed(&strings(ids));
+            assert_eq!(data.len(), 2 * ids.len());
+        }
+        // BootNext is a single ID.
+        assert_eq!(recomposed(&strings(&["2001"])), [0x01, 0x20]);
+    }
+
+    // The only test pointing the base at a directory of its own: it is set
+    // once per process.
+    #[test]
+    fn writes_keep_the_attribute_header() {
+        let dir = std::env::temp_dir().join(format!("swiftboot-efivars-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        set_base(&dir);
+        // Runtime-only attributes, which a write has to keep.
+        let mut existing = 0x6u32.to_le_bytes().to_vec();
+        existing.extend_from_slice(BOOT_ORDER);
+        fs::write(var_path("BootOrder"), &existing).unwrap();
+
+        let order = strings(&["2001", "0001", "000A", "0002"]);
+        write(&Write::Order("BootOrder", order.clone())).unwrap();
+        let mut expected = 0x6u32.to_le_bytes().to_vec();
+        expected.extend_from_slice(&encode_ids(&order).unwrap());
+        assert_eq!(fs::read(var_path("BootOrder")).unwrap(), expected);
+
+        // A new variable gets the default attributes.
+        write(&Write::Next("2001".to_string())).unwrap();
+        assert_eq!(
+            fs::read(var_path("BootNext")).unwrap(),
+            [0x07, 0, 0, 0, 0x01, 0x20]
+        );
+
+        let status = read_status();
+        assert_eq!(status.order, order);
+        assert_eq!(status.next.as_deref(), Some("2001"));
+        // Written back as read, the bytes are the same.
+        write(&Write::Order("BootOrder", status.order)).unwrap();
+        assert_eq!(fs::read(var_path("BootOrder")).unwrap(), expected);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bad_ids_are_not_written() {
+        assert_eq!(parse_ids(&BOOT_ORDER[..3]), None);
+        assert!(encode_ids(&strings(&["0001", "Boot0002"])).is_err());
+        assert!(encode_ids(&strings(&["10000"])).is_err());
+    }
+
+    #[test]
+    fn load_options_parse() {
+        let entry = parse_load_option("0001", FEDORA).unwrap();
+        assert_eq!(entry.name, "Fedora");
+        assert!(entry.active);
+        assert_eq!(
+            entry.device_path,
+            "HD(1,GPT,8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b,0x800,0x100000)/\
+             File(\\EFI\\fedora\\shimx64.efi)"
+        );
+
+        let entry = parse_load_option("0002", WINDOWS).unwrap();
+        assert_eq!(entry.name, "Windows Boot Manager");
+        assert!(!entry.active);
+        assert!(
+            entry
+                .device_path
+                .ends_with("/File(\\EFI\\Microsoft\\Boot\\bootmgfw.efi)")
+        );
+        assert!(entry.device_path.contains(",0x800,0x32000)"));
+    }
+
+    #[test]
+    fn toggling_active_changes_only_the_attribute_bit() {
+        let mut data = WINDOWS.to_vec();
+        data[0] |= LOAD_OPTION_ACTIVE as u8;
+        assert!(parse_load_option("0002", &data).unwrap().active);
+        assert_eq!(data[1..], WINDOWS[1..]);
+    }
+
+    #[test]
+    fn truncated_load_options_are_rejected_or_cut_short() {
+        assert!(parse_load_option("0001", &FEDORA[..5]).is_none());
+        // A device path running past the end is left out rather than read.
+        let entry = parse_load_option("0001", &FEDORA[..40]).unwrap();
+        assert_eq!(entry.name, "Fedora");
+        assert_eq!(entry.device_path, "");
+    }
+
+    #[test]
+    fn nvram_space_comes_from_statvfs() {
+        let space = NvramSpace::from_statvfs(64, 16, 1024).unwrap();
+        assert_eq!((space.free, space.total), (16 * 1024, 64 * 1024));
+        assert!(!space.is_low());
+        // Older kernels report no blocks at all.
+        assert!(NvramSpace::from_statvfs(0, 0, 4096).is_none());
+        assert!(NvramSpace::from_statvfs(64, 0, 0).is_none());
+        // Free never exceeds the total.
+        assert_eq!(NvramSpace::from_statvfs(4, 9, 1024).unwrap().free, 4096);
+    }
+
+    #[test]
+    fn less_than_8_kib_free_is_low() {
+        assert!(NvramSpace::from_statvfs(64, 7, 1024).unwrap().is_low());
+        assert!(!NvramSpace::from_statvfs(64, 8, 1024).unwrap().is_low());
+        assert!(NvramSpace::from_statvfs(64, 0, 1024).unwrap().is_low());
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::{
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+// fwupdmgr waits on the fwupd daemon over D-Bus; a hung daemon mustn't
+// hold up startup.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct Devices {
+    #[serde(rename = "Devices", default)]
+    devices: Vec<Device>,
+}
+
+#[derive(Deserialize)]
+struct Device {
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Flags", default)]
+    flags: Vec<String>,
+    // A string in current fwupd, a number in some older releases.
+    #[serde(rename = "UpdateState", default)]
+    update_state: serde_json::Value,
+}
+
+impl Device {
+    fn update_scheduled(&self) -> bool {
+        self.flags.iter().any(|f| f == "needs-reboot")
+            || matches!(
+                &self.update_state,
+                serde_json::Value::String(s) if s == "pending" || s == "needs-reboot"
+            )
+    }
+}
+
+// The name of a device with a firmware update staged for the next reboot,
+// e.g. a UEFI capsule scheduled by `fwupdmgr update`. The firmware runs it
+// before looking at BootNext or BootOrder. None when fwupd isn't installed,
+// doesn't answer in time, or has nothing scheduled.
+pub fn pending_update() -> Option<String> {
+    let child = Command::new("fwupdmgr")
+        .args(["get-devices", "--json"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let pid = child.id() as libc::pid_t;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(child.wait_with_output());
+    });
+    let output = match rx.recv_timeout(TIMEOUT) {
+        Ok(output) => output.ok()?,
+        Err(_) => {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+            return None;
+        }
+    };
+    if !output.status.success() {
+        return None;
+    }
+
+    let parsed: Devices = serde_json::from_slice(&output.stdout).ok()?;
+    parsed
+        .devices
+        .into_iter()
+        .find(Device::update_scheduled)
+        .map(|d| d.name)
+}
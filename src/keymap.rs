@@ -0,0 +1,436 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use unicode_width::UnicodeWidthStr;
+
+// Everything that can be triggered from the main view. The config file
+// refers to these by their kebab-case names under `[keys]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    Up,
+    Down,
+    NextPanel,
+    PrevPanel,
+    MoveUp,
+    MoveDown,
+    MoveMode,
+    SwapDefault,
+    PinRest,
+    EditOrder,
+    Undo,
+    Redo,
+    ResetOrder,
+    Refresh,
+    DismissBanner,
+    Warnings,
+    Confirm,
+    ApplyAndBootOnce,
+    ApplyAtShutdown,
+    DeferredChange,
+    BootPrevious,
+    DeleteEntry,
+    Reboot,
+    ToggleIds,
+    ToggleGroups,
+    ToggleFirmwareEntries,
+    Details,
+    Profiles,
+    Help,
+    Quit,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 30] = [
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::NextPanel,
+        KeyAction::PrevPanel,
+        KeyAction::MoveUp,
+        KeyAction::MoveDown,
+        KeyAction::MoveMode,
+        KeyAction::SwapDefault,
+        KeyAction::PinRest,
+        KeyAction::EditOrder,
+        KeyAction::Undo,
+        KeyAction::Redo,
+        KeyAction::ResetOrder,
+        KeyAction::Refresh,
+        KeyAction::DismissBanner,
+        KeyAction::Warnings,
+        KeyAction::Confirm,
+        KeyAction::ApplyAndBootOnce,
+        KeyAction::ApplyAtShutdown,
+        KeyAction::DeferredChange,
+        KeyAction::BootPrevious,
+        KeyAction::DeleteEntry,
+        KeyAction::Reboot,
+        KeyAction::ToggleIds,
+        KeyAction::ToggleGroups,
+        KeyAction::ToggleFirmwareEntries,
+        KeyAction::Details,
+        KeyAction::Profiles,
+        KeyAction::Help,
+        KeyAction::Quit,
+    ];
+
+    // The name used under `[keys]`, e.g. "move-up".
+    pub fn name(self) -> String {
+        toml::Value::try_from(self)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            KeyAction::Up => "Move selection up",
+            KeyAction::Down => "Move selection down",
+            KeyAction::NextPanel => "Switch to the next panel",
+            KeyAction::PrevPanel => "Switch to the previous panel",
+            KeyAction::MoveUp => "Move entry up in boot order",
+            KeyAction::MoveDown => "Move entry down in boot order",
+            KeyAction::MoveMode => "Pick up the entry: place it with ↑/↓, Enter drops, Esc cancels",
+            KeyAction::SwapDefault => "Swap the first two entries",
+            KeyAction::PinRest => "Pin the rest: apply only the moved entries",
+            KeyAction::EditOrder => "Edit the boot order as text in $EDITOR",
+            KeyAction::Undo => "Undo the last reorder",
+            KeyAction::Redo => "Redo the last undone reorder",
+            KeyAction::ResetOrder => "Discard the reorder not yet applied",
+            KeyAction::Refresh => "Re-read the boot entries from the firmware",
+            KeyAction::DismissBanner => "Dismiss the banner under the title",
+            KeyAction::Warnings => "Show the warnings efibootmgr printed",
+            KeyAction::Confirm => "Apply order / boot selected entry",
+            KeyAction::ApplyAndBootOnce => "Apply order and boot the Boot To selection once",
+            KeyAction::ApplyAtShutdown => "Apply the order at the next shutdown instead of now",
+            KeyAction::DeferredChange => "Show or cancel the change deferred to shutdown",
+            KeyAction::BootPrevious => "Boot the previously booted entry once",
+            KeyAction::DeleteEntry => "Delete the selected boot entry (asks first)",
+            KeyAction::Reboot => "Reboot now, into the default or the armed BootNext",
+            KeyAction::ToggleIds => "Show/hide Boot#### IDs",
+            KeyAction::ToggleGroups => "Group boot priority entries by disk",
+            KeyAction::ToggleFirmwareEntries => "Show/hide network, shell and diagnostic entries",
+            KeyAction::Details => "Show details of the selected entry",
+            KeyAction::Profiles => "Pick a boot order profile",
+            KeyAction::Help => "Show this help screen",
+            KeyAction::Quit => "Quit application",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            KeyAction::Up => &["Up", "k"],
+            KeyAction::Down => &["Down", "j"],
+            KeyAction::NextPanel => &["Tab", "l"],
+            KeyAction::PrevPanel => &["BackTab", "h"],
+            KeyAction::MoveUp => &["u", "Alt+Up", "Shift+Up"],
+            KeyAction::MoveDown => &["d", "Alt+Down", "Shift+Down"],
+            KeyAction::MoveMode => &["m"],
+            KeyAction::SwapDefault => &["s"],
+            KeyAction::PinRest => &["P"],
+            KeyAction::EditOrder => &["E"],
+            KeyAction::Undo => &["Ctrl+z"],
+            KeyAction::Redo => &["Ctrl+y"],
+            KeyAction::ResetOrder => &["Ctrl+u"],
+            KeyAction::Refresh => &["r"],
+            KeyAction::DismissBanner => &["x"],
+            KeyAction::Warnings => &["o"],
+            KeyAction::Confirm => &["Enter"],
+            // Most terminals can't tell Shift+Enter from Enter, hence `b`.
+            KeyAction::ApplyAndBootOnce => &["Shift+Enter", "b"],
+            // Not `S` and `D`: with Caps Lock on those have to reach `s`
+            // and `d`.
+            KeyAction::ApplyAtShutdown => &["Ctrl+s"],
+            KeyAction::DeferredChange => &["Ctrl+d"],
+            KeyAction::BootPrevious => &["b"],
+            KeyAction::DeleteEntry => &["Delete"],
+            KeyAction::Reboot => &["Ctrl+r"],
+            KeyAction::ToggleIds => &["#"],
+            KeyAction::ToggleGroups => &["G"],
+            KeyAction::ToggleFirmwareEntries => &["H"],
+            KeyAction::Details => &["i"],
+            KeyAction::Profiles => &["p"],
+            KeyAction::Help => &["?", "F1"],
+            KeyAction::Quit => &["q", "Esc", "Ctrl+c"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    // Shift is already part of the character for printable keys (and of
+    // BackTab), so it's dropped to make `?` match however the terminal
+    // reports it.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyBinding { code, modifiers }
+    }
+
+    pub fn parse(text: &str) -> Result<KeyBinding, String> {
+        let invalid = || format!("invalid key binding '{}'", text);
+
+        let (mods, key) = if text == "+" {
+            ("", "+")
+        } else if let Some(mods) = text.strip_suffix("++") {
+            (mods, "+")
+        } else {
+            match text.rfind('+') {
+                Some(i) => (&text[..i], &text[i + 1..]),
+                None => ("", text),
+            }
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for m in mods.split('+').filter(|m| !m.is_empty()) {
+            modifiers |= match m.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                f if f.starts_with('f') => match f[1..].parse::<u8>() {
+                    Ok(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            },
+        };
+
+        Ok(KeyBinding::normalized(code, modifiers))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut out = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            out.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            out.push_str("Alt+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            out.push_str("Shift+");
+        }
+        match self.code {
+            KeyCode::Char(' ') => out.push_str("Space"),
+            KeyCode::Char(c) => out.push(c),
+            KeyCode::Up => out.push('↑'),
+            KeyCode::Down => out.push('↓'),
+            KeyCode::Left => out.push('←'),
+            KeyCode::Right => out.push('→'),
+            KeyCode::BackTab => out.push_str("Shift+Tab"),
+            KeyCode::F(n) => out.push_str(&format!("F{}", n)),
+            KeyCode::PageUp => out.push_str("PgUp"),
+            KeyCode::PageDown => out.push_str("PgDn"),
+            other => out.push_str(&format!("{:?}", other)),
+        }
+        out
+    }
+}
+
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, KeyAction)>,
+}
+
+impl Keymap {
+    // Builds the keymap from the defaults, replacing the bindings of any
+    // action listed in the config's `[keys]` table.
+    pub fn new(overrides: &HashMap<KeyAction, Vec<String>>) -> Result<Keymap, String> {
+        let mut bindings = Vec::new();
+        for action in KeyAction::ALL {
+            let keys: Vec<&str> = match overrides.get(&action) {
+                Some(keys) => keys.iter().map(String::as_str).collect(),
+                None => action.default_keys().to_vec(),
+            };
+            for key in keys {
+                bindings.push((KeyBinding::parse(key)?, action));
+            }
+        }
+        Ok(Keymap { bindings })
+    }
+
+    pub fn default_bindings() -> Keymap {
+        Keymap::new(&HashMap::new()).expect("default key bindings are valid")
+    }
+
+    // The first action bound to `key` that `applies` accepts, so one key
+    // can mean different things in different panels. Exact matches win;
+    // otherwise letters are retried in lowercase so the bindings keep
+    // working with Caps Lock on.
+    pub fn lookup(&self, key: &KeyEvent, applies: impl Fn(KeyAction) -> bool) -> Option<KeyAction> {
+        let pressed = KeyBinding::normalized(key.code, key.modifiers);
+        let find = |binding: KeyBinding| {
+            self.bindings
+                .iter()
+                .find(|(b, action)| *b == binding && applies(*action))
+                .map(|(_, action)| *action)
+        };
+
+        find(pressed).or_else(|| match pressed.code {
+            KeyCode::Char(c) if c.is_ascii_uppercase() => find(KeyBinding {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers: pressed.modifiers,
+            }),
+            _ => None,
+        })
+    }
+
+    pub fn keys_for(&self, action: KeyAction) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(b, _)| b.describe())
+            .collect()
+    }
+
+    // Every action with its keys, one per line, for `--print-keys`.
+    // Actions whose keys were all removed in the config show "(none)".
+    pub fn table(&self) -> String {
+        let rows: Vec<_> = KeyAction::ALL
+            .iter()
+            .map(|&action| {
+                let keys = self.keys_for(action);
+                let keys = if keys.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    keys.join(", ")
+                };
+                (keys, action.name(), action.description())
+            })
+            .collect();
+        let keys_width = rows.iter().map(|(k, _, _)| k.width()).max().unwrap_or(0);
+        let name_width = rows.iter().map(|(_, n, _)| n.len()).max().unwrap_or(0);
+        rows.iter()
+            .map(|(keys, name, description)| {
+                let padding = " ".repeat(keys_width - keys.width());
+                format!(
+                    "{}{}  {:<name_width$}  {}\n",
+                    keys, padding, name, description
+                )
+            })
+            .collect()
+    }
+
+    // Short form for the footer: the first binding only.
+    pub fn label(&self, action: KeyAction) -> String {
+        self.keys_for(action).into_iter().next().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEventKind;
+
+    fn lookup(code: KeyCode, modifiers: KeyModifiers) -> Option<KeyAction> {
+        let key = KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press);
+        Keymap::default_bindings().lookup(&key, |_| true)
+    }
+
+    fn char_key(c: char) -> Option<KeyAction> {
+        lookup(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn uppercase_letters_fall_back_to_their_lowercase_bindings() {
+        assert_eq!(char_key('Q'), Some(KeyAction::Quit));
+        assert_eq!(char_key('J'), Some(KeyAction::Down));
+        assert_eq!(char_key('K'), Some(KeyAction::Up));
+        assert_eq!(char_key('U'), Some(KeyAction::MoveUp));
+        assert_eq!(char_key('S'), Some(KeyAction::SwapDefault));
+        assert_eq!(char_key('D'), Some(KeyAction::MoveDown));
+    }
+
+    #[test]
+    fn deferring_is_on_ctrl_keys() {
+        assert_eq!(
+            lookup(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            Some(KeyAction::ApplyAtShutdown)
+        );
+        assert_eq!(
+            lookup(KeyCode::Char('d'), KeyModifiers::CONTROL),
+            Some(KeyAction::DeferredChange)
+        );
+    }
+
+    #[test]
+    fn terminals_reporting_shift_with_the_letter_still_match() {
+        assert_eq!(
+            lookup(KeyCode::Char('Q'), KeyModifiers::SHIFT),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            lookup(KeyCode::Char('?'), KeyModifiers::SHIFT),
+            Some(KeyAction::Help)
+        );
+    }
+
+    #[test]
+    fn help_has_question_mark_and_f1() {
+        assert_eq!(char_key('?'), Some(KeyAction::Help));
+        assert_eq!(
+            lookup(KeyCode::F(1), KeyModifiers::NONE),
+            Some(KeyAction::Help)
+        );
+    }
+
+    #[test]
+    fn exact_uppercase_bindings_win_over_the_fallback() {
+        assert_eq!(char_key('G'), Some(KeyAction::ToggleGroups));
+        assert_eq!(char_key('g'), None);
+    }
+
+    #[test]
+    fn the_fallback_keeps_the_modifiers() {
+        assert_eq!(
+            lookup(KeyCode::Char('C'), KeyModifiers::CONTROL),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(char_key('C'), None);
+    }
+
+    #[test]
+    fn bindings_parse_and_describe() {
+        let binding = KeyBinding::parse("Ctrl+Alt+x").unwrap();
+        assert_eq!(binding.describe(), "Ctrl+Alt+x");
+        assert_eq!(
+            KeyBinding::parse("Shift+Tab").unwrap().describe(),
+            "Shift+Tab"
+        );
+        assert_eq!(KeyBinding::parse("F1").unwrap().describe(), "F1");
+        assert_eq!(KeyBinding::parse("Ctrl++").unwrap().describe(), "Ctrl++");
+        assert!(KeyBinding::parse("Hyper+x").is_err());
+        assert!(KeyBinding::parse("F13").is_err());
+    }
+}
@@ -0,0 +1,17 @@
+//! The efibootmgr layer behind the swiftboot TUI: listing UEFI boot
+//! entries, reading BootOrder and BootNext, and changing them.
+//!
+//! [`BootManager`] is the interface. [`Efibootmgr`] implements it by running
+//! efibootmgr, [`Efivarfs`] through the [`efivars`] module, which reads and
+//! writes the variables in efivarfs instead. The [`efi`] module has the
+//! parsers for efibootmgr's output.
+//!
+//! The TUI and its dependencies sit behind the default `cli` feature; depend
+//! on this crate with `default-features = false` to leave them out.
+
+pub mod efi;
+pub mod efivars;
+mod manager;
+
+pub use efi::{BootEntry, BootStatus, EntryId, EntryKind};
+pub use manager::{BootManager, Efibootmgr, Efivarfs, Error};
@@ -0,0 +1,60 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{self, Read, Seek, Write},
+    path::PathBuf,
+};
+
+// Held for the lifetime of the process. The kernel drops the flock when the
+// file is closed, so it is released on every exit path, panics included.
+pub struct InstanceLock {
+    _file: File,
+}
+
+pub enum LockStatus {
+    Acquired(InstanceLock),
+    // Another instance holds the lock; its PID if it could be read.
+    HeldBy(Option<u32>),
+    // No runtime directory or the lock file can't be created; run unlocked.
+    Unavailable,
+}
+
+pub fn lock_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join("swiftboot.lock"))
+}
+
+pub fn acquire() -> LockStatus {
+    let Some(path) = lock_path() else {
+        return LockStatus::Unavailable;
+    };
+    let Ok(mut file) = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+    else {
+        return LockStatus::Unavailable;
+    };
+
+    match file.try_lock() {
+        Ok(()) => {
+            let _ = write_pid(&mut file);
+            LockStatus::Acquired(InstanceLock { _file: file })
+        }
+        Err(TryLockError::WouldBlock) => {
+            let mut text = String::new();
+            let _ = file.read_to_string(&mut text);
+            LockStatus::HeldBy(text.trim().parse().ok())
+        }
+        Err(TryLockError::Error(_)) => LockStatus::Unavailable,
+    }
+}
+
+fn write_pid(file: &mut File) -> io::Result<()> {
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()
+}
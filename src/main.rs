@@ -1,953 +1,641 @@
+mod app;
+mod batch;
+mod cli;
+mod config;
+mod daemon;
+mod fwupd;
+mod keymap;
+mod lock;
+mod man;
+mod modal;
+mod partition;
+mod plain;
+mod report;
+mod schedule;
+mod state;
+mod status;
+mod text_input;
+mod ui;
+
+use app::{App, EntryList, Focus};
+use clap::Parser;
+use cli::{Backend, Cli, ClientCommand, Command, ConfigCommand, StartPanel};
+use config::Config;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::prelude::Stylize;
-use ratatui::{
-    Terminal,
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-};
-use regex::Regex;
+use keymap::Keymap;
+use lock::LockStatus;
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use state::State;
 use std::{
-    io::{self, Write},
-    process::{Command, Stdio},
-    time::Duration,
+    fs,
+    io::{self, IsTerminal, Write},
+    os::unix::fs::OpenOptionsExt,
+    process::Command as Process,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
 };
+use swiftboot::{efi, efivars};
 
-#[derive(Clone)]
-struct BootEntry {
-    id: String,
-    name: String,
-}
+// Rows of the inline viewport used with `--no-alt-screen`.
+const INLINE_HEIGHT: u16 = 20;
 
-#[derive(Clone, Copy)]
-enum Focus {
-    Priority,
-    BootOnce,
-}
+// Whether the TUI holds the terminal: raw mode, and the alternate screen
+// without `--no-alt-screen`.
+static TERMINAL_TAKEN: AtomicBool = AtomicBool::new(false);
 
-#[derive(Clone)]
-enum Action {
-    None,
-    SetOrder(Vec<String>),
-    BootOnce(String),
-}
-
-enum UIState {
-    Main,
-    AskPassword,
-    PasswordError,
-    ConfirmReboot,
-    CountdownReboot(u8),
-    QuitConfirm,
-    Help,
-    ErrorMessage(String),
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("swiftboot: {}", e);
+        std::process::exit(1);
+    }
 }
 
-fn execute_sudo_command(
-    args: &[&str],
-    password: &str,
-) -> Result<(bool, String), Box<dyn std::error::Error>> {
-    let mut child = Command::new("sudo")
-        .arg("-S")
-        .args(args)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if cli.version {
+        println!("{}", cli::version_text());
+        return Ok(());
+    }
 
-    if let Some(mut stdin) = child.stdin.take() {
-        stdin.write_all(password.as_bytes())?;
-        stdin.write_all(b"\n")?;
-        stdin.flush()?;
-        drop(stdin);
+    // Describes the defaults, whatever the config file says.
+    if let Some(Command::GenerateMan) = &cli.command {
+        return man::render(&mut io::stdout());
     }
 
-    let output = child.wait_with_output()?;
+    // Runs before loading so it can replace a broken config file.
+    if let Some(Command::Config {
+        action: ConfigCommand::Init { force },
+    }) = &cli.command
+    {
+        return config::init(*force);
+    }
 
-    let stderr_text = String::from_utf8_lossy(&output.stderr).to_string();
+    // Status bars poll this; a broken config shouldn't blank them, so it
+    // falls back to the defaults (only the aliases matter here).
+    if let Some(Command::Status { format }) = &cli.command {
+        return status::run(&Config::load().unwrap_or_default(), *format);
+    }
 
-    if stderr_text.contains("Sorry") || stderr_text.contains("try again") {
-        return Ok((false, "Incorrect password".to_string()));
+    // Only touches the state file, so it works whatever else is broken.
+    if let Some(Command::Confirm) = &cli.command {
+        return State::confirm_safe_apply();
     }
 
-    if !output.status.success() {
-        let error_msg = if !stderr_text.trim().is_empty() {
-            stderr_text.trim().to_string()
-        } else {
-            format!(
-                "Command failed with exit code: {}",
-                output.status.code().unwrap_or(-1)
-            )
+    // The daemon does the work with its own configuration.
+    if let Some(Command::Client { socket, request }) = &cli.command {
+        let request = match request {
+            ClientCommand::List => daemon::Request::List,
+            ClientCommand::Status => daemon::Request::Status,
+            ClientCommand::Once { entry } => daemon::Request::BootOnce {
+                entry: entry.clone(),
+            },
+            ClientCommand::Order { entries } => daemon::Request::SetOrder {
+                entries: entries.clone(),
+            },
         };
-        return Ok((false, error_msg));
+        return daemon::client(&daemon::socket_path(socket.as_deref())?, &request);
     }
 
-    Ok((true, String::new()))
-}
+    let (mut config, keymap) = match Config::load_validated() {
+        Ok(loaded) => loaded,
+        Err(e) if cli.ignore_config_errors && cli.command.is_none() => {
+            eprintln!("swiftboot: {}", e);
+            eprintln!("swiftboot: continuing with the default configuration");
+            (Config::default(), Keymap::default_bindings())
+        }
+        Err(e) => return Err(e),
+    };
+    cli.apply_to(&mut config);
+    efi::set_capturing(config.debug_capture);
 
-fn execute_set_boot_order(
-    order_ids: &[String],
-    password: &str,
-) -> Result<UIState, Box<dyn std::error::Error>> {
-    let order = order_ids.join(",");
-    let result = execute_sudo_command(&["efibootmgr", "-o", &order], password)?;
-
-    if result.0 {
-        Ok(UIState::ConfirmReboot)
-    } else if result.1 == "Incorrect password" {
-        Ok(UIState::PasswordError)
-    } else {
-        Ok(UIState::ErrorMessage(result.1))
+    if cli.print_keys {
+        print!("{}", keymap.table());
+        return Ok(());
     }
-}
-
-fn execute_boot_once(id: &str, password: &str) -> Result<UIState, Box<dyn std::error::Error>> {
-    let result = execute_sudo_command(&["efibootmgr", "-n", id], password)?;
 
-    if result.0 {
-        Ok(UIState::CountdownReboot(5))
-    } else if result.1 == "Incorrect password" {
-        Ok(UIState::PasswordError)
-    } else {
-        Ok(UIState::ErrorMessage(result.1))
+    if let Some(Command::Daemon { socket }) = &cli.command {
+        return daemon::serve(&daemon::socket_path(socket.as_deref())?, config, keymap);
     }
-}
-
-fn center(area: Rect, width: u16, height: u16) -> Rect {
-    Rect::new(
-        area.x + area.width / 2 - width / 2,
-        area.y + area.height / 2 - height / 2,
-        width,
-        height,
-    )
-}
-
-fn centered_area(area: Rect, width_pct: u16, height_pct: u16) -> Rect {
-    let w = area.width * width_pct / 100;
-    let h = area.height * height_pct / 100;
-    Rect::new(
-        area.x + (area.width - w) / 2,
-        area.y + (area.height - h) / 2,
-        w,
-        h,
-    )
-}
 
-fn fetch_boot_entries() -> Result<Vec<BootEntry>, Box<dyn std::error::Error>> {
-    let output = Command::new("efibootmgr").arg("-v").output()?;
-
-    if !output.status.success() {
-        return Err("Failed to run efibootmgr. Are you running on a UEFI system?".into());
+    if let Some(Command::Report { no_redact }) = &cli.command {
+        return report::run(&config, !no_redact);
     }
 
-    let text = String::from_utf8_lossy(&output.stdout);
-    let regex = Regex::new(r"Boot(?P<id>[0-9A-Fa-f]{4})\*?\s+(?P<name>[^\t\(]+)").unwrap();
-
-    let entries = text
-        .lines()
-        .filter_map(|line| {
-            regex.captures(line).map(|cap| BootEntry {
-                id: cap["id"].trim().to_string(),
-                name: cap["name"].trim().to_string(),
-            })
-        })
-        .collect();
-
-    Ok(entries)
-}
-
-fn fetch_boot_order() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let output = Command::new("efibootmgr").output()?;
-
-    if !output.status.success() {
-        return Err("Failed to run efibootmgr".into());
+    if let Some(Command::Config { action }) = &cli.command {
+        return match action {
+            ConfigCommand::Check => config::check(&config),
+            ConfigCommand::Init { .. } => unreachable!("handled before loading the config"),
+        };
     }
 
-    let text = String::from_utf8_lossy(&output.stdout);
-
-    let order = text
-        .lines()
-        .find(|l| l.starts_with("BootOrder:"))
-        .map(|l| {
-            l["BootOrder:".len()..]
-                .trim()
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .collect()
-        })
-        .unwrap_or_default();
-
-    Ok(order)
-}
-
-fn draw_main_ui(
-    f: &mut ratatui::Frame,
-    area: Rect,
-    entries: &[BootEntry],
-    focus: Focus,
-    selected_priority: usize,
-    selected_boot_once: usize,
-    current_boot_id: &str,
-) {
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(10),
-            Constraint::Percentage(40),
-            Constraint::Percentage(40),
-            Constraint::Percentage(10),
-        ])
-        .split(area);
-
-    // Title
-    f.render_widget(
-        Paragraph::new("SwiftBoot")
-            .style(Style::default().fg(Color::Cyan).bold())
-            .alignment(Alignment::Center),
-        layout[0],
-    );
+    // One-shot commands and plain mode don't draw anything, so they just
+    // wait for the fetch; the TUI shows a loading screen meanwhile.
+    let tui = !cli.plain && io::stdout().is_terminal() && cli.command.is_none();
+    let source = Source::new(&config, &cli);
+    if cli.timings {
+        let started = Instant::now();
+        let loaded = load(source)?;
+        for timing in &loaded.timings {
+            println!(
+                "{:<20} ran {:.3}s, parsed {:.3}s",
+                timing.command,
+                timing.run.as_secs_f64(),
+                timing.parse.as_secs_f64()
+            );
+        }
+        if loaded.timings.is_empty() {
+            println!("efibootmgr wasn't run; the entries were read from efivarfs");
+        }
+        println!(
+            "Startup reads took {:.3}s in all, with fwupd and the partition scan",
+            started.elapsed().as_secs_f64()
+        );
+        return Ok(());
+    }
+    if !tui {
+        let loaded = load(source)?;
+        for warning in &loaded.warnings {
+            eprintln!("swiftboot: {}", warning);
+        }
+        let (mut app, _lock) = start_app(config, keymap, cli.backend, loaded);
+        let (input, out) = (&mut io::stdin().lock(), &mut io::stdout());
+        if app.reads_need_root {
+            plain::read_with_sudo(&mut app, input, out)?;
+        }
+        return match &cli.command {
+            Some(Command::ApplyProfile { name }) => {
+                plain::apply_profile(&mut app, name, input, out)
+            }
+            Some(Command::SwapDefault) => plain::swap_default(&mut app, input, out),
+            Some(Command::Revert) => plain::revert(&mut app, input, out),
+            Some(Command::Deferred { cancel }) => plain::deferred(&mut app, *cancel, input, out),
+            Some(Command::Reboot) => plain::reboot(&mut app, input, out),
+            Some(Command::Batch {
+                dry_run,
+                continue_on_error,
+                askpass,
+            }) => {
+                let options = batch::Options {
+                    dry_run: *dry_run,
+                    continue_on_error: *continue_on_error,
+                    askpass: askpass.clone(),
+                };
+                batch::run(&mut app, &options, input, out)
+            }
+            _ => plain::run(&mut app, input, out),
+        };
+    }
 
-    // Priority panel
-    let priority_items: Vec<ListItem> = entries
-        .iter()
-        .enumerate()
-        .map(|(i, e)| {
-            let style = if matches!(focus, Focus::Priority) && i == selected_priority {
-                Style::default().bg(Color::Cyan).fg(Color::Black).bold()
-            } else {
-                Style::default().fg(Color::White)
-            };
-            let marker = if e.id == current_boot_id {
-                " →"
-            } else {
-                "  "
-            };
-            ListItem::new(format!("{} {}. {}", marker, i + 1, e.name)).style(style)
-        })
-        .collect();
+    // The handlers only record which signal arrived and stop a running sudo
+    // child; the loops below do the orderly teardown.
+    let signal = Arc::new(AtomicUsize::new(0));
+    for sig in [SIGTERM, SIGHUP, SIGINT] {
+        signal_hook::flag::register_usize(sig, Arc::clone(&signal), sig as usize)?;
+        unsafe { signal_hook::low_level::register(sig, efi::kill_child)? };
+    }
 
-    let priority_border_style = if matches!(focus, Focus::Priority) {
-        Style::default().fg(Color::White)
+    // A panic message printed on the alternate screen would vanish with it.
+    let inline = cli.no_alt_screen;
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal(inline);
+        default_hook(info);
+    }));
+
+    let _guard = TerminalGuard::take(inline)?;
+    let viewport = if inline {
+        Viewport::Inline(INLINE_HEIGHT)
     } else {
-        Style::default().fg(Color::DarkGray)
+        Viewport::Fullscreen
     };
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::with_options(backend, TerminalOptions { viewport })?;
 
-    f.render_widget(
-        List::new(priority_items).block(
-            Block::default()
-                .title(" Boot Priority (default order) ")
-                .borders(Borders::ALL)
-                .border_style(priority_border_style),
-        ),
-        layout[1],
-    );
-
-    // Boot once panel
-    let boot_once_items: Vec<ListItem> = entries
-        .iter()
-        .enumerate()
-        .map(|(i, e)| {
-            let style = if matches!(focus, Focus::BootOnce) && i == selected_boot_once {
-                Style::default().bg(Color::Cyan).fg(Color::Black).bold()
-            } else {
-                Style::default().fg(Color::White)
-            };
-            let marker = if e.id == current_boot_id {
-                " →"
-            } else {
-                "  "
-            };
-            ListItem::new(format!("{} {}", marker, e.name)).style(style)
-        })
-        .collect();
-
-    let boot_to_border_style = if matches!(focus, Focus::BootOnce) {
-        Style::default().fg(Color::White)
-    } else {
-        Style::default().fg(Color::DarkGray)
+    let Some(loaded) = wait_for_load(&mut terminal, &cli, &signal, source)? else {
+        restore_terminal(inline)?;
+        return exit_on_signal(&signal, None, None);
     };
+    let warnings = loaded.warnings.clone();
+    let (mut app, lock) = start_app(config, keymap, cli.backend, loaded);
+    // Plain mode and the subcommands always see every entry.
+    if !app.config.show_firmware_entries {
+        app.set_firmware_entries_hidden(true);
+    }
+    // The startup checks need the entries, which are only read once the
+    // password is in.
+    if app.reads_need_root {
+        if matches!(app.state, app::UIState::Main) {
+            app.read_with_sudo();
+        }
+    } else if app.read_only.is_none() && !app.check_safe_apply() {
+        app.check_temporary_order();
+    }
+    let outcomes: Vec<String> = [app.check_deferred(), app.check_armed_boot_next()]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !outcomes.is_empty() {
+        app.show_toast(outcomes.join("; "));
+    }
+    app.check_profile_suggestion();
+    let focus = cli.focus.map(|panel| match panel {
+        StartPanel::Priority => Focus::Priority,
+        StartPanel::BootOnce => Focus::BootOnce,
+    });
+    app.start_at(focus, cli.select.as_deref());
 
-    f.render_widget(
-        List::new(boot_once_items).block(
-            Block::default()
-                .title(" Boot To ")
-                .borders(Borders::ALL)
-                .border_style(boot_to_border_style),
-        ),
-        layout[2],
-    );
-
-    let footer = "Tab: Switch panel  |  ↑↓/jk: Move  |  u/d: Reorder  |  Enter: Apply/Boot  |  ?: Help  |  q: Quit";
-    f.render_widget(
-        Paragraph::new(footer)
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray)),
-        layout[3],
-    );
-}
-
-fn draw_password_popup(f: &mut ratatui::Frame, area: Rect, password: &str, show: bool) {
-    let popup_width = area.width * 3 / 4;
-    let popup_height = 6;
-    let popup = center(area, popup_width, popup_height);
+    loop {
+        if signal.load(Ordering::Relaxed) != 0 {
+            break;
+        }
 
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Authentication "),
-        popup,
-    );
+        terminal.draw(|f| {
+            if cli.no_alt_screen {
+                ui::draw_inline(f, &mut app)
+            } else {
+                ui::draw(f, &mut app)
+            }
+        })?;
 
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Length(1),
-            Constraint::Length(1),
-        ])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new("Enter sudo password")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White)),
-        inner[0],
-    );
+        if matches!(app.state, app::UIState::Processing) {
+            app.execute_pending()?;
+        }
 
-    let displayed = if show {
-        password.to_string()
-    } else {
-        "*".repeat(password.len())
-    };
+        app.tick();
 
-    let bar_width = popup_width / 2;
-    let bar_area = Rect {
-        x: popup.x + (popup.width - bar_width) / 2,
-        y: inner[2].y,
-        width: bar_width,
-        height: 1,
-    };
+        if event::poll(Duration::from_millis(50))?
+            && let Event::Key(key) = event::read()?
+        {
+            app.handle_key(key);
+        }
 
-    f.render_widget(
-        Paragraph::new(format!(" {}", displayed))
-            .style(Style::default().bg(Color::Cyan).fg(Color::Black))
-            .alignment(Alignment::Left),
-        bar_area,
-    );
+        if let Some(text) = app.order_to_edit.take() {
+            let edited = suspended(&mut terminal, &cli, || edit_text(&text))?;
+            app.finish_order_edit(edited);
+        }
 
-    let help_area = Rect {
-        x: area.x,
-        y: popup.y + popup_height + 1,
-        width: area.width,
-        height: 1,
-    };
+        if app.should_quit {
+            break;
+        }
+    }
 
-    f.render_widget(
-        Paragraph::new("Enter = Confirm  |  Esc = Cancel  |  Tab = Show/Hide")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray)),
-        help_area,
-    );
+    terminal.show_cursor()?;
+    restore_terminal(inline)?;
+    for warning in &warnings {
+        eprintln!("swiftboot: {}", warning);
+    }
+    if !cli.quiet {
+        for line in app.exit_summary() {
+            println!("swiftboot: {}", line);
+        }
+    }
+    exit_on_signal(&signal, Some(&app), lock)
 }
 
-fn draw_reboot_popup(f: &mut ratatui::Frame, area: Rect, yes_selected: bool) {
-    let popup_width = area.width / 3;
-    let popup_height = 7;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Apply Complete "),
-        popup,
-    );
+// Where the boot variables are read from, decided before the fetch starts.
+struct Source {
+    efibootmgr: String,
+    extra_args: Vec<String>,
+    show_all: bool,
+    read_only: bool,
+    backend: Backend,
+}
 
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Length(2), Constraint::Length(2)])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new("Reboot now?")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White)),
-        inner[0],
-    );
+impl Source {
+    fn new(config: &Config, cli: &Cli) -> Source {
+        Source {
+            efibootmgr: config.efibootmgr(),
+            extra_args: config.efibootmgr_extra_args.clone(),
+            show_all: config.show_all,
+            read_only: cli.read_only,
+            backend: cli.backend,
+        }
+    }
+}
 
-    let buttons = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(inner[1]);
+// Everything read from the system at startup. Errors are plain strings so
+// the result can be sent back from the loading thread.
+struct Loaded {
+    entries: Vec<efi::BootEntry>,
+    status: efi::BootStatus,
+    extra_lists: Vec<EntryList>,
+    read_only: Option<&'static str>,
+    firmware: efi::FirmwareInfo,
+    firmware_update: Option<String>,
+    resolver: partition::SystemResolver,
+    // Printed once the terminal is back to normal.
+    warnings: Vec<String>,
+    timings: Vec<efi::Timing>,
+    reads_efivarfs: bool,
+    // Nothing could be read: efibootmgr needs root even to read here.
+    reads_need_root: bool,
+}
 
-    let yes_style = if yes_selected {
-        Style::default().bg(Color::Green).fg(Color::Black).bold()
+fn load(source: Source) -> Result<Loaded, String> {
+    let mut warnings = Vec::new();
+    // Asked alongside efibootmgr rather than after it.
+    let firmware_update = thread::spawn(fwupd::pending_update);
+
+    // Without efibootmgr the variables can still be shown, read straight
+    // from efivarfs, but nothing can be changed.
+    let mut read_only = source.read_only.then_some(app::READ_ONLY_REQUESTED);
+    let mut direct = if source.backend == Backend::Efivarfs {
+        if !efivars::readable() {
+            return Err("--backend efivarfs: /sys/firmware/efi/efivars can't be read".to_string());
+        }
+        if !efivars::is_root() && read_only.is_none() {
+            return Err(
+                "--backend efivarfs writes the variables itself and needs root; add --read-only to only look"
+                    .to_string(),
+            );
+        }
+        true
     } else {
-        Style::default().fg(Color::White)
+        match efi::configure(&source.efibootmgr, &source.extra_args) {
+            Ok(()) => false,
+            Err(e) if efivars::readable() => {
+                warnings.push(e.to_string());
+                warnings.push("reading efivarfs directly; changes are disabled".to_string());
+                read_only = read_only.or(Some(app::READ_ONLY_NO_EFIBOOTMGR));
+                true
+            }
+            Err(e) => return Err(e.to_string()),
+        }
     };
 
-    let no_style = if !yes_selected {
-        Style::default().bg(Color::Red).fg(Color::Black).bold()
+    let mut fetched = if direct {
+        efivars::read_entries("Boot").map(|entries| (entries, efivars::read_status()))
     } else {
-        Style::default().fg(Color::White)
+        efi::fetch_boot_entries().and_then(|entries| Ok((entries, efi::fetch_boot_status()?)))
     };
+    // Where only root may read the variables, efivarfs may still let this
+    // user read them, which needs no password. Otherwise the app starts
+    // empty and reads through sudo; see `App::read_with_sudo`.
+    let mut reads_need_root = false;
+    if let Err(e) = &fetched
+        && e.is::<efi::ReadNeedsRoot>()
+    {
+        if efivars::values_readable() {
+            warnings.push(
+                "efibootmgr can't read the boot variables as this user; reading efivarfs directly"
+                    .to_string(),
+            );
+            direct = true;
+            fetched =
+                efivars::read_entries("Boot").map(|entries| (entries, efivars::read_status()));
+        } else {
+            reads_need_root = true;
+            fetched = Ok((Vec::new(), efi::BootStatus::default()));
+        }
+    }
+    let (entries, status) = fetched.map_err(|e| e.to_string())?;
+    for item in &status.invalid_order {
+        warnings.push(format!("ignoring invalid BootOrder item '{}'", item));
+    }
 
-    f.render_widget(
-        Paragraph::new("[ Yes ]")
-            .alignment(Alignment::Center)
-            .style(yes_style),
-        buttons[0],
-    );
-    f.render_widget(
-        Paragraph::new("[ No ]")
-            .alignment(Alignment::Center)
-            .style(no_style),
-        buttons[1],
-    );
-}
+    let mut extra_lists = Vec::new();
+    if source.show_all && !reads_need_root {
+        for kind in efi::EntryKind::ALL {
+            let entries = if direct {
+                efivars::read_entries(kind.prefix())
+            } else {
+                efi::fetch_entry_list(kind)
+            };
+            extra_lists.push(EntryList::new(kind, entries.map_err(|e| e.to_string())?));
+        }
+    }
 
-fn draw_processing_screen(f: &mut ratatui::Frame, area: Rect) {
-    let popup_width = area.width / 3;
-    let popup_height = 5;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Paragraph::new("Processing...")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Cyan).bold())
-            .block(Block::default().borders(Borders::ALL)),
-        popup,
-    );
+    report::save_captures();
+    Ok(Loaded {
+        entries,
+        status,
+        extra_lists,
+        read_only,
+        firmware: efi::firmware_info(),
+        firmware_update: firmware_update.join().ok().flatten(),
+        resolver: partition::SystemResolver::new(),
+        warnings,
+        timings: efi::take_timings(),
+        reads_efivarfs: direct,
+        reads_need_root,
+    })
 }
 
-fn draw_password_error_popup(f: &mut ratatui::Frame, area: Rect) {
-    let popup_width = area.width / 2;
-    let popup_height = 7;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Authentication Failed ")
-            .style(Style::default().fg(Color::Red)),
-        popup,
-    );
-
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Length(2),
-            Constraint::Length(1),
-        ])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new("Incorrect password!")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Red).bold()),
-        inner[0],
-    );
+// Runs `load` on a background thread while the loading screen is drawn.
+// None when quit with a key or a signal before it finished.
+fn wait_for_load(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    cli: &Cli,
+    signal: &AtomicUsize,
+    source: Source,
+) -> Result<Option<Loaded>, Box<dyn std::error::Error>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(load(source));
+    });
+
+    let started = Instant::now();
+    let error = loop {
+        if signal.load(Ordering::Relaxed) != 0 {
+            return Ok(None);
+        }
+        match rx.try_recv() {
+            Ok(Ok(loaded)) => return Ok(Some(loaded)),
+            Ok(Err(e)) => break e,
+            Err(TryRecvError::Disconnected) => break "Loading boot entries failed".to_string(),
+            Err(TryRecvError::Empty) => {}
+        }
 
-    f.render_widget(
-        Paragraph::new("Please try again.")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White)),
-        inner[1],
-    );
+        terminal.draw(|f| ui::draw_loading(f, cli.no_alt_screen, started.elapsed()))?;
+        if event::poll(Duration::from_millis(50))?
+            && let Event::Key(key) = event::read()?
+            && is_quit_key(key)
+        {
+            return Ok(None);
+        }
+    };
 
-    f.render_widget(
-        Paragraph::new("Press any key to continue")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Gray)),
-        inner[2],
-    );
+    // Kept on screen until a key is pressed, then returned as the error.
+    loop {
+        if signal.load(Ordering::Relaxed) != 0 {
+            break;
+        }
+        terminal.draw(|f| ui::draw_fatal_error(f, cli.no_alt_screen, &error))?;
+        if event::poll(Duration::from_millis(50))?
+            && let Event::Key(_) = event::read()?
+        {
+            break;
+        }
+    }
+    Err(error.into())
 }
 
-fn draw_countdown_screen(f: &mut ratatui::Frame, area: Rect, seconds: u8) {
-    let popup_width = area.width / 2;
-    let popup_height = 8;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Rebooting ")
-            .style(Style::default().fg(Color::Cyan)),
-        popup,
-    );
-
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(2),
-            Constraint::Length(2),
-            Constraint::Length(1),
-        ])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new(format!(
-            "Rebooting in {} second{}...",
-            seconds,
-            if seconds == 1 { "" } else { "s" }
-        ))
-        .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::White)),
-        inner[0],
-    );
-
-    let progress = (5 - seconds) as f32 / 5.0;
-    let bar_width = (popup_width - 10) as f32 * progress;
-    let filled = "█".repeat(bar_width as usize);
-    let empty = "░".repeat((popup_width - 10) as usize - bar_width as usize);
-
-    f.render_widget(
-        Paragraph::new(format!("{}{}", filled, empty))
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Cyan)),
-        inner[1],
-    );
-
-    f.render_widget(
-        Paragraph::new("Press Esc to cancel")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray)),
-        inner[2],
-    );
+// The keymap isn't loaded into an app yet while loading, so the fixed keys.
+fn is_quit_key(key: KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Esc | KeyCode::Char('q'))
+        || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
 }
 
-fn draw_quit_confirm_popup(f: &mut ratatui::Frame, area: Rect, yes_selected: bool) {
-    let popup_width = area.width / 3;
-    let popup_height = 7;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Quit ")
-            .style(Style::default().fg(Color::Yellow)),
-        popup,
-    );
-
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Length(2), Constraint::Length(2)])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new("Quit without applying?")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White)),
-        inner[0],
+fn start_app(
+    config: Config,
+    keymap: Keymap,
+    backend: Backend,
+    loaded: Loaded,
+) -> (App, Option<lock::InstanceLock>) {
+    let mut app = App::new(
+        config,
+        keymap,
+        State::load(),
+        loaded.entries,
+        loaded.status,
+        loaded.extra_lists,
     );
-
-    let buttons = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(inner[1]);
-
-    let yes_style = if yes_selected {
-        Style::default().bg(Color::Red).fg(Color::Black).bold()
-    } else {
-        Style::default().fg(Color::White)
-    };
-
-    let no_style = if !yes_selected {
-        Style::default().bg(Color::Green).fg(Color::Black).bold()
+    app.resolve_partitions(&loaded.resolver);
+    app.read_only = loaded.read_only;
+    app.firmware = loaded.firmware;
+    app.firmware_update = loaded.firmware_update;
+    app.efivarfs_writes = backend == Backend::Efivarfs;
+    app.reads_efivarfs = loaded.reads_efivarfs;
+    app.reads_need_root = loaded.reads_need_root;
+    app.note_read_time(&loaded.timings);
+
+    // Kept alive until main returns; dropping it releases the lock. Not
+    // taken when nothing can be written anyway.
+    let lock = if app.read_only.is_some() {
+        None
     } else {
-        Style::default().fg(Color::White)
+        match lock::acquire() {
+            LockStatus::Acquired(lock) => Some(lock),
+            LockStatus::HeldBy(pid) => {
+                app.state = app::UIState::InstanceLocked(pid);
+                None
+            }
+            LockStatus::Unavailable => None,
+        }
     };
-
-    f.render_widget(
-        Paragraph::new("[ Yes ]")
-            .alignment(Alignment::Center)
-            .style(yes_style),
-        buttons[0],
-    );
-    f.render_widget(
-        Paragraph::new("[ No ]")
-            .alignment(Alignment::Center)
-            .style(no_style),
-        buttons[1],
-    );
+    (app, lock)
 }
 
-fn draw_help_screen(f: &mut ratatui::Frame, area: Rect) {
-    let popup_width = area.width * 3 / 4;
-    let popup_height = 23;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Help ")
-            .style(Style::default().fg(Color::Cyan)),
-        popup,
-    );
-
-    let help_text = vec![
-        "",
-        "Navigation:",
-        "  Tab              Switch between panels",
-        "  ↑/↓ or k/j       Move selection up/down",
-        "",
-        "Boot Priority Panel:",
-        "  u/d              Move entry up/down in boot order",
-        "  Enter            Apply new boot order (requires reboot)",
-        "",
-        "Boot To Panel:",
-        "  Enter            Boot directly to selected OS",
-        "",
-        "Password Dialog:",
-        "  Tab              Toggle password visibility",
-        "  Enter            Confirm",
-        "  Esc              Cancel",
-        "",
-        "General:",
-        "  ? or h           Show this help screen",
-        "  q                Quit application",
-        "",
-        "Press any key to close this help screen",
-    ];
-
-    let inner = Rect {
-        x: popup.x + 2,
-        y: popup.y + 1,
-        width: popup.width - 4,
-        height: popup.height - 2,
-    };
-
-    f.render_widget(
-        Paragraph::new(help_text.join("\n"))
-            .style(Style::default().fg(Color::White))
-            .alignment(Alignment::Left),
-        inner,
-    );
+// Runs `f` with the terminal handed back to the shell, e.g. for an editor,
+// and takes it over again afterwards.
+fn suspended<T>(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    cli: &Cli,
+    f: impl FnOnce() -> T,
+) -> Result<T, Box<dyn std::error::Error>> {
+    terminal.show_cursor()?;
+    restore_terminal(cli.no_alt_screen)?;
+    let result = f();
+    take_terminal(cli.no_alt_screen)?;
+    terminal.clear()?;
+    Ok(result)
 }
 
-fn draw_error_message_popup(f: &mut ratatui::Frame, area: Rect, error_msg: &str) {
-    let popup_width = area.width * 2 / 3;
-    let popup_height = 9;
-    let popup = center(area, popup_width, popup_height);
-
-    f.render_widget(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Error ")
-            .style(Style::default().fg(Color::Red)),
-        popup,
-    );
-
-    let inner = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(1),
-            Constraint::Min(3),
-            Constraint::Length(1),
-        ])
-        .split(Rect {
-            x: popup.x + 1,
-            y: popup.y + 1,
-            width: popup.width - 2,
-            height: popup.height - 2,
-        });
-
-    f.render_widget(
-        Paragraph::new("Command failed:")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Red).bold()),
-        inner[0],
-    );
-
-    f.render_widget(
-        Paragraph::new(error_msg)
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White)),
-        inner[1],
-    );
-
-    f.render_widget(
-        Paragraph::new("Press any key to continue")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Gray)),
-        inner[2],
-    );
+// Opens `text` in $VISUAL or $EDITOR (vi without either) and returns what
+// was saved. The command goes through sh so it may carry arguments, such
+// as `code --wait`.
+fn edit_text(text: &str) -> Result<String, String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .find(|v| !v.is_empty())
+        .map_or_else(|| "vi".to_string(), |v| v.to_string_lossy().into_owned());
+    let path = std::env::temp_dir().join(format!("swiftboot-order-{}.txt", std::process::id()));
+    let _ = fs::remove_file(&path);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+
+    let status = Process::new("sh")
+        .args(["-c", &format!("{} \"$1\"", editor), "sh"])
+        .arg(&path)
+        .status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    match status {
+        Ok(status) if status.success() => {
+            edited.map_err(|e| format!("Can't read {}: {}", path.display(), e))
+        }
+        Ok(status) => Err(format!("{} exited with {}", editor, status)),
+        Err(e) => Err(format!("Can't run {}: {}", editor, e)),
+    }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut entries = fetch_boot_entries()?;
-    let order = fetch_boot_order()?;
-
-    let current_boot_id = order.first().cloned().unwrap_or_default();
-
-    if !order.is_empty() {
-        entries.sort_by_key(|e| {
-            order
-                .iter()
-                .position(|id| id == &e.id)
-                .unwrap_or(usize::MAX)
-        });
+fn take_terminal(inline: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    TERMINAL_TAKEN.store(true, Ordering::SeqCst);
+    if !inline {
+        execute!(io::stdout(), EnterAlternateScreen)?;
     }
+    Ok(())
+}
 
-    let mut selected_priority = 0usize;
-    let mut selected_boot_once = 0usize;
-    let mut focus = Focus::Priority;
-
-    let mut state = UIState::Main;
-    let mut password = String::new();
-    let mut show_password = false;
-    let mut pending_action = Action::None;
-    let mut reboot_yes = true;
-    let mut quit_yes = false;
-    let original_order: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
-    let mut last_tick = std::time::Instant::now();
-
-    enable_raw_mode()?;
+// Hands the terminal back to the shell. Only the first call after
+// `take_terminal` does anything, so the panic hook, `TerminalGuard` and the
+// normal exit path can all call it.
+fn restore_terminal(inline: bool) -> io::Result<()> {
+    if !TERMINAL_TAKEN.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    disable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    if inline {
+        // Leave the last frame in place and continue below it.
+        writeln!(stdout)?;
+    } else {
+        execute!(stdout, LeaveAlternateScreen)?;
+    }
+    execute!(stdout, cursor::Show)
+}
 
-    loop {
-        terminal.draw(|f| {
-            let area = centered_area(f.area(), 65, 60);
-
-            match &state {
-                UIState::Main => draw_main_ui(
-                    f,
-                    area,
-                    &entries,
-                    focus,
-                    selected_priority,
-                    selected_boot_once,
-                    &current_boot_id,
-                ),
-                UIState::AskPassword => draw_password_popup(f, area, &password, show_password),
-                UIState::PasswordError => draw_password_error_popup(f, area),
-                UIState::ConfirmReboot => draw_reboot_popup(f, area, reboot_yes),
-                UIState::CountdownReboot(seconds) => draw_countdown_screen(f, area, *seconds),
-                UIState::QuitConfirm => draw_quit_confirm_popup(f, area, quit_yes),
-                UIState::Help => draw_help_screen(f, area),
-                UIState::ErrorMessage(msg) => draw_error_message_popup(f, area, msg),
-            }
-        })?;
+// Restores the terminal when dropped, so returning early with `?` doesn't
+// leave the shell in raw mode on the alternate screen.
+struct TerminalGuard {
+    inline: bool,
+}
 
-        if let UIState::CountdownReboot(seconds) = state {
-            if last_tick.elapsed() >= Duration::from_secs(1) {
-                last_tick = std::time::Instant::now();
-                if seconds > 1 {
-                    state = UIState::CountdownReboot(seconds - 1);
-                } else {
-                    let mut reboot = Command::new("sudo")
-                        .arg("reboot")
-                        .stdin(Stdio::null())
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .spawn()?;
-                    let _ = reboot.wait();
-                    break;
-                }
-            }
-        }
+impl TerminalGuard {
+    fn take(inline: bool) -> io::Result<Self> {
+        let guard = TerminalGuard { inline };
+        take_terminal(inline)?;
+        Ok(guard)
+    }
+}
 
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match state {
-                    UIState::Main => match key.code {
-                        KeyCode::Char('q') => {
-                            let current_order: Vec<String> =
-                                entries.iter().map(|e| e.id.clone()).collect();
-                            let has_changes = current_order != original_order;
-                            if has_changes {
-                                state = UIState::QuitConfirm;
-                                quit_yes = false;
-                            } else {
-                                break;
-                            }
-                        }
-
-                        KeyCode::Tab => {
-                            focus = match focus {
-                                Focus::Priority => Focus::BootOnce,
-                                Focus::BootOnce => Focus::Priority,
-                            }
-                        }
-
-                        KeyCode::Up | KeyCode::Char('k') => match focus {
-                            Focus::Priority if selected_priority > 0 => selected_priority -= 1,
-                            Focus::BootOnce if selected_boot_once > 0 => selected_boot_once -= 1,
-                            _ => {}
-                        },
-
-                        KeyCode::Down | KeyCode::Char('j') => match focus {
-                            Focus::Priority if selected_priority + 1 < entries.len() => {
-                                selected_priority += 1
-                            }
-                            Focus::BootOnce if selected_boot_once + 1 < entries.len() => {
-                                selected_boot_once += 1
-                            }
-                            _ => {}
-                        },
-
-                        KeyCode::Char('u') if matches!(focus, Focus::Priority) => {
-                            if selected_priority > 0 {
-                                entries.swap(selected_priority, selected_priority - 1);
-                                selected_priority -= 1;
-                            }
-                        }
-
-                        KeyCode::Char('d') if matches!(focus, Focus::Priority) => {
-                            if selected_priority + 1 < entries.len() {
-                                entries.swap(selected_priority, selected_priority + 1);
-                                selected_priority += 1;
-                            }
-                        }
-
-                        KeyCode::Enter => {
-                            pending_action = match focus {
-                                Focus::Priority => {
-                                    let ids =
-                                        entries.iter().map(|e| e.id.clone()).collect::<Vec<_>>();
-                                    Action::SetOrder(ids)
-                                }
-                                Focus::BootOnce => {
-                                    let id = entries[selected_boot_once].id.clone();
-                                    Action::BootOnce(id)
-                                }
-                            };
-                            password.clear();
-                            state = UIState::AskPassword;
-                        }
-
-                        KeyCode::Char('?') | KeyCode::Char('h') => {
-                            state = UIState::Help;
-                        }
-
-                        _ => {}
-                    },
-
-                    UIState::AskPassword => match key.code {
-                        KeyCode::Esc => {
-                            password.clear();
-                            pending_action = Action::None;
-                            state = UIState::Main;
-                        }
-                        KeyCode::Tab => {
-                            show_password = !show_password;
-                        }
-                        KeyCode::Backspace => {
-                            password.pop();
-                        }
-                        KeyCode::Enter => {
-                            terminal.draw(|f| {
-                                let area = centered_area(f.area(), 65, 60);
-                                draw_processing_screen(f, area);
-                            })?;
-
-                            state = match pending_action.clone() {
-                                Action::SetOrder(order_ids) => {
-                                    execute_set_boot_order(&order_ids, &password)?
-                                }
-                                Action::BootOnce(id) => execute_boot_once(&id, &password)?,
-                                Action::None => UIState::Main,
-                            };
-
-                            if matches!(state, UIState::PasswordError | UIState::ErrorMessage(_)) {
-                                password.clear();
-                            }
-                        }
-                        KeyCode::Char(c) => password.push(c),
-                        _ => {}
-                    },
-
-                    UIState::PasswordError => {
-                        state = UIState::AskPassword;
-                    }
-
-                    UIState::ConfirmReboot => match key.code {
-                        KeyCode::Esc => {
-                            state = UIState::Main;
-                        }
-                        KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
-                            reboot_yes = !reboot_yes;
-                        }
-                        KeyCode::Enter => {
-                            if reboot_yes {
-                                state = UIState::CountdownReboot(5);
-                                last_tick = std::time::Instant::now();
-                            } else {
-                                state = UIState::Main;
-                            }
-                        }
-                        _ => {}
-                    },
-
-                    UIState::CountdownReboot(_) => {
-                        if let KeyCode::Esc = key.code {
-                            state = UIState::Main;
-                        }
-                    }
-
-                    UIState::QuitConfirm => match key.code {
-                        KeyCode::Esc => {
-                            state = UIState::Main;
-                        }
-                        KeyCode::Left | KeyCode::Right | KeyCode::Tab => {
-                            quit_yes = !quit_yes;
-                        }
-                        KeyCode::Enter => {
-                            if quit_yes {
-                                break;
-                            } else {
-                                state = UIState::Main;
-                            }
-                        }
-                        _ => {}
-                    },
-
-                    UIState::Help => {
-                        state = UIState::Main;
-                    }
-
-                    UIState::ErrorMessage(_) => {
-                        state = UIState::AskPassword;
-                    }
-                }
-            }
-        }
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal(self.inline);
     }
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+fn exit_on_signal(
+    signal: &AtomicUsize,
+    app: Option<&App>,
+    lock: Option<lock::InstanceLock>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signal = signal.load(Ordering::Relaxed);
+    if signal != 0 {
+        if app.is_some_and(App::is_dirty) {
+            eprintln!("swiftboot: terminated with unapplied boot order changes");
+        }
+        // `exit` skips destructors, so release the lock first.
+        drop(lock);
+        std::process::exit(128 + signal as i32);
+    }
     Ok(())
 }
@@ -0,0 +1,73 @@
+use crate::cli::{self, Cli};
+use crate::config::{self, Config};
+use crate::keymap::{KeyAction, Keymap};
+use clap::CommandFactory;
+use clap_mangen::{
+    Man,
+    roff::{Roff, bold, italic, roman},
+};
+use std::io::Write;
+
+// `swiftboot generate-man`: the options and subcommands come from the clap
+// definitions, the rest from the tables `--help`, `config init` and the
+// keymap use, so the page can't fall behind the program.
+pub fn render(out: &mut impl Write) -> Result<(), Box<dyn std::error::Error>> {
+    let man = Man::new(Cli::command())
+        .date(env!("SWIFTBOOT_BUILD_DATE"))
+        .source(format!("swiftboot {}", env!("CARGO_PKG_VERSION")));
+    // Everything but the after-help text, which the sections below replace.
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+    man.render_subcommands_section(out)?;
+
+    let mut roff = Roff::new();
+    roff.control("SH", ["KEY BINDINGS"]);
+    roff.text([
+        roman("The defaults. Each action can be given other keys under "),
+        bold("[keys]"),
+        roman(" in the configuration file, by the name in parentheses."),
+    ]);
+    let keymap = Keymap::default_bindings();
+    for action in KeyAction::ALL {
+        roff.control("TP", []);
+        roff.text([bold(keymap.keys_for(action).join(", "))]);
+        roff.text([roman(format!(
+            "{} ({})",
+            action.description(),
+            action.name()
+        ))]);
+    }
+
+    roff.control("SH", ["CONFIGURATION"]);
+    roff.text([
+        roman("Settings in "),
+        italic(cli::FILES[0].0),
+        roman(". "),
+        bold("swiftboot config init"),
+        roman(" writes them all with their defaults."),
+    ]);
+    let defaults = toml::Value::try_from(Config::default())?;
+    for (field, doc) in config::FIELD_DOCS {
+        roff.control("TP", []);
+        roff.text([bold(*field)]);
+        let mut text = vec![roman(*doc)];
+        if let Some(value) = defaults.get(field).filter(|v| !v.is_table()) {
+            text.push(roman(format!(". Default: {}", value)));
+        }
+        roff.text(text);
+    }
+
+    for (heading, rows) in [("ENVIRONMENT", cli::ENVIRONMENT), ("FILES", cli::FILES)] {
+        roff.control("SH", [heading]);
+        for (name, text) in rows {
+            roff.control("TP", []);
+            roff.text([bold(*name)]);
+            roff.text([roman(*text)]);
+        }
+    }
+    roff.to_writer(out)?;
+    Ok(())
+}
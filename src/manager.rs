@@ -0,0 +1,222 @@
+use crate::efi::{self, BootEntry, BootStatus, EntryId, SudoEvent};
+use crate::efivars::{self, Write};
+use std::{fmt, io};
+
+/// Why a [`BootManager`] call failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// efibootmgr or sudo couldn't be started.
+    Spawn(io::Error),
+    /// The command ran and failed, with its error output.
+    Failed(String),
+    /// sudo rejected the password.
+    IncorrectPassword,
+    /// Reading or writing a variable through efivarfs failed.
+    Efivarfs(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Spawn(e) => write!(f, "couldn't run efibootmgr: {}", e),
+            Error::Failed(message) => write!(f, "efibootmgr failed: {}", message),
+            Error::IncorrectPassword => write!(f, "incorrect sudo password"),
+            Error::Efivarfs(message) => write!(f, "efivarfs: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Spawn(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Spawn(e)
+    }
+}
+
+/// Reads and changes the firmware's boot entries.
+pub trait BootManager {
+    /// Every Boot#### entry, with its device path, in the order the
+    /// firmware lists them (not the boot order).
+    ///
+    /// ```no_run
+    /// use swiftboot::{BootManager, Efibootmgr};
+    ///
+    /// for entry in Efibootmgr::new().list_entries()? {
+    ///     println!("Boot{} {}", entry.id, entry.name);
+    /// }
+    /// # Ok::<(), swiftboot::Error>(())
+    /// ```
+    fn list_entries(&self) -> Result<Vec<BootEntry>, Error>;
+
+    /// BootCurrent, BootOrder, BootNext and the menu timeout.
+    fn status(&self) -> Result<BootStatus, Error>;
+
+    /// Replaces BootOrder.
+    ///
+    /// ```no_run
+    /// use swiftboot::{BootManager, Efibootmgr, EntryId};
+    ///
+    /// let order: Vec<EntryId> = ["0002", "0001"]
+    ///     .into_iter()
+    ///     .filter_map(EntryId::parse)
+    ///     .collect();
+    /// Efibootmgr::with_sudo("password").set_order(&order)?;
+    /// # Ok::<(), swiftboot::Error>(())
+    /// ```
+    fn set_order(&self, order: &[EntryId]) -> Result<(), Error>;
+
+    /// Sets BootNext: `id` boots once, on the next reboot.
+    fn set_boot_next(&self, id: &EntryId) -> Result<(), Error>;
+
+    /// Removes BootNext, so the next reboot follows BootOrder again.
+    fn clear_boot_next(&self) -> Result<(), Error>;
+}
+
+/// A [`BootManager`] that runs the efibootmgr binary, found in PATH or
+/// through `$SWIFTBOOT_EFIBOOTMGR`.
+///
+/// Reading works as any user. Writing needs root: either the process runs
+/// as root, or the writes go through `sudo` with the password given to
+/// [`Efibootmgr::with_sudo`].
+pub struct Efibootmgr {
+    sudo_password: Option<String>,
+}
+
+impl Efibootmgr {
+    /// Runs efibootmgr directly, so writes only work as root.
+    pub fn new() -> Efibootmgr {
+        Efibootmgr {
+            sudo_password: None,
+        }
+    }
+
+    /// Runs writes through `sudo -S` with `password`.
+    pub fn with_sudo(password: impl Into<String>) -> Efibootmgr {
+        Efibootmgr {
+            sudo_password: Some(password.into()),
+        }
+    }
+
+    fn read(&self, args: &[&str]) -> Result<String, Error> {
+        let output = efi::efibootmgr_command(args).output()?;
+        if !output.status.success() {
+            return Err(Error::Failed(failure_text(&output)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn write(&self, args: &[&str]) -> Result<(), Error> {
+        let Some(password) = &self.sudo_password else {
+            let output = efi::efibootmgr_command(args).output()?;
+            if !output.status.success() {
+                return Err(Error::Failed(failure_text(&output)));
+            }
+            return Ok(());
+        };
+
+        let events = efi::spawn_sudo_command(&efi::efibootmgr_args(args), password)?;
+        for event in events.iter() {
+            if let SudoEvent::Done(output) = event {
+                return if output.success {
+                    Ok(())
+                } else if output.password_rejected() {
+                    Err(Error::IncorrectPassword)
+                } else {
+                    Err(Error::Failed(output.stderr))
+                };
+            }
+        }
+        Err(Error::Failed("sudo exited without a result".to_string()))
+    }
+}
+
+impl Default for Efibootmgr {
+    fn default() -> Efibootmgr {
+        Efibootmgr::new()
+    }
+}
+
+impl BootManager for Efibootmgr {
+    fn list_entries(&self) -> Result<Vec<BootEntry>, Error> {
+        Ok(efi::parse_entries(&self.read(&["-v"])?, "Boot"))
+    }
+
+    fn status(&self) -> Result<BootStatus, Error> {
+        Ok(efi::parse_status(&self.read(&[])?))
+    }
+
+    fn set_order(&self, order: &[EntryId]) -> Result<(), Error> {
+        self.write(&["-o", &EntryId::join(order)])
+    }
+
+    fn set_boot_next(&self, id: &EntryId) -> Result<(), Error> {
+        self.write(&["-n", id.as_str()])
+    }
+
+    fn clear_boot_next(&self) -> Result<(), Error> {
+        self.write(&["-N"])
+    }
+}
+
+/// A [`BootManager`] that reads and writes the variables in efivarfs
+/// itself, without efibootmgr.
+///
+/// Reading needs efivarfs mounted (and on some distros, root). Writing
+/// needs root. [`efivars::set_base`] points it at another directory laid
+/// out like efivarfs.
+pub struct Efivarfs;
+
+impl Efivarfs {
+    fn write(&self, change: Write) -> Result<(), Error> {
+        match efivars::spawn_write(change).recv() {
+            Ok(SudoEvent::Done(output)) if output.success => Ok(()),
+            Ok(SudoEvent::Done(output)) => Err(Error::Efivarfs(output.stderr)),
+            _ => Err(Error::Efivarfs("the write didn't finish".to_string())),
+        }
+    }
+}
+
+impl BootManager for Efivarfs {
+    fn list_entries(&self) -> Result<Vec<BootEntry>, Error> {
+        efivars::read_entries("Boot").map_err(|e| Error::Efivarfs(e.to_string()))
+    }
+
+    fn status(&self) -> Result<BootStatus, Error> {
+        if !efivars::values_readable() {
+            return Err(Error::Efivarfs(
+                "can't read BootOrder or BootCurrent".to_string(),
+            ));
+        }
+        Ok(efivars::read_status())
+    }
+
+    fn set_order(&self, order: &[EntryId]) -> Result<(), Error> {
+        self.write(Write::Order("BootOrder", EntryId::to_strings(order)))
+    }
+
+    fn set_boot_next(&self, id: &EntryId) -> Result<(), Error> {
+        self.write(Write::Next(id.as_str().to_string()))
+    }
+
+    fn clear_boot_next(&self) -> Result<(), Error> {
+        self.write(Write::ClearNext)
+    }
+}
+
+fn failure_text(output: &std::process::Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.is_empty() {
+        output.status.to_string()
+    } else {
+        stderr
+    }
+}
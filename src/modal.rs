@@ -0,0 +1,350 @@
+use crate::text_input::TextInput;
+use crossterm::event::KeyCode;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use unicode_width::UnicodeWidthStr;
+
+// A centered dialog: a title, wrapped body text, and optionally an input
+// field, a row of buttons and a hint line, in that order. Sized to its
+// content and clamped to the screen.
+pub struct Modal<'a> {
+    title: &'a str,
+    color: Color,
+    width_percent: u16,
+    body: Vec<Line<'a>>,
+    input: Option<(&'a TextInput, bool)>,
+    // Label, highlight and whether it can be pressed.
+    buttons: Vec<(&'a str, Color, bool)>,
+    selected: usize,
+    hint: Option<&'a str>,
+}
+
+impl<'a> Modal<'a> {
+    pub fn new(title: &'a str) -> Modal<'a> {
+        Modal {
+            title,
+            color: Color::White,
+            width_percent: 50,
+            body: Vec::new(),
+            input: None,
+            buttons: Vec::new(),
+            selected: 0,
+            hint: None,
+        }
+    }
+
+    // The border and title color.
+    pub fn color(mut self, color: Color) -> Modal<'a> {
+        self.color = color;
+        self
+    }
+
+    // Width as a percentage of the screen.
+    pub fn width(mut self, percent: u16) -> Modal<'a> {
+        self.width_percent = percent;
+        self
+    }
+
+    pub fn line(mut self, line: impl Into<Line<'a>>) -> Modal<'a> {
+        self.body.push(line.into());
+        self
+    }
+
+    // A one-line text field; `masked` shows a `*` for each grapheme.
+    pub fn input(mut self, input: &'a TextInput, masked: bool) -> Modal<'a> {
+        self.input = Some((input, masked));
+        self
+    }
+
+    // `highlight` is the button's background while it is selected.
+    pub fn button(mut self, label: &'a str, highlight: Color) -> Modal<'a> {
+        self.buttons.push((label, highlight, true));
+        self
+    }
+
+    // A button that can't be pressed yet, drawn dimmed. The dialog's key
+    // handling has to ignore it.
+    pub fn disabled_button(mut self, label: &'a str) -> Modal<'a> {
+        self.buttons.push((label, Color::DarkGray, false));
+        self
+    }
+
+    pub fn selected(mut self, index: usize) -> Modal<'a> {
+        self.selected = index;
+        self
+    }
+
+    pub fn hint(mut self, hint: &'a str) -> Modal<'a> {
+        self.hint = Some(hint);
+        self
+    }
+
+    pub fn render(self, f: &mut Frame, area: Rect) {
+        let width = (area.width * self.width_percent / 100)
+            .max(30)
+            .min(area.width);
+        let text_width = width.saturating_sub(2);
+        let body_height = self
+            .body
+            .iter()
+            .map(|line| wrapped_height(line, text_width))
+            .sum::<u16>();
+
+        let mut rows = vec![Constraint::Length(body_height)];
+        if self.input.is_some() {
+            rows.push(Constraint::Length(3));
+        }
+        if !self.buttons.is_empty() {
+            rows.extend([Constraint::Length(1), Constraint::Length(1)]);
+        }
+        if let Some(hint) = self.hint {
+            rows.push(Constraint::Length(wrapped_height(
+                &Line::from(hint),
+                text_width,
+            )));
+        }
+        let height = rows
+            .iter()
+            .map(|row| match row {
+                Constraint::Length(n) => *n,
+                _ => 0,
+            })
+            .sum::<u16>()
+            + 2;
+
+        let [popup] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup] = Layout::vertical([Constraint::Length(height.min(area.height))])
+            .flex(Flex::Center)
+            .areas(popup);
+        f.render_widget(Clear, popup);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.title)
+            .border_style(Style::default().fg(self.color));
+        let inner = block.inner(popup);
+        f.render_widget(block, popup);
+
+        let areas = Layout::vertical(rows).split(inner);
+        let mut areas = areas.iter();
+
+        if let Some(body) = areas.next() {
+            f.render_widget(
+                Paragraph::new(self.body)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::White)),
+                *body,
+            );
+        }
+
+        if let Some((input, masked)) = self.input
+            && let Some(&field) = areas.next()
+        {
+            let field = Rect {
+                x: field.x + 1,
+                width: field.width.saturating_sub(2),
+                ..field
+            };
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan));
+            let field_width = block.inner(field).width as usize;
+            f.render_widget(
+                Paragraph::new(input.line(field_width, masked)).block(block),
+                field,
+            );
+        }
+
+        if !self.buttons.is_empty()
+            && let (Some(_), Some(&row)) = (areas.next(), areas.next())
+        {
+            let cells = Layout::horizontal(vec![
+                Constraint::Ratio(1, self.buttons.len() as u32);
+                self.buttons.len()
+            ])
+            .split(row);
+            for (i, ((label, highlight, enabled), cell)) in
+                self.buttons.iter().zip(cells.iter()).enumerate()
+            {
+                let style = if i == self.selected {
+                    Style::default().bg(*highlight).fg(Color::Black).bold()
+                } else if !enabled {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                f.render_widget(
+                    Paragraph::new(*label)
+                        .alignment(Alignment::Center)
+                        .style(style),
+                    *cell,
+                );
+            }
+        }
+
+        if let Some(hint) = self.hint
+            && let Some(&row) = areas.next()
+        {
+            f.render_widget(
+                Paragraph::new(hint)
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: true })
+                    .style(Style::default().fg(Color::DarkGray)),
+                row,
+            );
+        }
+    }
+}
+
+// Rows `line` takes when word-wrapped to `width` columns.
+fn wrapped_height(line: &Line, width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+    let mut rows = 1;
+    let mut used = 0;
+    for word in text.split_whitespace() {
+        let word_width = word.width();
+        let needed = if used == 0 {
+            word_width
+        } else {
+            used + 1 + word_width
+        };
+        if needed <= width {
+            used = needed;
+        } else if used == 0 {
+            // A word wider than the line is broken across rows.
+            rows += (word_width - 1) / width;
+            used = (word_width - 1) % width + 1;
+        } else {
+            rows += 1 + word_width.saturating_sub(1) / width;
+            used = word_width.saturating_sub(1) % width + 1;
+        }
+    }
+    rows as u16
+}
+
+// What a key does in a dialog with buttons.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ButtonKey {
+    // The highlight moved to this button.
+    Select(usize),
+    // This button was chosen.
+    Press(usize),
+    Cancel,
+    Ignored,
+}
+
+// The keys every dialog shares: Left/Right/Tab move the highlight among
+// `count` buttons, Enter presses the highlighted one, `y` and `n` press the
+// first and the last, and Esc cancels.
+pub fn button_key(code: KeyCode, selected: usize, count: usize) -> ButtonKey {
+    let count = count.max(1);
+    match code {
+        KeyCode::Left | KeyCode::BackTab => ButtonKey::Select((selected + count - 1) % count),
+        KeyCode::Right | KeyCode::Tab => ButtonKey::Select((selected + 1) % count),
+        KeyCode::Enter => ButtonKey::Press(selected.min(count - 1)),
+        KeyCode::Char('y') | KeyCode::Char('Y') => ButtonKey::Press(0),
+        KeyCode::Char('n') | KeyCode::Char('N') => ButtonKey::Press(count - 1),
+        KeyCode::Esc => ButtonKey::Cancel,
+        _ => ButtonKey::Ignored,
+    }
+}
+
+// `button_key` for a two-button dialog that keeps its highlight as a bool,
+// true for the first button. Updates the highlight and returns which button
+// was pressed, or None.
+pub fn yes_no_key(code: KeyCode, first: &mut bool) -> Option<ButtonKey> {
+    match button_key(code, if *first { 0 } else { 1 }, 2) {
+        ButtonKey::Select(i) => {
+            *first = i == 0;
+            None
+        }
+        ButtonKey::Ignored => None,
+        other => Some(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+
+    fn render(width: u16, height: u16, modal: Modal) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| modal.render(f, f.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|c| c.symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn keys_move_and_press_buttons() {
+        assert!(button_key(KeyCode::Right, 0, 3) == ButtonKey::Select(1));
+        assert!(button_key(KeyCode::Tab, 2, 3) == ButtonKey::Select(0));
+        assert!(button_key(KeyCode::Left, 0, 3) == ButtonKey::Select(2));
+        assert!(button_key(KeyCode::BackTab, 1, 3) == ButtonKey::Select(0));
+        assert!(button_key(KeyCode::Enter, 1, 3) == ButtonKey::Press(1));
+        assert!(button_key(KeyCode::Char('Y'), 2, 3) == ButtonKey::Press(0));
+        assert!(button_key(KeyCode::Char('n'), 0, 3) == ButtonKey::Press(2));
+        assert!(button_key(KeyCode::Esc, 0, 3) == ButtonKey::Cancel);
+        assert!(button_key(KeyCode::Char('x'), 0, 3) == ButtonKey::Ignored);
+        // A dialog without buttons still presses "the" button.
+        assert!(button_key(KeyCode::Enter, 4, 0) == ButtonKey::Press(0));
+    }
+
+    #[test]
+    fn yes_no_keeps_the_highlight() {
+        let mut yes = true;
+        assert!(yes_no_key(KeyCode::Right, &mut yes).is_none());
+        assert!(!yes);
+        assert!(yes_no_key(KeyCode::Enter, &mut yes) == Some(ButtonKey::Press(1)));
+        assert!(yes_no_key(KeyCode::Tab, &mut yes).is_none());
+        assert!(yes);
+        assert!(yes_no_key(KeyCode::Char('q'), &mut yes).is_none());
+        assert!(yes_no_key(KeyCode::Esc, &mut yes) == Some(ButtonKey::Cancel));
+    }
+
+    #[test]
+    fn wrapping_counts_rows() {
+        assert_eq!(wrapped_height(&Line::from(""), 10), 1);
+        assert_eq!(wrapped_height(&Line::from("one two"), 7), 1);
+        assert_eq!(wrapped_height(&Line::from("one two"), 6), 2);
+        // Too long for any line: broken across rows.
+        assert_eq!(wrapped_height(&Line::from("abcdefghij"), 4), 3);
+        assert_eq!(wrapped_height(&Line::from("ab abcdefghij"), 4), 4);
+        assert_eq!(wrapped_height(&Line::from("x"), 0), 1);
+    }
+
+    #[test]
+    fn input_fields_can_be_masked() {
+        let input = TextInput::from("hunter2".to_string());
+        let rows = render(40, 7, Modal::new(" Password ").input(&input, true));
+        assert!(rows.iter().any(|row| row.contains("│*******")));
+        assert!(!rows.iter().any(|row| row.contains("hunter2")));
+    }
+
+    #[test]
+    fn a_modal_is_clamped_to_a_small_screen() {
+        let modal = Modal::new(" T ")
+            .line("A line long enough to wrap more than once here")
+            .button("[ Ok ]", Color::Green)
+            .hint("hint");
+        let rows = render(20, 4, modal);
+        assert_eq!(rows.len(), 4);
+        // Narrower than the usual 30 columns: the whole width, and the
+        // border still closes on screen.
+        assert!(rows[0].starts_with("┌ T ─"));
+        assert!(rows[3].ends_with("┘"));
+    }
+}
@@ -0,0 +1,256 @@
+use regex::Regex;
+use serde::Deserialize;
+use std::{fs, path::Path, process::Command};
+
+pub struct PartitionInfo {
+    pub device: String,
+    pub size: Option<u64>,
+    pub fstype: Option<String>,
+    pub mountpoint: Option<String>,
+}
+
+impl PartitionInfo {
+    // e.g. "nvme0n1p1 — 512 MiB vfat, mounted at /boot/efi"
+    pub fn describe(&self) -> String {
+        let mut details = Vec::new();
+        if let Some(size) = self.size {
+            details.push(format_size(size));
+        }
+        if let Some(fstype) = &self.fstype {
+            details.push(fstype.clone());
+        }
+        let mut text = self.device.clone();
+        if !details.is_empty() {
+            text.push_str(" — ");
+            text.push_str(&details.join(" "));
+        }
+        if let Some(mountpoint) = &self.mountpoint {
+            text.push_str(&format!(", mounted at {}", mountpoint));
+        }
+        text
+    }
+}
+
+pub enum PartitionStatus {
+    Found(PartitionInfo),
+    // The GUID isn't on any attached disk: a dangling entry.
+    Missing,
+    // Nothing to look the GUID up in (no udev links, no lsblk).
+    Unknown,
+}
+
+// Looks up a GPT partition by its unique GUID. Behind a trait so the lookup
+// can be swapped out where there are no real block devices.
+pub trait PartitionResolver {
+    fn resolve(&self, guid: &str) -> PartitionStatus;
+}
+
+// The partition GUID of an `HD(n,GPT,<guid>,start,size)` device path node.
+pub fn partition_guid(device_path: &str) -> Option<String> {
+    let regex = Regex::new(r"HD\(\d+,GPT,(?P<guid>[0-9A-Fa-f-]{36})").unwrap();
+    regex
+        .captures(device_path)
+        .map(|cap| cap["guid"].to_ascii_lowercase())
+}
+
+const BY_PARTUUID: &str = "/dev/disk/by-partuuid";
+
+#[derive(Deserialize)]
+struct Lsblk {
+    blockdevices: Vec<LsblkDevice>,
+}
+
+#[derive(Deserialize)]
+struct LsblkDevice {
+    name: String,
+    partuuid: Option<String>,
+    size: Option<u64>,
+    fstype: Option<String>,
+    mountpoint: Option<String>,
+    #[serde(default)]
+    children: Vec<LsblkDevice>,
+}
+
+// Resolves against udev's `/dev/disk/by-partuuid` links, with lsblk for
+// the size, filesystem and mount point (and as a fallback for the lookup).
+pub struct SystemResolver {
+    lsblk: Option<Vec<LsblkDevice>>,
+}
+
+impl SystemResolver {
+    pub fn new() -> SystemResolver {
+        SystemResolver {
+            lsblk: read_lsblk(),
+        }
+    }
+
+    fn lsblk_device(&self, guid: &str) -> Option<&LsblkDevice> {
+        fn find<'a>(devices: &'a [LsblkDevice], guid: &str) -> Option<&'a LsblkDevice> {
+            devices.iter().find_map(|d| {
+                if d.partuuid
+                    .as_deref()
+                    .is_some_and(|p| p.eq_ignore_ascii_case(guid))
+                {
+                    Some(d)
+                } else {
+                    find(&d.children, guid)
+                }
+            })
+        }
+        find(self.lsblk.as_deref()?, guid)
+    }
+}
+
+impl PartitionResolver for SystemResolver {
+    fn resolve(&self, guid: &str) -> PartitionStatus {
+        let linked = fs::canonicalize(Path::new(BY_PARTUUID).join(guid))
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()));
+        let listed = self.lsblk_device(guid);
+
+        match (linked, listed) {
+            (device, Some(d)) => PartitionStatus::Found(PartitionInfo {
+                device: device.unwrap_or_else(|| d.name.clone()),
+                size: d.size,
+                fstype: d.fstype.clone(),
+                mountpoint: d.mountpoint.clone(),
+            }),
+            (Some(device), None) => PartitionStatus::Found(PartitionInfo {
+                device,
+                size: None,
+                fstype: None,
+                mountpoint: None,
+            }),
+            (None, None) if self.lsblk.is_some() || Path::new(BY_PARTUUID).is_dir() => {
+                PartitionStatus::Missing
+            }
+            (None, None) => PartitionStatus::Unknown,
+        }
+    }
+}
+
+// The disk a partition device belongs to: "nvme0n1p2" -> "nvme0n1",
+// "mmcblk0p1" -> "mmcblk0", "sda1" -> "sda".
+pub fn disk_name(device: &str) -> String {
+    let regex = Regex::new(r"^(?P<disk>.*\d)p\d+$").unwrap();
+    match regex.captures(device) {
+        Some(cap) => cap["disk"].to_string(),
+        None => device
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .to_string(),
+    }
+}
+
+fn read_lsblk() -> Option<Vec<LsblkDevice>> {
+    let output = Command::new("lsblk")
+        .args(["-J", "-b", "-o", "NAME,PARTUUID,SIZE,FSTYPE,MOUNTPOINT"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice::<Lsblk>(&output.stdout)
+        .ok()
+        .map(|l| l.blockdevices)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size.fract() == 0.0 || size >= 100.0 {
+        format!("{:.0} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUID: &str = "8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b";
+
+    #[test]
+    fn guid_comes_from_the_hd_node() {
+        let path = "PciRoot(0x0)/Pci(0x1d,0x0)/NVMe(0x1,00-00-00-00-00-00-00-00)/\
+                    HD(1,GPT,8F7A2C1E-5B3D-4E6F-9A0B-1C2D3E4F5A6B,0x800,0x100000)/\
+                    File(\\EFI\\fedora\\shimx64.efi)";
+        assert_eq!(partition_guid(path).as_deref(), Some(GUID));
+        // MBR partitions have a signature, not a GUID.
+        assert_eq!(partition_guid("HD(1,MBR,0x1234abcd,0x800,0x100000)"), None);
+        assert_eq!(partition_guid("PciRoot(0x0)/Pci(0x14,0x0)/USB(3,0)"), None);
+        assert_eq!(partition_guid(""), None);
+    }
+
+    #[test]
+    fn disks_are_named_without_the_partition_number() {
+        assert_eq!(disk_name("nvme0n1p2"), "nvme0n1");
+        assert_eq!(disk_name("mmcblk0p1"), "mmcblk0");
+        assert_eq!(disk_name("sda1"), "sda");
+        assert_eq!(disk_name("vdb12"), "vdb");
+    }
+
+    #[test]
+    fn sizes_use_binary_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(512 * 1024 * 1024), "512 MiB");
+        assert_eq!(format_size(1536 * 1024 * 1024), "1.5 GiB");
+        assert_eq!(format_size(250 * 1024 * 1024 * 1024 + 1), "250 GiB");
+    }
+
+    #[test]
+    fn description_leaves_out_what_is_not_known() {
+        let mut info = PartitionInfo {
+            device: "nvme0n1p1".to_string(),
+            size: Some(512 * 1024 * 1024),
+            fstype: Some("vfat".to_string()),
+            mountpoint: Some("/boot/efi".to_string()),
+        };
+        assert_eq!(
+            info.describe(),
+            "nvme0n1p1 — 512 MiB vfat, mounted at /boot/efi"
+        );
+        info.mountpoint = None;
+        info.size = None;
+        assert_eq!(info.describe(), "nvme0n1p1 — vfat");
+        info.fstype = None;
+        assert_eq!(info.describe(), "nvme0n1p1");
+    }
+
+    #[test]
+    fn lsblk_finds_partitions_below_their_disk() {
+        let json = format!(
+            r#"{{"blockdevices": [
+                {{"name": "sda", "partuuid": null, "size": 1000204886016,
+                  "fstype": null, "mountpoint": null}},
+                {{"name": "nvme0n1", "partuuid": null, "size": 512110190592,
+                  "fstype": null, "mountpoint": null, "children": [
+                    {{"name": "nvme0n1p1", "partuuid": "{}", "size": 536870912,
+                      "fstype": "vfat", "mountpoint": "/boot/efi"}}
+                ]}}
+            ]}}"#,
+            GUID.to_ascii_uppercase()
+        );
+        let resolver = SystemResolver {
+            lsblk: serde_json::from_str::<Lsblk>(&json)
+                .ok()
+                .map(|l| l.blockdevices),
+        };
+        match resolver.resolve(GUID) {
+            PartitionStatus::Found(info) => assert_eq!(
+                info.describe(),
+                "nvme0n1p1 — 512 MiB vfat, mounted at /boot/efi"
+            ),
+            _ => panic!("partition not found"),
+        }
+        assert!(matches!(
+            resolver.resolve("00000000-0000-0000-0000-000000000000"),
+            PartitionStatus::Missing
+        ));
+    }
+}
@@ -0,0 +1,702 @@
+use crate::app::{self, Action, App, COUNTDOWN, DeviceState, READ_ONLY_LOCKED, UIState};
+use crate::config::BootOnceMethod;
+use crate::state;
+use crate::text_input::TextInput;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::{
+    io::{self, BufRead, IsTerminal, Write},
+    thread,
+    time::Duration,
+};
+use swiftboot::efi;
+
+const COMMANDS: &str = "Commands: list, order <numbers...>, once <number>, help, quit";
+
+// Line-based alternative to the TUI: plain sentences out, one command per
+// line in. Actions go through the same `App` execution path as the TUI.
+pub fn run(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let UIState::InstanceLocked(pid) = app.state {
+        match pid {
+            Some(pid) => write!(out, "Another swiftboot instance (PID {}) is running", pid)?,
+            None => write!(out, "Another swiftboot instance is running")?,
+        }
+        writeln!(out, "; opening read-only.")?;
+        app.read_only = Some(READ_ONLY_LOCKED);
+        app.state = UIState::Main;
+    }
+
+    if app.read_only.is_none() && app.check_safe_apply() {
+        writeln!(
+            out,
+            "The safe apply window passed without `swiftboot confirm`."
+        )?;
+        match &app.state {
+            UIState::ErrorMessage(e) => {
+                writeln!(out, "{}", e)?;
+                app.state = UIState::Main;
+            }
+            _ => {
+                app.state = UIState::Main;
+                // Declining leaves the safety net in place for next time.
+                if let Err(e) = run_revert(app, input, out) {
+                    writeln!(out, "{}", e)?;
+                }
+            }
+        }
+    }
+
+    if app.read_only.is_none() && app.check_temporary_order() {
+        let UIState::RestoreOrder(temporary) = std::mem::replace(&mut app.state, UIState::Main)
+        else {
+            unreachable!()
+        };
+        writeln!(
+            out,
+            "{} was put first to boot it once.",
+            app.entry_name(&temporary.target)
+        )?;
+        write!(
+            out,
+            "Restore the previous order {}? (y/N) ",
+            app.order_names(&temporary.original)
+        )?;
+        if read_answer(input, out)?.eq_ignore_ascii_case("y") {
+            match app.restore_action(&temporary) {
+                Ok(action) => {
+                    let old = app.draft_order();
+                    app.reorder_entries(&temporary.original);
+                    app.pending_action = action;
+                    if run_action(app, input, out)? {
+                        writeln!(out, "Previous boot order restored.")?;
+                    } else {
+                        app.set_draft(&old);
+                    }
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            }
+        } else {
+            app.keep_temporary_order();
+        }
+    }
+
+    for message in [app.check_deferred(), app.check_armed_boot_next()]
+        .into_iter()
+        .flatten()
+    {
+        writeln!(out, "{}.", message)?;
+    }
+    print_entries(app, out)?;
+    writeln!(out, "{}", COMMANDS)?;
+
+    loop {
+        write!(out, "> ")?;
+        out.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+
+        match command.to_ascii_lowercase().as_str() {
+            "list" | "ls" => print_entries(app, out)?,
+            "help" | "?" => writeln!(out, "{}", COMMANDS)?,
+            "quit" | "exit" | "q" => break,
+            "order" => match parse_order(&args, app.rows().len()) {
+                Ok(positions) => {
+                    let old = app.draft_order();
+                    let rows: Vec<String> = app.rows().iter().map(|e| e.id.clone()).collect();
+                    let order: Vec<String> = positions.iter().map(|&i| rows[i].clone()).collect();
+                    app.reorder_entries(&order);
+                    match app.order_action(&app.order_to_apply()) {
+                        Ok(action) => app.pending_action = action,
+                        Err(e) => {
+                            writeln!(out, "{}", e)?;
+                            continue;
+                        }
+                    }
+                    if run_action(app, input, out)? {
+                        report_order_updated(app, out)?;
+                        print_entries(app, out)?;
+                    } else {
+                        app.set_draft(&old);
+                    }
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            },
+            "once" => match parse_position(&args, app.rows().len()) {
+                Ok(i) => {
+                    let id = app.rows()[i].id.clone();
+                    app.pending_action = Action::BootOnce(id.clone());
+                    let by_reorder = match app.config.boot_once_method {
+                        BootOnceMethod::BootNext => false,
+                        BootOnceMethod::ReorderTemporarily => true,
+                        BootOnceMethod::Ask => {
+                            write!(
+                                out,
+                                "Boot once via (n)BootNext or by (r)eordering temporarily? [n] "
+                            )?;
+                            read_answer(input, out)?.eq_ignore_ascii_case("r")
+                        }
+                    };
+                    if by_reorder {
+                        app.boot_once_by_reorder();
+                    }
+                    let name = app.entry_name(&id);
+                    if run_action(app, input, out)? {
+                        writeln!(out, "The next boot will start {}.", name)?;
+                        if by_reorder {
+                            writeln!(
+                                out,
+                                "It is first in the boot order until swiftboot runs again and offers to restore the order."
+                            )?;
+                        }
+                        if let Some(device) = &app.firmware_update {
+                            writeln!(
+                                out,
+                                "Note: a firmware update for {} is staged and runs first.",
+                                device
+                            )?;
+                        }
+                        if !app.can_reboot() {
+                            writeln!(out, "Reboot manually to start it.")?;
+                            continue;
+                        }
+                        write!(out, "Reboot now? (y/N) ")?;
+                        if read_answer(input, out)?.eq_ignore_ascii_case("y") {
+                            writeln!(out, "Rebooting.")?;
+                            app.perform_reboot();
+                            if let UIState::ErrorMessage(msg) = &app.state {
+                                writeln!(out, "{}", msg)?;
+                                app.state = UIState::Main;
+                                continue;
+                            }
+                            break;
+                        }
+                    }
+                }
+                Err(e) => writeln!(out, "{}", e)?,
+            },
+            other => writeln!(out, "Unknown command \"{}\". {}", other, COMMANDS)?,
+        }
+    }
+    Ok(())
+}
+
+// `swiftboot apply-profile <name>`: shows the resulting order and applies it
+// after asking for the password.
+pub fn apply_profile(
+    app: &mut App,
+    name: &str,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_writable(app)?;
+    let order = app.profile_order(name)?;
+    app.reorder_entries(&order);
+    writeln!(out, "Profile \"{}\":", name)?;
+    print_entries(app, out)?;
+
+    app.pending_action = app.order_action(&order)?;
+    if run_action(app, input, out)? {
+        report_order_updated(app, out)?;
+        Ok(())
+    } else {
+        Err("Boot order not changed".into())
+    }
+}
+
+// `swiftboot swap-default`: swaps the first two entries of the live boot
+// order and applies it after asking for the password.
+pub fn swap_default(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_writable(app)?;
+    let before = app.order_to_apply();
+    app.swap_default()?;
+    let after = app.order_to_apply();
+    writeln!(out, "Before: {}", app.order_names(&before))?;
+    writeln!(out, "After:  {}", app.order_names(&after))?;
+
+    app.pending_action = app.order_action(&after)?;
+    if run_action(app, input, out)? {
+        report_order_updated(app, out)?;
+        Ok(())
+    } else {
+        Err("Boot order not changed".into())
+    }
+}
+
+// `swiftboot revert`: puts back the order saved by a safe apply.
+pub fn revert(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    check_writable(app)?;
+    app.pending_action = app.revert_action()?;
+    run_revert(app, input, out)
+}
+
+fn run_revert(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(safety) = app.safe_apply() {
+        writeln!(out, "Restoring: {}", app.order_names(&safety.old_order))?;
+    }
+    if run_action(app, input, out)? {
+        writeln!(out, "Previous boot order restored.")?;
+        Ok(())
+    } else {
+        Err("Boot order not restored".into())
+    }
+}
+
+// `swiftboot reboot`: the reboot prompt and countdown of the TUI, with
+// nothing written first.
+pub fn reboot(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !app.can_reboot() {
+        return Err("Rebooting is disabled (reboot_cmd = [])".into());
+    }
+    let target = app.reboot_target();
+    if let Some(device) = &app.firmware_update {
+        writeln!(
+            out,
+            "Note: a firmware update for {} is staged and runs first.",
+            device
+        )?;
+    }
+    if !app.config.expert_mode {
+        write!(
+            out,
+            "Reboot into {} ({}) now? (y/N) ",
+            target.target,
+            target.kind()
+        )?;
+        if !read_answer(input, out)?.eq_ignore_ascii_case("y") {
+            writeln!(out, "Not rebooting.")?;
+            return Ok(());
+        }
+    }
+    // Ctrl+C stops it, as nothing has been written.
+    for seconds in (1..=COUNTDOWN.as_secs()).rev() {
+        write!(
+            out,
+            "\rRebooting into {} in {}s... ",
+            target.target, seconds
+        )?;
+        out.flush()?;
+        thread::sleep(Duration::from_secs(1));
+    }
+    writeln!(out)?;
+    app.perform_reboot();
+    match &app.state {
+        UIState::ErrorMessage(message) => Err(message.clone().into()),
+        _ => Ok(()),
+    }
+}
+
+// Where efibootmgr needs root even to read, the entries are read through
+// sudo before anything else.
+pub fn read_with_sudo(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(out, "{}.", efi::ReadNeedsRoot)?;
+    app.pending_action = Action::ReadEntries;
+    if run_action(app, input, out)? {
+        Ok(())
+    } else {
+        Err("The boot entries weren't read".into())
+    }
+}
+
+// `swiftboot deferred`: shows the change deferred to shutdown, and with
+// `cancel` stops its unit.
+pub fn deferred(
+    app: &mut App,
+    cancel: bool,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(message) = app.check_deferred() {
+        writeln!(out, "{}.", message)?;
+    }
+    let Some(deferred) = app.deferred() else {
+        writeln!(out, "No change is deferred to shutdown.")?;
+        return Ok(());
+    };
+    writeln!(
+        out,
+        "Deferred {}: boot order {} at the next shutdown.",
+        state::local_time(deferred.deferred_at),
+        app.order_names(&deferred.order)
+    )?;
+    if !cancel {
+        return Ok(());
+    }
+    check_writable(app)?;
+    app.pending_action = Action::CancelDeferred;
+    if run_action(app, input, out)? {
+        writeln!(out, "Deferred change cancelled.")?;
+        Ok(())
+    } else {
+        Err("Deferred change not cancelled".into())
+    }
+}
+
+fn read_answer(input: &mut impl BufRead, out: &mut impl Write) -> io::Result<String> {
+    out.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+fn report_order_updated(app: &App, out: &mut impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "Boot order updated. It takes effect on the next reboot."
+    )?;
+    if let Some(safety) = app.safe_apply() {
+        writeln!(
+            out,
+            "Safe apply: the next boot goes to the previous default once. Run `swiftboot confirm` within {} minutes, or the previous order is restored the next time swiftboot runs.",
+            safety.minutes_left(state::unix_now())
+        )?;
+    }
+    Ok(())
+}
+
+// The one-shot commands write without the TUI's read-only fallback.
+pub fn check_writable(app: &App) -> Result<(), Box<dyn std::error::Error>> {
+    if let UIState::InstanceLocked(_) = app.state {
+        return Err("Another swiftboot instance is running".into());
+    }
+    if let Some(reason) = app.read_only {
+        return Err(format!("Read-only: {}", reason).into());
+    }
+    Ok(())
+}
+
+fn print_entries(app: &App, out: &mut impl Write) -> io::Result<()> {
+    if app.has_boot_order {
+        writeln!(out, "Boot entries, in boot order:")?;
+    } else {
+        writeln!(
+            out,
+            "No BootOrder is set; applying an order will create one. Boot entries:"
+        )?;
+    }
+    for (i, e) in app.rows().into_iter().enumerate() {
+        let mut notes = Vec::new();
+        let markers = app.markers(e);
+        if markers.current {
+            notes.push("running now");
+        }
+        if markers.default {
+            notes.push("default");
+        }
+        if markers.next {
+            notes.push("next boot");
+        }
+        if app.previous_boot.as_deref() == Some(e.id.as_str()) {
+            notes.push("booted previously");
+        }
+        if !e.active {
+            notes.push("inactive");
+        }
+        if !app.in_boot_order(e) {
+            notes.push("not in BootOrder");
+        }
+        if e.uses_fallback_loader() && !e.is_removable() {
+            notes.push("fallback loader");
+        }
+        match app.device_state(e) {
+            DeviceState::Present => {}
+            DeviceState::Unplugged => notes.push("not connected"),
+            DeviceState::Missing => notes.push("missing device"),
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(", {}", notes.join(", "))
+        };
+        writeln!(out, "{}. {}{}", i + 1, app.display_name(e), notes)?;
+    }
+    Ok(())
+}
+
+// Turns "3 1" into list indexes [2, 0, 1, ...]: the named entries first, in
+// that order, followed by the rest in their current relative order.
+fn parse_order(args: &[&str], len: usize) -> Result<Vec<usize>, String> {
+    if args.is_empty() {
+        return Err("Usage: order <numbers...>, for example: order 3 1 2".to_string());
+    }
+
+    let mut positions = Vec::new();
+    for arg in args {
+        let i = parse_number(arg, len)?;
+        if positions.contains(&i) {
+            return Err(format!("Entry {} is listed more than once.", i + 1));
+        }
+        positions.push(i);
+    }
+    let rest: Vec<usize> = (0..len).filter(|i| !positions.contains(i)).collect();
+    positions.extend(rest);
+    Ok(positions)
+}
+
+fn parse_position(args: &[&str], len: usize) -> Result<usize, String> {
+    match args {
+        [arg] => parse_number(arg, len),
+        _ => Err("Usage: once <number>, for example: once 2".to_string()),
+    }
+}
+
+fn parse_number(arg: &str, len: usize) -> Result<usize, String> {
+    match arg.parse::<usize>() {
+        Ok(n) if (1..=len).contains(&n) => Ok(n - 1),
+        _ => Err(format!(
+            "\"{}\" is not an entry number between 1 and {}.",
+            arg, len
+        )),
+    }
+}
+
+// Asks for the password and runs the pending action, retrying on a wrong
+// password. Returns whether the action succeeded.
+fn run_action(
+    app: &mut App,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // Reading is fine in read-only mode.
+    if let Some(reason) = app.read_only
+        && app.pending_action != Action::ReadEntries
+    {
+        writeln!(out, "Read-only: {}.", reason)?;
+        app.pending_action = Action::None;
+        return Ok(false);
+    }
+    if let Some((windows, linux)) = app.windows_first_warning() {
+        writeln!(out, "Note: {} will boot before {}.", windows, linux)?;
+        writeln!(
+            out,
+            "  Windows updates sometimes rewrite the boot order and can drop {} from it.",
+            linux
+        )?;
+        writeln!(
+            out,
+            "  (Set warn_windows_first = false in the config to hide this.)"
+        )?;
+    }
+    if let Some(warning) = app::low_nvram_warning(&app.pending_action) {
+        writeln!(out, "Warning: {}.", warning)?;
+    }
+
+    loop {
+        // Nothing to ask for when writing to efivarfs as root.
+        if !app.efivarfs_writes {
+            app.password = TextInput::from(read_password(input, out)?);
+        }
+        if app.password.is_empty() && !app.efivarfs_writes {
+            writeln!(out, "Cancelled.")?;
+            app.pending_action = Action::None;
+            return Ok(false);
+        }
+
+        wait_for_pending(app, out)?;
+        app.password.clear();
+
+        match &app.state {
+            UIState::PasswordError => writeln!(out, "Incorrect password, please try again.")?,
+            UIState::ErrorMessage(msg) => {
+                writeln!(out, "The command failed: {}", msg)?;
+                app.state = UIState::Main;
+                return Ok(false);
+            }
+            UIState::NvramFull(msg) => {
+                writeln!(out, "The command failed: {}", msg)?;
+                writeln!(out, "{}", efi::NVRAM_FULL_ADVICE)?;
+                app.state = UIState::Main;
+                return Ok(false);
+            }
+            UIState::OrderModified {
+                requested, actual, ..
+            } => {
+                writeln!(out, "Warning: the firmware modified the boot order.")?;
+                writeln!(out, "  Requested: {}", requested.join(", "))?;
+                writeln!(out, "  Actual:    {}", actual.join(", "))?;
+                app.state = UIState::Main;
+                return Ok(true);
+            }
+            _ => {
+                app.state = UIState::Main;
+                return Ok(true);
+            }
+        }
+    }
+}
+
+// Runs the pending action to completion, passing on anything sudo asks for
+// along the way (e.g. to touch a security key).
+pub fn wait_for_pending(
+    app: &mut App,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut shown: Option<String> = None;
+    app.execute_pending()?;
+    while app.is_running() {
+        if let Some((_, Some(prompt))) = app.job_status()
+            && shown.as_deref() != Some(prompt)
+        {
+            writeln!(out, "{}", prompt)?;
+            shown = Some(prompt.to_string());
+        }
+        thread::sleep(Duration::from_millis(50));
+        app.execute_pending()?;
+    }
+    if let Some(warnings) = app.command_warnings.take() {
+        writeln!(out, "efibootmgr reported warnings:")?;
+        for line in warnings.lines() {
+            writeln!(out, "  {}", line)?;
+        }
+    }
+    Ok(())
+}
+
+// Reads the sudo password without echoing it when attached to a terminal.
+// An empty line cancels.
+fn read_password(input: &mut impl BufRead, out: &mut impl Write) -> io::Result<String> {
+    if !io::stdin().is_terminal() {
+        write!(out, "Sudo password (empty to cancel): ")?;
+        out.flush()?;
+        let mut line = String::new();
+        input.read_line(&mut line)?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+    read_hidden(out, "Sudo password (empty to cancel): ")
+}
+
+// Reads a line from the terminal without echoing it. crossterm falls back
+// to /dev/tty when stdin is redirected, as it is for `batch`.
+pub fn read_hidden(out: &mut impl Write, prompt: &str) -> io::Result<String> {
+    write!(out, "{}", prompt)?;
+    out.flush()?;
+
+    let mut password = TextInput::default();
+    enable_raw_mode()?;
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    password.clear();
+                    break Ok(());
+                }
+                _ => {
+                    password.handle_key(key);
+                }
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    disable_raw_mode()?;
+    writeln!(out)?;
+    result.map(|_| password.text().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::READ_ONLY_REQUESTED;
+    use crate::app::testing::{app, order};
+    use std::io::Cursor;
+
+    fn session(app: &mut App, script: &str) -> String {
+        let mut out = Vec::new();
+        run(app, &mut Cursor::new(script), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn order_puts_named_entries_first() {
+        assert_eq!(parse_order(&["3", "1"], 3), Ok(vec![2, 0, 1]));
+        assert_eq!(parse_order(&["2"], 3), Ok(vec![1, 0, 2]));
+    }
+
+    #[test]
+    fn order_rejects_bad_input() {
+        assert!(parse_order(&[], 3).unwrap_err().starts_with("Usage:"));
+        assert_eq!(
+            parse_order(&["1", "1"], 3),
+            Err("Entry 1 is listed more than once.".to_string())
+        );
+        assert_eq!(
+            parse_order(&["4"], 3),
+            Err("\"4\" is not an entry number between 1 and 3.".to_string())
+        );
+    }
+
+    #[test]
+    fn numbers_are_one_based() {
+        assert_eq!(parse_number("1", 3), Ok(0));
+        assert_eq!(parse_number("3", 3), Ok(2));
+        assert!(parse_number("0", 3).is_err());
+        assert!(parse_number("-1", 3).is_err());
+        assert!(parse_number("two", 3).is_err());
+        assert!(parse_position(&["1", "2"], 3).is_err());
+    }
+
+    #[test]
+    fn list_prints_entries_in_boot_order() {
+        let mut app = app();
+        let out = session(&mut app, "list\nquit\n");
+        let fedora = out.find("1. Fedora").unwrap();
+        let windows = out.find("2. Windows Boot Manager").unwrap();
+        assert!(fedora < windows);
+        assert!(out.contains("3. UEFI: USB"));
+    }
+
+    #[test]
+    fn unknown_commands_and_errors_keep_the_session_going() {
+        let mut app = app();
+        let out = session(&mut app, "bogus\norder 9\nlist\n");
+        assert!(out.contains("Unknown command \"bogus\"."));
+        assert!(out.contains("\"9\" is not an entry number between 1 and 3."));
+        // Reaching the end of input behaves like quit.
+        assert!(out.contains("1. Fedora"));
+    }
+
+    #[test]
+    fn read_only_order_is_refused_and_undone() {
+        let mut app = app();
+        app.read_only = Some(READ_ONLY_REQUESTED);
+        let out = session(&mut app, "order 3 1\nquit\n");
+        assert!(out.contains("Read-only: started with --read-only."));
+        assert_eq!(order(&app), ["0001", "0002", "0003"]);
+        assert!(app.pending_action == Action::None);
+    }
+}
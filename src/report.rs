@@ -0,0 +1,314 @@
+use crate::cli;
+use crate::config::Config;
+use crate::state;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use swiftboot::efi;
+
+// Captures kept in the state directory, newest last.
+const KEPT: usize = 3;
+
+// Identifiers in device paths that point at one particular machine: GPT
+// partition GUIDs, MBR disk signatures, NVMe EUI-64s, USB serial numbers
+// and MAC addresses. Only the first group is replaced.
+const SERIALS: &[&str] = &[
+    r"HD\(\d+,GPT,([0-9A-Fa-f-]+)",
+    r"HD\(\d+,MBR,0x([0-9A-Fa-f]+)",
+    r"NVMe\(0x[0-9A-Fa-f]+,([0-9A-Fa-f-]+)\)",
+    r"UsbWwid\([^,)]*,[^,)]*,[^,)]*,([^)]+)\)",
+    r"MAC\(([0-9A-Fa-f]+),",
+];
+
+// Network nodes and the addresses in them, e.g. both ends of
+// `IPv4(192.168.1.20:0<->10.0.0.1:0,0,0)`.
+const ADDRESSES: &[(&str, &str)] = &[
+    (r"IPv4\(([^)]*)\)", r"(\d{1,3}(?:\.\d{1,3}){3})"),
+    (
+        r"IPv6\(([^)]*)\)",
+        r"([0-9A-Fa-f]*:[0-9A-Fa-f]*:[0-9A-Fa-f:]*)",
+    ),
+];
+
+fn captures_dir() -> Option<PathBuf> {
+    Some(state::state_path()?.parent()?.join("captures"))
+}
+
+// Writes the captures taken since the last call to the state directory
+// and drops all but the newest `KEPT`. Does nothing unless capturing is on;
+// see `efi::set_capturing`.
+pub fn save_captures() {
+    let captures = efi::take_captures();
+    if captures.is_empty() {
+        return;
+    }
+    let Some(dir) = captures_dir() else {
+        return;
+    };
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    for capture in captures.iter().rev().take(KEPT).rev() {
+        // Nanoseconds, zero-padded so the names sort by age.
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let text = format!("# {}\n{}", capture.command, capture.output);
+        let _ = fs::write(dir.join(format!("{:020}.txt", nanos)), text);
+    }
+    for old in saved().iter().rev().skip(KEPT) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+// The saved captures, oldest first.
+fn saved() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = captures_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|dirent| Some(dirent.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    files.sort();
+    files
+}
+
+// Replaces each identifier matched by `SERIALS`, and each address in the
+// `ADDRESSES` nodes, with a placeholder of the same shape, numbered by first
+// appearance, so the same disk still shows up as the same disk and the
+// output still parses.
+pub fn scrub(text: &str) -> String {
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut text = text.to_string();
+    for pattern in SERIALS {
+        let re = Regex::new(pattern).expect("valid pattern");
+        text = replace_first_group(&text, &re, |found| pseudonym(&mut seen, found));
+    }
+    for (node, address) in ADDRESSES {
+        let node = Regex::new(node).expect("valid pattern");
+        let address = Regex::new(address).expect("valid pattern");
+        text = replace_first_group(&text, &node, |inside| {
+            replace_first_group(inside, &address, |found| pseudonym(&mut seen, found))
+        });
+    }
+    text
+}
+
+// `text` with the first group of every match of `re` put through `replace`.
+fn replace_first_group(text: &str, re: &Regex, mut replace: impl FnMut(&str) -> String) -> String {
+    let mut replaced = String::new();
+    let mut last = 0;
+    for found in re.captures_iter(text).filter_map(|c| c.get(1)) {
+        replaced.push_str(&text[last..found.start()]);
+        replaced.push_str(&replace(found.as_str()));
+        last = found.end();
+    }
+    replaced.push_str(&text[last..]);
+    replaced
+}
+
+// The placeholder for `value`: the one it already got, or the next one.
+// An all-zero value, like the unspecified address of an entry that asks
+// DHCP, identifies nothing and stays.
+fn pseudonym(seen: &mut HashMap<String, String>, value: &str) -> String {
+    if !value.chars().any(|c| c.is_ascii_alphanumeric() && c != '0') {
+        return value.to_string();
+    }
+    let n = seen.len() + 1;
+    seen.entry(value.to_ascii_lowercase())
+        .or_insert_with(|| placeholder(value, n))
+        .clone()
+}
+
+// `value` with its letters and digits replaced by the digits of `n`,
+// right-aligned and zero-filled: placeholder("8f7a-1b2c", 3) is "0000-0003".
+fn placeholder(value: &str, n: usize) -> String {
+    let mut digits = n.to_string().into_bytes();
+    let mut chars: Vec<char> = value.chars().collect();
+    for c in chars.iter_mut().rev() {
+        if c.is_ascii_alphanumeric() {
+            *c = digits.pop().map_or('0', char::from);
+        }
+    }
+    chars.into_iter().collect()
+}
+
+// `swiftboot report`: everything a parse bug report needs, as one block of
+// text on stdout. Without a saved capture the entries are read now.
+pub fn run(config: &Config, redact: bool) -> Result<(), Box<dyn std::error::Error>> {
+    efi::configure(&config.efibootmgr(), &config.efibootmgr_extra_args)?;
+
+    let mut out = String::new();
+    writeln!(out, "## Version\n{}", cli::version_text())?;
+    if let Ok(kernel) = fs::read_to_string("/proc/sys/kernel/osrelease") {
+        writeln!(out, "kernel: {}", kernel.trim())?;
+    }
+    if let Some(firmware) = efi::firmware_info().describe() {
+        writeln!(out, "firmware: {}", firmware)?;
+    }
+
+    let (heading, capture) = match saved().last() {
+        Some(path) => {
+            let saved_at = path
+                .file_stem()
+                .and_then(|stem| stem.to_str()?.parse::<u128>().ok())
+                .map_or("an unknown time".to_string(), |nanos| {
+                    state::local_time((nanos / 1_000_000_000) as u64)
+                });
+            (
+                format!("## Capture (saved {})", saved_at),
+                fs::read_to_string(path)?,
+            )
+        }
+        None => {
+            let output = efi::efibootmgr_command(&["-v"]).output()?;
+            let mut text = format!(
+                "# efibootmgr -v\n{}",
+                String::from_utf8_lossy(&output.stdout)
+            );
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.status.success() || !stderr.trim().is_empty() {
+                writeln!(text, "# {} ({})", stderr.trim(), output.status)?;
+            }
+            (
+                "## Capture (read now; none saved, see debug_capture)".to_string(),
+                text,
+            )
+        }
+    };
+    writeln!(out, "\n{}\n{}", heading, capture.trim_end())?;
+
+    let status = efi::parse_status(&capture);
+    let text = |id: Option<&String>| id.cloned().unwrap_or_else(|| "-".to_string());
+    writeln!(out, "\n## Parsed")?;
+    writeln!(out, "BootCurrent: {}", text(status.current.as_ref()))?;
+    writeln!(out, "BootNext: {}", text(status.next.as_ref()))?;
+    writeln!(out, "BootOrder: {}", status.order.join(","))?;
+    if !status.invalid_order.is_empty() {
+        writeln!(
+            out,
+            "invalid BootOrder items: {}",
+            status.invalid_order.join(",")
+        )?;
+    }
+    if let Some(timeout) = status.timeout {
+        writeln!(out, "Timeout: {}", timeout)?;
+    }
+    for entry in efi::parse_entries(&capture, "Boot") {
+        writeln!(
+            out,
+            "Boot{} active={} name={:?} device={:?}\n  path={:?}",
+            entry.id,
+            entry.active,
+            entry.name,
+            entry.device_kind().describe(),
+            entry.device_path
+        )?;
+    }
+
+    // The config holds no passwords; reboot_cmd is left out as the one
+    // setting that is a command line, which could carry one.
+    let mut config = toml::Value::try_from(config)?;
+    if let Some(table) = config.as_table_mut() {
+        table.remove("reboot_cmd");
+    }
+    writeln!(
+        out,
+        "\n## Config\n{}",
+        toml::to_string_pretty(&config)?.trim_end()
+    )?;
+
+    print!("{}", if redact { scrub(&out) } else { out });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_keep_the_shape() {
+        assert_eq!(placeholder("8f7a-1b2c", 3), "0000-0003");
+        assert_eq!(placeholder("192.168.1.20", 12), "000.000.0.12");
+        assert_eq!(placeholder("fe80::1", 2), "0000::2");
+    }
+
+    #[test]
+    fn disk_identifiers_are_replaced() {
+        let gpt = "HD(1,GPT,8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b,0x800,0x100000)/File(\\EFI\\fedora\\shimx64.efi)";
+        assert_eq!(
+            scrub(gpt),
+            "HD(1,GPT,00000000-0000-0000-0000-000000000001,0x800,0x100000)/File(\\EFI\\fedora\\shimx64.efi)"
+        );
+        assert_eq!(
+            scrub("HD(1,MBR,0x6e2a91c4,0x800,0x100000)"),
+            "HD(1,MBR,0x00000001,0x800,0x100000)"
+        );
+        assert_eq!(
+            scrub("PciRoot(0x0)/Pci(0x1d,0x0)/NVMe(0x1,00-25-38-5B-91-B0-2C-4D)"),
+            "PciRoot(0x0)/Pci(0x1d,0x0)/NVMe(0x1,00-00-00-00-00-00-00-01)"
+        );
+        assert_eq!(
+            scrub("UsbWwid(781,5583,0,4C530001230512117354)"),
+            "UsbWwid(781,5583,0,00000000000000000001)"
+        );
+    }
+
+    #[test]
+    fn network_identifiers_are_replaced() {
+        assert_eq!(
+            scrub("MAC(c8d9d2aabbcc,0)/IPv4(192.168.1.20:0<->10.0.0.1:0,0,0)"),
+            "MAC(000000000001,0)/IPv4(000.000.0.02:0<->00.0.0.3:0,0,0)"
+        );
+        assert_eq!(
+            scrub("MAC(c8d9d2aabbcc,0)/IPv6([fe80::1a2b:3c4d]:0<->[2001:db8::7]:69,0,0)"),
+            "MAC(000000000001,0)/IPv6([0000::0000:0002]:0<->[0000:000::3]:69,0,0)"
+        );
+        // The UEFI spec's own text form, without ports.
+        assert_eq!(
+            scrub("IPv4(10.0.0.1,TCP,Static,192.168.1.20)"),
+            "IPv4(00.0.0.1,TCP,Static,000.000.0.02)"
+        );
+    }
+
+    #[test]
+    fn unspecified_addresses_are_kept() {
+        let dhcp = "MAC(000000000000,0)/IPv4(0.0.0.0:0<->0.0.0.0:0,0,0)";
+        assert_eq!(scrub(dhcp), dhcp);
+        let dhcp6 = "MAC(000000000000,0)/IPv6([::]:0<->[::]:0,0,0)";
+        assert_eq!(scrub(dhcp6), dhcp6);
+    }
+
+    #[test]
+    fn the_same_value_gets_the_same_placeholder() {
+        let text = "Boot0001* Fedora\tHD(1,GPT,8F7A2C1E-5B3D-4E6F-9A0B-1C2D3E4F5A6B,0x800,0x1)\n\
+            Boot0002* Windows\tHD(2,GPT,1b2c3d4e-0000-4000-8000-aabbccddeeff,0x1,0x1)\n\
+            Boot0003* Linux\tHD(1,GPT,8f7a2c1e-5b3d-4e6f-9a0b-1c2d3e4f5a6b,0x800,0x1)\n\
+            Boot0004* PXE\tMAC(c8d9d2aabbcc,0)/IPv4(192.168.1.20:0<->192.168.1.1:0,0,0)\n\
+            Boot0005* PXE\tMAC(C8D9D2AABBCC,0)/IPv6([fe80::1]:0<->[FE80::1]:0,0,0)\n";
+        let scrubbed = scrub(text);
+        let lines: Vec<&str> = scrubbed.lines().collect();
+        assert!(lines[0].contains("GPT,00000000-0000-0000-0000-000000000001,"));
+        assert!(lines[1].contains("GPT,00000000-0000-0000-0000-000000000002,"));
+        assert!(lines[2].contains("GPT,00000000-0000-0000-0000-000000000001,"));
+        assert!(lines[3].contains("MAC(000000000003,0)"));
+        assert!(lines[3].contains("IPv4(000.000.0.04:0<->000.000.0.5:0,"));
+        assert!(lines[4].contains("MAC(000000000003,0)"));
+        assert!(lines[4].contains("IPv6([0000::6]:0<->[0000::6]:0,"));
+        // Names and the rest of the paths are left as they are.
+        assert!(lines[0].starts_with("Boot0001* Fedora\tHD(1,GPT,"));
+    }
+
+    #[test]
+    fn nothing_else_is_touched() {
+        let text = "kernel: 6.8.0-45-generic\nfirmware: 1.2.3.4\nBootOrder: 0001,0002\n";
+        assert_eq!(scrub(text), text);
+    }
+}
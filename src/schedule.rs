@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// When a profile is worth suggesting, e.g. "Sat,Sun 18:00-23:59" or
+// "Mon-Fri 06:00-09:30". The days are optional (every day without them).
+// A window whose end is before its start runs past midnight: "Fri 22:00-02:00"
+// covers Friday night until two on Saturday morning. Both ends are included.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Schedule {
+    text: String,
+    // Indexed like `tm_wday`: Sunday is 0.
+    days: [bool; 7],
+    // Minutes since midnight.
+    start: u16,
+    end: u16,
+}
+
+impl TryFrom<String> for Schedule {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Schedule, String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let (days, times) = match words.as_slice() {
+            [times] => ([true; 7], *times),
+            [days, times] => (parse_days(days)?, *times),
+            _ => {
+                return Err(format!(
+                    "\"{}\" is not a schedule; expected e.g. \"Sat,Sun 18:00-23:59\"",
+                    text
+                ));
+            }
+        };
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("\"{}\" is not a time range like 18:00-23:59", times))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            return Err(format!("\"{}\" starts and ends at the same time", times));
+        }
+        Ok(Schedule {
+            text,
+            days,
+            start,
+            end,
+        })
+    }
+}
+
+impl From<Schedule> for String {
+    fn from(schedule: Schedule) -> String {
+        schedule.text
+    }
+}
+
+impl Schedule {
+    // Whether the window covers `minute` (since midnight) on `weekday`
+    // (Sunday is 0).
+    pub fn matches(&self, weekday: usize, minute: u16) -> bool {
+        let weekday = weekday % 7;
+        if self.start < self.end {
+            return self.days[weekday] && (self.start..=self.end).contains(&minute);
+        }
+        // Past midnight: the evening part belongs to the day itself, the
+        // morning part to the day before.
+        let yesterday = (weekday + 6) % 7;
+        (self.days[weekday] && minute >= self.start) || (self.days[yesterday] && minute <= self.end)
+    }
+
+    pub fn matches_now(&self) -> bool {
+        let (weekday, minute) = local_now();
+        self.matches(weekday, minute)
+    }
+}
+
+// "Sat,Sun", "Mon-Fri" or a mix like "Mon-Wed,Fri".
+fn parse_days(text: &str) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for part in text.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_day(first)?, parse_day(last)?);
+                let mut day = first;
+                loop {
+                    days[day] = true;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_day(part)?] = true,
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(text: &str) -> Result<usize, String> {
+    let lower = text.to_ascii_lowercase();
+    DAYS.iter()
+        .position(|day| *day == lower)
+        .ok_or_else(|| format!("\"{}\" is not a day; use Mon, Tue, ... Sun", text))
+}
+
+// "18:00" as minutes since midnight: exactly two digits, a colon and two
+// digits.
+fn parse_time(text: &str) -> Result<u16, String> {
+    let invalid = || format!("\"{}\" is not a time like 18:00", text);
+    let digits = |part: &str| {
+        if part.len() == 2 && part.bytes().all(|b| b.is_ascii_digit()) {
+            part.parse::<u16>().ok()
+        } else {
+            None
+        }
+    };
+    let (hours, minutes) = text.split_once(':').ok_or_else(invalid)?;
+    let (Some(hours), Some(minutes)) = (digits(hours), digits(minutes)) else {
+        return Err(invalid());
+    };
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+// The local weekday (Sunday is 0) and minute of the day.
+fn local_now() -> (usize, u16) {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return (0, 0);
+    }
+    (tm.tm_wday as usize, (tm.tm_hour * 60 + tm.tm_min) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN: usize = 0;
+    const MON: usize = 1;
+    const FRI: usize = 5;
+    const SAT: usize = 6;
+
+    fn schedule(text: &str) -> Schedule {
+        Schedule::try_from(text.to_string()).unwrap()
+    }
+
+    fn error(text: &str) -> String {
+        Schedule::try_from(text.to_string())
+            .err()
+            .expect("schedule should not parse")
+    }
+
+    #[test]
+    fn times_are_exactly_hh_mm() {
+        assert_eq!(parse_time("00:00"), Ok(0));
+        assert_eq!(parse_time("09:30"), Ok(570));
+        assert_eq!(parse_time("23:59"), Ok(1439));
+        for bad in [
+            "1:0",
+            "1:00",
+            "01:0",
+            "+1:00",
+            "-1:00",
+            "01:+0",
+            "24:00",
+            "12:60",
+            "12:00 ",
+            "1200",
+            "12:000",
+            "١٢:٠٠",
+            "",
+        ] {
+            assert_eq!(
+                parse_time(bad),
+                Err(format!("\"{}\" is not a time like 18:00", bad)),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn days_take_lists_and_ranges() {
+        let weekend = [true, false, false, false, false, false, true];
+        assert_eq!(parse_days("Sat,Sun"), Ok(weekend));
+        assert_eq!(parse_days("sat-SUN"), Ok(weekend));
+        assert_eq!(
+            parse_days("Mon-Wed,Fri"),
+            Ok([false, true, true, true, false, true, false])
+        );
+        assert!(parse_days("Funday").is_err());
+        assert!(parse_days("Mon,").is_err());
+    }
+
+    #[test]
+    fn invalid_specs_are_rejected() {
+        assert!(error("").starts_with("\"\" is not a schedule"));
+        assert!(
+            error("Sat Sun 18:00-23:59").starts_with("\"Sat Sun 18:00-23:59\" is not a schedule")
+        );
+        assert_eq!(
+            error("Sat 18:00"),
+            "\"18:00\" is not a time range like 18:00-23:59"
+        );
+        assert_eq!(
+            error("18:00-18:00"),
+            "\"18:00-18:00\" starts and ends at the same time"
+        );
+        assert_eq!(error("Sat 8:00-9:00"), "\"8:00\" is not a time like 18:00");
+        assert!(error("Someday 18:00-23:59").contains("is not a day"));
+    }
+
+    #[test]
+    fn a_window_within_a_day() {
+        let evenings = schedule("Sat,Sun 18:00-23:59");
+        assert!(evenings.matches(SAT, 18 * 60));
+        assert!(evenings.matches(SUN, 23 * 60 + 59));
+        assert!(!evenings.matches(SAT, 17 * 60 + 59));
+        assert!(!evenings.matches(FRI, 20 * 60));
+
+        let every_day = schedule("06:00-09:30");
+        assert!((0..7).all(|day| every_day.matches(day, 7 * 60)));
+        assert!(!every_day.matches(MON, 9 * 60 + 31));
+    }
+
+    #[test]
+    fn a_window_across_midnight_belongs_to_the_day_it_starts() {
+        let friday_night = schedule("Fri 22:00-02:00");
+        assert!(friday_night.matches(FRI, 22 * 60));
+        assert!(friday_night.matches(FRI, 23 * 60 + 59));
+        assert!(friday_night.matches(SAT, 0));
+        assert!(friday_night.matches(SAT, 2 * 60));
+        assert!(!friday_night.matches(SAT, 2 * 60 + 1));
+        assert!(!friday_night.matches(SAT, 22 * 60));
+        // Thursday night isn't included, and Friday morning belongs to it.
+        assert!(!friday_night.matches(FRI, 60));
+        assert!(!friday_night.matches(FRI, 21 * 60 + 59));
+
+        // Saturday's window runs into Sunday, wrapping the week.
+        let saturday_night = schedule("Sat 23:00-01:00");
+        assert!(saturday_night.matches(SUN, 30));
+        assert!(!saturday_night.matches(MON, 30));
+    }
+
+    #[test]
+    fn schedules_keep_their_text() {
+        let text = "Mon-Fri 06:00-09:30";
+        assert_eq!(String::from(schedule(text)), text);
+    }
+}
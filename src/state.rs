@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Things remembered between runs. Unlike the config file this is written by
+// swiftboot itself, so a missing or unreadable file just means "start fresh".
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub show_ids: Option<bool>,
+    pub grouped: bool,
+    // Set once the first-run welcome popup has been dismissed.
+    pub welcomed: bool,
+    // BootCurrent as of the last run, and the one before it changed.
+    pub boot_current: Option<String>,
+    pub boot_previous: Option<String>,
+    // The entry last armed as BootNext, for `boot_to_initial = "last-used"`.
+    pub last_boot_once: Option<String>,
+    // Pending safe apply, until `swiftboot confirm` or a revert.
+    pub safe_apply: Option<SafeApply>,
+    // "Don't show again" on the Windows-first warning.
+    pub windows_first_dismissed: bool,
+    // A boot once done by reordering, until the order is put back.
+    pub temporary_order: Option<TemporaryOrder>,
+    // The last BootNext armed, until a later run sees what became of it.
+    pub armed_boot_next: Option<ArmedBootNext>,
+    // A boot order set up to be written at the next shutdown, until a
+    // later run sees what became of it.
+    pub deferred: Option<DeferredOrder>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeferredOrder {
+    pub order: Vec<String>,
+    // Unix time, in seconds.
+    pub deferred_at: u64,
+    // The kernel's boot ID when it was set up; see `boot_id`. The unit
+    // doesn't outlive the boot.
+    pub boot_id: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArmedBootNext {
+    pub target: String,
+    // Unix time, in seconds.
+    pub armed_at: u64,
+    // The kernel's boot ID when it was armed; see `boot_id`.
+    pub boot_id: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BootNextOutcome {
+    // Still armed for the next boot.
+    Pending,
+    // The firmware used it: this boot came from the target.
+    Completed,
+    // Gone or pointing elsewhere without the target having booted, e.g.
+    // removed with `efibootmgr -N` or replaced by another tool.
+    Cleared,
+}
+
+impl ArmedBootNext {
+    // BootNext clears itself once used, so its absence alone doesn't say
+    // whether the boot happened; BootCurrent and the boot ID do. Without
+    // boot IDs, booting from the target counts as the sign.
+    pub fn outcome(
+        &self,
+        boot_next: Option<&str>,
+        current: Option<&str>,
+        boot_id: Option<&str>,
+    ) -> BootNextOutcome {
+        if boot_next == Some(self.target.as_str()) {
+            return BootNextOutcome::Pending;
+        }
+        let rebooted = match (self.boot_id.as_deref(), boot_id) {
+            (Some(then), Some(now)) => then != now,
+            _ => true,
+        };
+        if rebooted && current == Some(self.target.as_str()) {
+            BootNextOutcome::Completed
+        } else {
+            BootNextOutcome::Cleared
+        }
+    }
+}
+
+// `boot_once_method = "reorder-temporarily"`: the target was put first in
+// BootOrder, and `original` is to be restored after booting it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TemporaryOrder {
+    pub original: Vec<String>,
+    pub target: String,
+    // The kernel's boot ID when the order was changed; see `boot_id`.
+    pub boot_id: Option<String>,
+}
+
+impl TemporaryOrder {
+    // Once the machine has rebooted, the target has had its boot. Without
+    // boot IDs, booting from the target is the sign.
+    pub fn restore_due(&self, boot_id: Option<&str>, current: Option<&str>) -> bool {
+        match (self.boot_id.as_deref(), boot_id) {
+            (Some(then), Some(now)) => then != now,
+            _ => current == Some(self.target.as_str()),
+        }
+    }
+}
+
+// Changes on every boot; Linux only.
+pub fn boot_id() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+// The safety net of a boot order applied with `safe_apply = true`: the
+// order to go back to and when to stop waiting for a confirmation.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SafeApply {
+    pub old_order: Vec<String>,
+    pub new_order: Vec<String>,
+    // Unix time, in seconds.
+    pub deadline: u64,
+}
+
+impl SafeApply {
+    pub fn expired(&self, now: u64) -> bool {
+        now >= self.deadline
+    }
+
+    // Whole minutes left, rounded up so "0 minutes" only shows once expired.
+    pub fn minutes_left(&self, now: u64) -> u64 {
+        self.deadline.saturating_sub(now).div_ceil(60)
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// `secs` (Unix time) as local weekday and time, e.g. "Fri 22:10".
+pub fn local_time(secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    let time = secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return "an unknown time".to_string();
+    }
+    format!(
+        "{} {:02}:{:02}",
+        DAYS[tm.tm_wday as usize % 7],
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+pub fn state_path() -> Option<PathBuf> {
+    // Tests never read or write the real state file.
+    if cfg!(test) {
+        return None;
+    }
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("state"))
+        })?;
+    Some(base.join("swiftboot").join("state.toml"))
+}
+
+impl State {
+    pub fn load() -> State {
+        state_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    // Records the entry this boot came from and returns the one the boot
+    // before it came from, if that was a different entry.
+    pub fn record_boot(&mut self, current: Option<&str>) -> Option<String> {
+        if let Some(current) = current
+            && self.boot_current.as_deref() != Some(current)
+        {
+            self.boot_previous = self.boot_current.replace(current.to_string());
+            let _ = self.save();
+        }
+        self.boot_previous
+            .clone()
+            .filter(|previous| Some(previous.as_str()) != current)
+    }
+
+    // `swiftboot confirm`: the new order booted fine, drop the safety net.
+    pub fn confirm_safe_apply() -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = State::load();
+        match state.safe_apply.take() {
+            Some(_) => {
+                state.save()?;
+                println!("Boot order confirmed; the previous order won't be restored.");
+            }
+            None => println!("No safe apply is pending."),
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = state_path() else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Written to a temporary file and renamed so an interrupted save
+        // never leaves a truncated state file behind.
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn safety(deadline: u64) -> SafeApply {
+        SafeApply {
+            old_order: vec!["0001".to_string(), "0002".to_string()],
+            new_order: vec!["0002".to_string(), "0001".to_string()],
+            deadline,
+        }
+    }
+
+    #[test]
+    fn safe_apply_expires_at_the_deadline() {
+        let safety = safety(1000);
+        assert!(!safety.expired(999));
+        assert!(safety.expired(1000));
+        assert!(safety.expired(5000));
+    }
+
+    #[test]
+    fn minutes_left_round_up() {
+        let safety = safety(1000);
+        assert_eq!(safety.minutes_left(100), 15);
+        assert_eq!(safety.minutes_left(941), 1);
+        assert_eq!(safety.minutes_left(999), 1);
+        assert_eq!(safety.minutes_left(1000), 0);
+        assert_eq!(safety.minutes_left(2000), 0);
+    }
+
+    #[test]
+    fn the_safety_net_survives_the_state_file() {
+        let state = State {
+            safe_apply: Some(safety(1234)),
+            ..State::default()
+        };
+        let text = toml::to_string(&state).unwrap();
+        let loaded: State = toml::from_str(&text).unwrap();
+        let safety = loaded.safe_apply.unwrap();
+        assert_eq!(safety.old_order, ["0001", "0002"]);
+        assert_eq!(safety.new_order, ["0002", "0001"]);
+        assert_eq!(safety.deadline, 1234);
+        // Older state files without it still load.
+        let loaded: State = toml::from_str("welcomed = true").unwrap();
+        assert!(loaded.safe_apply.is_none());
+    }
+
+    fn armed(boot_id: Option<&str>) -> ArmedBootNext {
+        ArmedBootNext {
+            target: "0002".to_string(),
+            armed_at: 0,
+            boot_id: boot_id.map(String::from),
+        }
+    }
+
+    #[test]
+    fn an_armed_boot_next_is_pending_until_used() {
+        let armed = armed(Some("boot-a"));
+        let outcome = armed.outcome(Some("0002"), Some("0001"), Some("boot-a"));
+        assert!(outcome == BootNextOutcome::Pending);
+        // Still set even across a reboot into something else: the firmware
+        // didn't use it yet.
+        let outcome = armed.outcome(Some("0002"), Some("0001"), Some("boot-b"));
+        assert!(outcome == BootNextOutcome::Pending);
+    }
+
+    #[test]
+    fn an_armed_boot_next_completes_by_booting_the_target() {
+        let outcome = armed(Some("boot-a")).outcome(None, Some("0002"), Some("boot-b"));
+        assert!(outcome == BootNextOutcome::Completed);
+        // Without boot IDs, BootCurrent alone says so.
+        let outcome = armed(None).outcome(None, Some("0002"), None);
+        assert!(outcome == BootNextOutcome::Completed);
+    }
+
+    #[test]
+    fn an_armed_boot_next_can_be_cleared_externally() {
+        let armed = armed(Some("boot-a"));
+        // Gone within the same boot, even while running the target.
+        let outcome = armed.outcome(None, Some("0002"), Some("boot-a"));
+        assert!(outcome == BootNextOutcome::Cleared);
+        // Rebooted into something else.
+        let outcome = armed.outcome(None, Some("0001"), Some("boot-b"));
+        assert!(outcome == BootNextOutcome::Cleared);
+        // Replaced by another entry.
+        let outcome = armed.outcome(Some("0003"), Some("0001"), Some("boot-a"));
+        assert!(outcome == BootNextOutcome::Cleared);
+    }
+}
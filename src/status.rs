@@ -0,0 +1,199 @@
+use crate::cli::StatusFormat;
+use crate::config::Config;
+use serde::Serialize;
+use swiftboot::efi;
+
+// `swiftboot status`: a one-shot summary for scripts and status bars. Reads
+// everything from a single efibootmgr run and never touches the terminal.
+#[derive(Serialize)]
+struct Status {
+    current: Option<String>,
+    default: Option<String>,
+    next: Option<String>,
+    timeout: Option<u16>,
+    secure_boot: Option<bool>,
+    firmware: efi::FirmwareInfo,
+}
+
+pub fn run(config: &Config, format: StatusFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let overview = efi::configure(&config.efibootmgr(), &config.efibootmgr_extra_args)
+        .and_then(|()| efi::fetch_overview());
+    // On failure nothing goes to stdout, whatever the format, so a script
+    // can't mistake a partial answer for one.
+    let (entries, status) = overview?;
+
+    let name = |id: &String| {
+        config
+            .alias_for(id)
+            .map(str::to_string)
+            .or_else(|| entries.iter().find(|e| &e.id == id).map(|e| e.name.clone()))
+            .unwrap_or_else(|| format!("Boot{}", id))
+    };
+    let summary = Status {
+        current: status.current.as_ref().map(name),
+        default: status.order.first().map(name),
+        next: status.next.as_ref().map(name),
+        timeout: status.timeout,
+        secure_boot: efi::secure_boot(),
+        firmware: efi::firmware_info(),
+    };
+
+    match format {
+        StatusFormat::Json => println!("{}", serde_json::to_string(&summary)?),
+        StatusFormat::Plain => println!("{}", plain_line(&summary)),
+        StatusFormat::Sh => print!("{}", sh_lines(&summary, &status)),
+    }
+    Ok(())
+}
+
+// One `SWIFTBOOT_...=value` line per field, IDs and names apart, for
+// `eval "$(swiftboot status --format sh)"`. Every value is quoted, and
+// unset ones are empty.
+fn sh_lines(summary: &Status, status: &efi::BootStatus) -> String {
+    let on_off = summary.secure_boot.map(|on| if on { "on" } else { "off" });
+    let timeout = summary.timeout.map(|t| t.to_string());
+    let order = status.order.join(" ");
+    let fields = [
+        ("CURRENT", status.current.as_deref()),
+        ("CURRENT_NAME", summary.current.as_deref()),
+        ("DEFAULT", status.order.first().map(String::as_str)),
+        ("DEFAULT_NAME", summary.default.as_deref()),
+        ("NEXT", status.next.as_deref()),
+        ("NEXT_NAME", summary.next.as_deref()),
+        ("ORDER", Some(order.as_str())),
+        ("TIMEOUT", timeout.as_deref()),
+        ("SECURE_BOOT", on_off),
+        ("FIRMWARE_VENDOR", summary.firmware.vendor.as_deref()),
+        ("FIRMWARE_VERSION", summary.firmware.version.as_deref()),
+        ("FIRMWARE_PRODUCT", summary.firmware.product.as_deref()),
+    ];
+    fields
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "SWIFTBOOT_{}={}\n",
+                name,
+                shell_quote(value.unwrap_or_default())
+            )
+        })
+        .collect()
+}
+
+// `value` as a single-quoted shell word. Nothing is special inside single
+// quotes, newlines and `$` included, so the only character to deal with is
+// the quote itself, which becomes '\''. A NUL can't be passed to a shell
+// at all and is dropped.
+pub fn shell_quote(value: &str) -> String {
+    let mut quoted = String::from("'");
+    for c in value.chars() {
+        match c {
+            '\'' => quoted.push_str("'\\''"),
+            '\0' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+// e.g. "current=Fedora default=Windows next=- timeout=1s secure_boot=on"
+fn plain_line(status: &Status) -> String {
+    let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    let timeout = status
+        .timeout
+        .map_or("-".to_string(), |t| format!("{}s", t));
+    let secure_boot = match status.secure_boot {
+        Some(true) => "on",
+        Some(false) => "off",
+        None => "-",
+    };
+    format!(
+        "current={} default={} next={} timeout={} secure_boot={}",
+        text(&status.current),
+        text(&status.default),
+        text(&status.next),
+        timeout,
+        secure_boot
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn values_are_single_quoted() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("Fedora"), "'Fedora'");
+        assert_eq!(
+            shell_quote("Windows Boot Manager"),
+            "'Windows Boot Manager'"
+        );
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("`reboot`"), "'`reboot`'");
+        assert_eq!(shell_quote("a\nb"), "'a\nb'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("''"), "''\\'''\\'''");
+        // A NUL can't be passed on, so it's dropped.
+        assert_eq!(shell_quote("a\0b"), "'ab'");
+    }
+
+    // What `sh` makes of `value` quoted.
+    fn through_sh(value: &str) -> String {
+        let script = format!("printf %s {}", shell_quote(value));
+        let output = Command::new("sh").args(["-c", &script]).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn hostile_names_come_back_unchanged() {
+        for name in [
+            "",
+            " leading and trailing ",
+            "it's",
+            "'; reboot; '",
+            "$(reboot) ${HOME} `reboot`",
+            "line one\nline two\n",
+            "back\\slash \\'",
+            "*?[a] ~ # & | ; < >",
+        ] {
+            assert_eq!(through_sh(name), name);
+        }
+    }
+
+    #[test]
+    fn sh_output_has_a_line_per_variable() {
+        let summary = Status {
+            current: Some("Fedora".to_string()),
+            default: Some("it's $(reboot)".to_string()),
+            next: None,
+            timeout: Some(3),
+            secure_boot: Some(true),
+            firmware: efi::FirmwareInfo::default(),
+        };
+        let status = efi::BootStatus {
+            current: Some("0001".to_string()),
+            order: vec!["0002".to_string(), "0001".to_string()],
+            invalid_order: Vec::new(),
+            next: None,
+            timeout: Some(3),
+        };
+        assert_eq!(
+            sh_lines(&summary, &status),
+            "SWIFTBOOT_CURRENT='0001'\n\
+             SWIFTBOOT_CURRENT_NAME='Fedora'\n\
+             SWIFTBOOT_DEFAULT='0002'\n\
+             SWIFTBOOT_DEFAULT_NAME='it'\\''s $(reboot)'\n\
+             SWIFTBOOT_NEXT=''\n\
+             SWIFTBOOT_NEXT_NAME=''\n\
+             SWIFTBOOT_ORDER='0002 0001'\n\
+             SWIFTBOOT_TIMEOUT='3'\n\
+             SWIFTBOOT_SECURE_BOOT='on'\n\
+             SWIFTBOOT_FIRMWARE_VENDOR=''\n\
+             SWIFTBOOT_FIRMWARE_VERSION=''\n\
+             SWIFTBOOT_FIRMWARE_PRODUCT=''\n"
+        );
+    }
+}
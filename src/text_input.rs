@@ -0,0 +1,317 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+};
+use std::cell::Cell;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// A one-line text field: the text, where the cursor is in it, and how far
+// it is scrolled when it doesn't fit. Every field edits the same way:
+// Left/Right, Home/End and Ctrl+A/Ctrl+E move, Backspace deletes before the
+// cursor, Delete after it, and typing inserts at the cursor. All of these
+// work on graphemes, so "é" typed as "e" plus a combining accent, or a flag
+// emoji, is one step just as it is masked as one `*`.
+#[derive(Default)]
+pub struct TextInput {
+    text: String,
+    // A byte offset into `text`, always between graphemes.
+    cursor: usize,
+    // Columns scrolled off the left. Updated when drawn, as it depends on
+    // the field's width.
+    scroll: Cell<usize>,
+}
+
+impl From<String> for TextInput {
+    // The cursor starts at the end.
+    fn from(text: String) -> TextInput {
+        TextInput {
+            cursor: text.len(),
+            text,
+            scroll: Cell::new(0),
+        }
+    }
+}
+
+impl TextInput {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.scroll.set(0);
+    }
+
+    // Applies an editing key. False for keys that aren't one, which are
+    // left to the dialog (Enter, Esc, Tab...).
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Left => self.cursor = self.previous_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::End => self.cursor = self.text.len(),
+            KeyCode::Char('e') if ctrl => self.cursor = self.text.len(),
+            KeyCode::Backspace => {
+                let start = self.previous_boundary();
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.text.insert(self.cursor, c);
+                // A combining mark typed before another one joins the
+                // grapheme after it; the cursor moves past the whole of it.
+                let after = self.cursor + c.len_utf8();
+                let next = self.boundaries().find(|&i| i >= after);
+                self.cursor = next.unwrap_or(self.text.len());
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // The part of the field that fits in `width` columns, with the cursor
+    // shown as a reversed cell. Scrolls just enough to keep the cursor in
+    // sight. `masked` shows each grapheme as a `*`, for passwords.
+    pub fn line(&self, width: usize, masked: bool) -> Line<'static> {
+        let width = width.max(1);
+        // Each grapheme as shown, with its byte offset and first column.
+        let mut cells = Vec::new();
+        let mut column = 0;
+        for (i, g) in self.text.grapheme_indices(true) {
+            let shown = if masked { "*" } else { g };
+            cells.push((i, column, shown));
+            column += shown.width();
+        }
+        let total = column;
+        let cursor_column = cells
+            .iter()
+            .find(|(i, _, _)| *i == self.cursor)
+            .map_or(total, |(_, column, _)| *column);
+        let cursor_width = cells
+            .iter()
+            .find(|(i, _, _)| *i == self.cursor)
+            .map_or(1, |(_, _, shown)| shown.width().max(1));
+
+        // The cursor after the end takes a column of its own.
+        let mut scroll = self.scroll.get().min((total + 1).saturating_sub(width));
+        if cursor_column < scroll {
+            scroll = cursor_column;
+        } else if cursor_column + cursor_width > scroll + width {
+            scroll = cursor_column + cursor_width - width;
+        }
+        self.scroll.set(scroll);
+
+        let mut spans = Vec::new();
+        for (i, column, shown) in cells {
+            if column < scroll || column + shown.width() > scroll + width {
+                continue;
+            }
+            let span = Span::raw(shown.to_string());
+            spans.push(if i == self.cursor {
+                span.reversed()
+            } else {
+                span
+            });
+        }
+        if self.cursor == self.text.len() {
+            spans.push(Span::styled(" ", Style::default().reversed()));
+        }
+        Line::from(spans)
+    }
+
+    fn boundaries(&self) -> impl Iterator<Item = usize> + '_ {
+        self.text.grapheme_indices(true).map(|(i, _)| i)
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.boundaries()
+            .take_while(|&i| i < self.cursor)
+            .last()
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.boundaries()
+            .find(|&i| i > self.cursor)
+            .unwrap_or(self.text.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> TextInput {
+        let mut input = TextInput::default();
+        for c in text.chars() {
+            input.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        input
+    }
+
+    fn press(input: &mut TextInput, code: KeyCode) {
+        input.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn shown(input: &TextInput, width: usize, masked: bool) -> String {
+        input
+            .line(width, masked)
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn the_mask_has_a_star_per_grapheme() {
+        // ü as one code point and as u plus a combining diaeresis, an emoji
+        // and a flag made of two regional indicators.
+        for (password, graphemes) in [
+            ("pass", 4),
+            ("grün", 4),
+            ("gru\u{308}n", 4),
+            ("p🔑ss", 4),
+            ("🇩🇪x", 2),
+            ("", 0),
+        ] {
+            let input = typed(password);
+            assert_eq!(input.text(), password);
+            // The trailing cell is the cursor.
+            assert_eq!(
+                shown(&input, 40, true),
+                format!("{} ", "*".repeat(graphemes))
+            );
+        }
+    }
+
+    #[test]
+    fn backspace_removes_one_grapheme() {
+        let mut input = typed("ab\u{e9}e\u{301}🇩🇪");
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "ab\u{e9}e\u{301}");
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "ab\u{e9}");
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "ab");
+        press(&mut input, KeyCode::Home);
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "ab");
+    }
+
+    #[test]
+    fn editing_in_the_middle_keeps_graphemes_whole() {
+        let mut input = typed("x🔑y");
+        press(&mut input, KeyCode::Left);
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "xy");
+        press(&mut input, KeyCode::Home);
+        press(&mut input, KeyCode::Delete);
+        assert_eq!(input.text(), "y");
+        input.handle_key(KeyEvent::new(KeyCode::Char('ö'), KeyModifiers::NONE));
+        assert_eq!(input.text(), "öy");
+    }
+
+    #[test]
+    fn text_is_kept_as_typed() {
+        // No normalization: the decomposed form stays decomposed.
+        let input = typed("e\u{301}");
+        assert_eq!(input.text().as_bytes(), b"e\xcc\x81");
+        assert_eq!(typed(" pw ").text(), " pw ");
+    }
+
+    #[test]
+    fn a_long_password_scrolls_to_the_cursor() {
+        let input = typed("🔑🔑🔑🔑🔑🔑");
+        assert_eq!(shown(&input, 4, true), "*** ");
+        assert_eq!(shown(&input, 4, false), "🔑 ");
+    }
+
+    fn ctrl(input: &mut TextInput, c: char) -> bool {
+        input.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+    }
+
+    // The text of the reversed span: where the cursor is drawn.
+    fn cursor_cell(input: &TextInput, width: usize) -> String {
+        let line = input.line(width, false);
+        let cursor = line
+            .spans
+            .iter()
+            .find(|s| {
+                s.style
+                    .add_modifier
+                    .contains(ratatui::style::Modifier::REVERSED)
+            })
+            .expect("a cursor");
+        cursor.content.to_string()
+    }
+
+    #[test]
+    fn the_cursor_moves_and_typing_inserts_at_it() {
+        let mut input = typed("ac");
+        press(&mut input, KeyCode::Left);
+        assert_eq!(cursor_cell(&input, 10), "c");
+        input.handle_key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
+        assert_eq!(input.text(), "abc");
+
+        assert!(ctrl(&mut input, 'a'));
+        assert_eq!(cursor_cell(&input, 10), "a");
+        press(&mut input, KeyCode::Right);
+        assert_eq!(cursor_cell(&input, 10), "b");
+        assert!(ctrl(&mut input, 'e'));
+        assert_eq!(cursor_cell(&input, 10), " ");
+        press(&mut input, KeyCode::Home);
+        press(&mut input, KeyCode::End);
+        assert_eq!(cursor_cell(&input, 10), " ");
+
+        // Nothing to delete past the end, nor to move to.
+        press(&mut input, KeyCode::Delete);
+        press(&mut input, KeyCode::Right);
+        assert_eq!(input.text(), "abc");
+    }
+
+    #[test]
+    fn other_keys_are_left_to_the_dialog() {
+        let mut input = typed("pw");
+        for code in [KeyCode::Enter, KeyCode::Esc, KeyCode::Tab, KeyCode::F(1)] {
+            assert!(!input.handle_key(KeyEvent::new(code, KeyModifiers::NONE)));
+        }
+        // Control characters aren't typed into the field.
+        assert!(!ctrl(&mut input, 'u'));
+        assert_eq!(input.text(), "pw");
+    }
+
+    #[test]
+    fn text_handed_in_starts_with_the_cursor_at_the_end() {
+        let mut input = TextInput::from("boot".to_string());
+        assert_eq!(cursor_cell(&input, 10), " ");
+        press(&mut input, KeyCode::Backspace);
+        assert_eq!(input.text(), "boo");
+        input.clear();
+        assert!(input.is_empty());
+        assert_eq!(shown(&input, 10, false), " ");
+    }
+
+    #[test]
+    fn the_field_scrolls_back_to_a_cursor_moved_left() {
+        let mut input = typed("abcdefgh");
+        assert_eq!(shown(&input, 4, false), "fgh ");
+        press(&mut input, KeyCode::Home);
+        assert_eq!(shown(&input, 4, false), "abcd");
+        // Moving right inside the view doesn't scroll it.
+        press(&mut input, KeyCode::Right);
+        assert_eq!(shown(&input, 4, false), "abcd");
+    }
+}
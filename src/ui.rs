@@ -0,0 +1,2712 @@
+use crate::app::{
+    Action, ActionSummary, App, DeviceState, Focus, InactiveChoice, Markers, REBOOT_TIMEOUT,
+    UIState,
+};
+use crate::config::RebootChoice;
+use crate::keymap::{KeyAction, Keymap};
+use crate::modal::Modal;
+use crate::partition::PartitionStatus;
+use crate::state::{self, TemporaryOrder};
+use ratatui::prelude::Stylize;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Wrap},
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use swiftboot::efi::{self, BootEntry};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// Below this many rows the main view switches to the compact layout; see
+// `draw_compact_ui`.
+const COMPACT_HEIGHT: u16 = 16;
+
+pub fn draw(f: &mut Frame, app: &mut App) {
+    let area = screen_area(f, false);
+    draw_in(f, area, app);
+}
+
+// For the inline viewport of `--no-alt-screen`, which is already only as
+// tall as the UI needs.
+pub fn draw_inline(f: &mut Frame, app: &mut App) {
+    let area = screen_area(f, true);
+    draw_in(f, area, app);
+}
+
+fn screen_area(f: &Frame, inline: bool) -> Rect {
+    let centered = centered_area(f.area(), 65, 60);
+    // Short terminals need every row.
+    if inline || centered.height < COMPACT_HEIGHT {
+        f.area()
+    } else {
+        centered
+    }
+}
+
+// Shown while the boot entries are read at startup.
+pub fn draw_loading(f: &mut Frame, inline: bool, elapsed: Duration) {
+    let area = screen_area(f, inline);
+    let status = busy_status("Loading boot entries...", elapsed);
+    draw_busy_popup(f, area, status, None, "Esc: Quit");
+}
+
+// Startup failed; the message is printed again after the terminal is
+// restored.
+pub fn draw_fatal_error(f: &mut Frame, inline: bool, message: &str) {
+    let area = screen_area(f, inline);
+    let popup_width = area.width * 2 / 3;
+    let popup_height = 8.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Error ")
+            .style(Style::default().fg(Color::Red)),
+        popup,
+    );
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(2),
+            Constraint::Length(1),
+        ])
+        .split(popup);
+
+    f.render_widget(
+        Paragraph::new("Couldn't read the boot entries:")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Red).bold()),
+        layout[0],
+    );
+    f.render_widget(
+        Paragraph::new(message.to_string())
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::White))
+            .wrap(Wrap { trim: true }),
+        layout[1],
+    );
+    f.render_widget(
+        Paragraph::new("Press any key to exit")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Gray)),
+        layout[2],
+    );
+}
+
+fn draw_in(f: &mut Frame, area: Rect, app: &mut App) {
+    // Popups sit over a dimmed copy of the main view, so every frame
+    // repaints the whole app area and nothing stale shows around them.
+    if !matches!(app.state, UIState::Main) {
+        draw_main_ui(f, area, app);
+        f.buffer_mut()
+            .set_style(area, Style::default().fg(Color::DarkGray).bg(Color::Reset));
+    }
+
+    match &app.state {
+        UIState::Main => draw_main_ui(f, area, app),
+        UIState::AskPassword => draw_password_popup(f, area, app),
+        UIState::Processing => draw_processing_screen(f, area, app),
+        UIState::PasswordError => draw_password_error_popup(f, area),
+        UIState::ConfirmBootOnce => draw_boot_once_confirm_popup(f, area, app),
+        UIState::ConfirmInactive(id) => draw_inactive_popup(f, area, app, id),
+        UIState::WindowsFirst { windows, linux } => {
+            draw_windows_first_popup(f, area, windows, linux, app.windows_first_dismiss)
+        }
+        UIState::FirmwareUpdate(_) => draw_firmware_update_popup(f, area, app),
+        UIState::ChooseBootOnceMethod => draw_boot_once_method_popup(f, area, app),
+        UIState::RestoreOrder(temporary) => draw_restore_order_popup(f, area, app, temporary),
+        UIState::DeferUnavailable(reason) => draw_defer_unavailable_popup(f, area, app, reason),
+        UIState::Deferred => draw_deferred_popup(f, area, app),
+        UIState::ConfirmReboot(target) => draw_reboot_popup(f, area, app, target),
+        UIState::BootNextArmed(target) => draw_boot_next_armed_popup(f, area, app, target),
+        UIState::CountdownReboot {
+            target,
+            total,
+            paused_at,
+            ..
+        } => {
+            let remaining = app.countdown_remaining(Instant::now()).unwrap_or_default();
+            draw_countdown_screen(f, area, target, remaining, *total, paused_at.is_some())
+        }
+        UIState::QuitConfirm => draw_quit_confirm_popup(f, area, app.quit_yes),
+        UIState::QuitBootNext(name) => {
+            draw_quit_boot_next_popup(f, area, name, app.quit_boot_next_choice)
+        }
+        UIState::ConfirmDelete(id) => draw_confirm_delete_popup(f, area, app, id),
+        UIState::InstanceLocked(pid) => {
+            draw_instance_locked_popup(f, area, *pid, app.read_only_yes)
+        }
+        UIState::Welcome => draw_welcome_popup(f, area, &app.keymap),
+        UIState::ProfilePicker => draw_profile_picker(f, area, app),
+        UIState::Details(entry) => draw_details_popup(f, area, app, entry),
+        UIState::Warnings => draw_warnings_popup(f, area, app),
+        UIState::Help => draw_help_screen(f, area, &app.keymap),
+        UIState::ErrorMessage(msg) => draw_error_message_popup(f, area, msg, app.error_retry),
+        UIState::NvramFull(msg) => draw_nvram_full_popup(f, area, msg, app.error_retry),
+        UIState::OrderModified {
+            requested, actual, ..
+        } => draw_order_modified_popup(f, area, requested, actual),
+    }
+}
+
+// A popup of at most `width` x `height` centered in `area`, with whatever
+// was drawn there cleared.
+fn popup_area(f: &mut Frame, area: Rect, width: u16, height: u16) -> Rect {
+    let popup = center(area, width, height);
+    f.render_widget(Clear, popup);
+    popup
+}
+
+fn center(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + area.width / 2 - width / 2,
+        area.y + area.height / 2 - height / 2,
+        width,
+        height,
+    )
+}
+
+fn centered_area(area: Rect, width_pct: u16, height_pct: u16) -> Rect {
+    let w = area.width * width_pct / 100;
+    let h = area.height * height_pct / 100;
+    Rect::new(
+        area.x + (area.width - w) / 2,
+        area.y + (area.height - h) / 2,
+        w,
+        h,
+    )
+}
+
+// Cuts `text` down to `max_width` terminal columns, ending in `…` when
+// anything was dropped. Works on grapheme clusters so wide (CJK) characters
+// and combining marks are never split.
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    let mut used = 0;
+    for g in text.graphemes(true) {
+        let w = g.width();
+        if used + w > max_width - 1 {
+            break;
+        }
+        out.push_str(g);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+fn display_names(app: &App, entries: &[&BootEntry], max_width: usize) -> Vec<String> {
+    let named: Vec<(&str, &str)> = entries
+        .iter()
+        .map(|e| (app.display_name(e), e.id.as_str()))
+        .collect();
+    disambiguated_names(&named, max_width)
+}
+
+// Truncates every (name, ID) to `max_width` and, where two entries end up
+// looking identical, appends the entry ID so they can still be told apart.
+// Nothing comes out wider than `max_width`, the ID included.
+fn disambiguated_names(named: &[(&str, &str)], max_width: usize) -> Vec<String> {
+    let names: Vec<String> = named
+        .iter()
+        .map(|(name, _)| truncate_to_width(name, max_width))
+        .collect();
+
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for name in &names {
+        *seen.entry(name.as_str()).or_default() += 1;
+    }
+
+    names
+        .iter()
+        .zip(named)
+        .map(|(name, (full, id))| {
+            if seen[name.as_str()] < 2 || full.width() <= max_width {
+                return name.clone();
+            }
+            let suffix = format!(" [{}]", id);
+            let short = truncate_to_width(full, max_width.saturating_sub(suffix.width()));
+            truncate_to_width(&format!("{short}{suffix}"), max_width)
+        })
+        .collect()
+}
+
+// efibootmgr always writes Boot#### IDs as four uppercase hex digits.
+fn display_id(id: &str) -> String {
+    match u16::from_str_radix(id, 16) {
+        Ok(n) => format!("{:04X}", n),
+        Err(_) => id.to_ascii_uppercase(),
+    }
+}
+
+fn panel_border_style(app: &App, panel: Focus) -> Style {
+    if app.is_focused(panel) {
+        Style::default().fg(Color::White)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    }
+}
+
+fn id_column(app: &App, e: &BootEntry) -> String {
+    if app.show_ids {
+        format!("{} │ ", display_id(&e.id))
+    } else {
+        String::new()
+    }
+}
+
+// The position column, "2. " with `positions` right-aligned to the widest;
+// an entry without a position gets "–" in place of the number. Empty with
+// `show_numbers = false`.
+fn position_column(app: &App, position: Option<usize>, width: usize) -> String {
+    if !app.config.show_numbers {
+        return String::new();
+    }
+    match position {
+        Some(n) => format!("{:>width$}. ", n),
+        None => format!("{:>width$}  ", "–"),
+    }
+}
+
+// Rows for a reorderable panel: position, optional ID, name and how far the
+// entry has moved since the order was last read or applied.
+fn order_items(
+    app: &App,
+    panel: Focus,
+    entries: &[&BootEntry],
+    offsets: &[isize],
+    width: u16,
+) -> Vec<ListItem<'static>> {
+    let id_width = if app.show_ids { 7 } else { 0 };
+    let shift_labels: Vec<String> = offsets
+        .iter()
+        .map(|&shift| match shift {
+            0 => String::new(),
+            n if n > 0 => format!(" ↑{}", n),
+            n => format!(" ↓{}", -n),
+        })
+        .collect();
+    let shift_width = shift_labels.iter().map(|l| l.width()).max().unwrap_or(0);
+
+    let states: Vec<DeviceState> = entries.iter().map(|e| app.device_state(e)).collect();
+    let notes: Vec<String> = entries
+        .iter()
+        .zip(&states)
+        .map(|(e, &state)| entry_note(app, e, state))
+        .collect();
+    let note_width = notes.iter().map(|n| n.width()).max().unwrap_or(0);
+
+    let selected = app.selected_index(panel);
+    // The entry picked up in move mode, shown apart from the rest.
+    let ghost = app
+        .moving
+        .as_ref()
+        .filter(|moving| moving.panel == panel)
+        .map(|moving| moving.id.as_str());
+    let number_width = entries.len().to_string().len();
+    let position_width = position_column(app, Some(entries.len()), number_width).width();
+    let names = display_names(
+        app,
+        entries,
+        (width as usize)
+            .saturating_sub(2 + 5 + position_width + id_width + note_width + shift_width),
+    );
+    entries
+        .iter()
+        .zip(&names)
+        .zip(&shift_labels)
+        .zip(&states)
+        .zip(&notes)
+        .enumerate()
+        .map(|(i, ((((e, name), shift), &state), note))| {
+            let style = if ghost == Some(e.id.as_str()) {
+                Style::default().bg(Color::Yellow).fg(Color::Black).bold()
+            } else if app.is_focused(panel) && Some(i) == selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).bold()
+            } else if !shift.is_empty() {
+                Style::default().fg(Color::Yellow)
+            } else if state == DeviceState::Missing {
+                Style::default().fg(Color::Red).crossed_out()
+            } else if state == DeviceState::Unplugged || !e.active || e.unknown {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            // Driver and SysPrep entries aren't BootCurrent, BootOrder or
+            // BootNext material.
+            let markers = if panel == Focus::Priority {
+                app.markers(e)
+            } else {
+                Markers::default()
+            };
+            let mut line = marker_gutter(markers, app.is_focused(panel) && Some(i) == selected);
+            line.push(Span::raw(format!(
+                " {}{}{}{}{}",
+                position_column(app, Some(i + 1), number_width),
+                id_column(app, e),
+                name,
+                note,
+                shift,
+            )));
+            ListItem::new(Line::from(line)).style(style)
+        })
+        .collect()
+}
+
+// The three-cell marker column in front of every entry, one cell each for
+// BootCurrent (●), the default (→) and BootNext (»), so an entry can carry
+// all three. The selected row keeps the highlight's colors.
+fn marker_gutter(markers: Markers, selected: bool) -> Vec<Span<'static>> {
+    let cells = [
+        (markers.current, "●", Color::Green),
+        (markers.default, "→", Color::Cyan),
+        (markers.next, "»", Color::Yellow),
+    ]
+    .into_iter()
+    .map(|(set, glyph, color)| match (set, selected) {
+        (false, _) => Span::raw(" "),
+        (true, true) => Span::raw(glyph),
+        (true, false) => Span::styled(glyph, Style::default().fg(color).bold()),
+    });
+    std::iter::once(Span::raw(" ")).chain(cells).collect()
+}
+
+// Interleaves the grouped view's section headers with the entry rows.
+// Headers can't be selected; the selection is tracked by entry.
+fn with_headers<'a>(items: Vec<ListItem<'a>>, headers: &[Option<String>]) -> Vec<ListItem<'a>> {
+    let mut rows = Vec::with_capacity(items.len() + headers.len());
+    for (item, header) in items.into_iter().zip(headers) {
+        if let Some(title) = header {
+            rows.push(
+                ListItem::new(format!(" ── {} ──", title))
+                    .style(Style::default().fg(Color::DarkGray).bold()),
+            );
+        }
+        rows.push(item);
+    }
+    rows
+}
+
+// e.g. " (inactive, not in BootOrder)", or empty when nothing is unusual.
+fn entry_note(app: &App, entry: &BootEntry, state: DeviceState) -> String {
+    let mut notes = Vec::new();
+    if !entry.active {
+        notes.push("inactive");
+    }
+    if !app.in_boot_order(entry) {
+        notes.push("not in BootOrder");
+    }
+    // Expected on removable media, where the fallback is the only loader.
+    if entry.uses_fallback_loader() && !entry.is_removable() {
+        notes.push("fallback loader");
+    }
+    match state {
+        DeviceState::Present => {}
+        DeviceState::Unplugged => notes.push("not connected"),
+        DeviceState::Missing => notes.push("missing device"),
+    }
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", notes.join(", "))
+    }
+}
+
+fn draw_main_ui(f: &mut Frame, area: Rect, app: &mut App) {
+    if area.height < COMPACT_HEIGHT {
+        draw_compact_ui(f, area, app);
+        return;
+    }
+    // Title, the Priority and Boot To panels, any advanced-mode panels and
+    // the footer.
+    let panels = app.visible_panels();
+    let mut constraints = vec![Constraint::Percentage(10)];
+    constraints.extend(std::iter::repeat_n(Constraint::Fill(1), panels.len()));
+    constraints.push(Constraint::Percentage(10));
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+
+    // Scroll each panel so its selection stays in view; everything below
+    // only reads from `app`.
+    for (&panel, rect) in panels.iter().zip(&layout[1..]) {
+        app.scroll_to_selection(panel, rect.height.saturating_sub(2) as usize);
+    }
+    let app = &*app;
+
+    // Title
+    let mut title = vec![Span::styled(
+        "SwiftBoot",
+        Style::default().fg(Color::Cyan).bold(),
+    )];
+    if app.is_dirty() {
+        title.push(Span::styled(
+            " [modified]",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if app.read_only.is_some() {
+        title.push(Span::styled(
+            " [read-only]",
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if app.config.expert_mode {
+        title.push(Span::styled(
+            " [expert: no confirmations]",
+            Style::default().fg(Color::Magenta).bold(),
+        ));
+    }
+    let mut header = vec![Line::from(title)];
+    if let Some(firmware) = app.firmware.describe() {
+        header.push(Line::styled(firmware, Style::default().fg(Color::DarkGray)));
+    }
+    if let Some(reason) = app.read_only {
+        header.push(Line::styled(
+            format!("Read-only: {} — changes are disabled", reason),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if let Some(safety) = app.safe_apply() {
+        let now = state::unix_now();
+        let text = if safety.expired(now) {
+            " Safe apply window passed: run `swiftboot revert` to restore the previous order "
+                .to_string()
+        } else {
+            format!(
+                " Safe apply pending: run `swiftboot confirm` within {} min, or the previous order is restored ",
+                safety.minutes_left(now)
+            )
+        };
+        header.push(Line::styled(
+            text,
+            Style::default().fg(Color::Black).bg(Color::Yellow).bold(),
+        ));
+    }
+    if app.deferred().is_some() {
+        header.push(Line::styled(
+            format!(
+                " Deferred change pending: a new boot order at the next shutdown — {} to view or cancel ",
+                app.keymap.label(KeyAction::DeferredChange)
+            ),
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        ));
+    }
+    if let Some((applied, live)) = &app.order_drift {
+        header.push(Line::styled(
+            format!(
+                " BootOrder changed since you applied it (was: {}, now: {}) — {} to dismiss ",
+                applied.join(","),
+                live.join(","),
+                app.keymap.label(KeyAction::DismissBanner)
+            ),
+            Style::default().fg(Color::Black).bg(Color::Red).bold(),
+        ));
+    }
+    if let Some(device) = &app.firmware_update {
+        header.push(Line::styled(
+            format!(
+                " Firmware update pending for {}: it runs first on the next reboot ",
+                device
+            ),
+            Style::default().fg(Color::Black).bg(Color::Yellow).bold(),
+        ));
+    }
+    if app.command_warnings.is_some() {
+        header.push(Line::styled(
+            format!(
+                " efibootmgr reported warnings: press {} for details, {} to dismiss ",
+                app.keymap.label(KeyAction::Warnings),
+                app.keymap.label(KeyAction::DismissBanner)
+            ),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        ));
+    }
+    if let Some(name) = &app.suggested_profile {
+        header.push(Line::styled(
+            format!(
+                " Apply profile '{}'? ({}; {} to dismiss) ",
+                name,
+                app.keymap.label(KeyAction::Profiles),
+                app.keymap.label(KeyAction::DismissBanner)
+            ),
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        ));
+    }
+    // Where reads need root, nothing is known until the password is in.
+    let unread = app.reads_need_root && app.entries.is_empty();
+    if !app.has_boot_order && !unread {
+        header.push(Line::styled(
+            "No BootOrder set — applying will create one",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    f.render_widget(
+        Paragraph::new(header).alignment(Alignment::Center),
+        layout[0],
+    );
+
+    // The panels: Priority, Boot To, then Driver and SysPrep in advanced
+    // mode.
+    for (&panel, &rect) in panels.iter().zip(&layout[1..]) {
+        let (items, title) = panel_items(app, panel, rect.width, 0);
+        f.render_stateful_widget(
+            List::new(items).block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(panel_border_style(app, panel)),
+            ),
+            rect,
+            &mut app.list_state(panel),
+        );
+    }
+
+    f.render_widget(
+        footer(app).alignment(Alignment::Center),
+        layout[layout.len() - 1],
+    );
+}
+
+// For short terminals such as a tmux popup: the focused panel alone on the
+// whole area, without the title rows, and the key hints (or a toast) in its
+// bottom border. Tab switches panels as usual.
+fn draw_compact_ui(f: &mut Frame, area: Rect, app: &mut App) {
+    let panel = app.focus;
+    app.scroll_to_selection(panel, area.height.saturating_sub(2) as usize);
+    let app = &*app;
+
+    let mut flags = Vec::new();
+    if app.is_dirty() {
+        flags.push(Span::styled(
+            "[modified] ",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    if app.read_only.is_some() {
+        flags.push(Span::styled(
+            "[read-only] ",
+            Style::default().fg(Color::Red),
+        ));
+    }
+    if app.deferred().is_some() {
+        flags.push(Span::styled(
+            "[deferred change pending] ",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    let flags_width = flags.iter().map(Span::width).sum();
+    let (items, title) = panel_items(app, panel, area.width, flags_width);
+    let mut title = title.spans;
+    title.extend(flags);
+    let hints = match &app.toast {
+        Some((message, _)) => Line::styled(format!(" {} ", message), Color::Green),
+        None => {
+            let k = |action| app.keymap.label(action);
+            Line::styled(
+                format!(
+                    " {}: Panel  {}: Apply/Boot  {}: Help  {}: Quit ",
+                    k(KeyAction::NextPanel),
+                    k(KeyAction::Confirm),
+                    k(KeyAction::Help),
+                    k(KeyAction::Quit),
+                ),
+                Color::DarkGray,
+            )
+        }
+    };
+    f.render_stateful_widget(
+        List::new(items).block(
+            Block::default()
+                .title(Line::from(title))
+                .title_bottom(hints.centered())
+                .borders(Borders::ALL)
+                .border_style(panel_border_style(app, panel)),
+        ),
+        area,
+        &mut app.list_state(panel),
+    );
+}
+
+// A panel's rows and its title. `reserved` columns of the title are left
+// for whatever the caller adds after it.
+fn panel_items(
+    app: &App,
+    panel: Focus,
+    width: u16,
+    reserved: usize,
+) -> (Vec<ListItem<'static>>, Line<'static>) {
+    match panel {
+        Focus::Priority => {
+            let items = order_items(app, panel, &app.rows(), &app.moved_offsets(), width);
+            let base = if app.pin_rest {
+                " Boot Priority (default order, rest pinned) "
+            } else {
+                " Boot Priority (default order) "
+            };
+            let room = (width as usize).saturating_sub(2 + base.width() + reserved);
+            let mut title = vec![Span::raw(base)];
+            title.extend(prospective_default(app, room));
+            (
+                with_headers(items, &app.group_headers(panel)),
+                Line::from(title),
+            )
+        }
+        Focus::BootOnce => {
+            let title = match app.boot_next_name() {
+                Some(name) => format!(" Boot To (next boot: {}) ", name),
+                None => " Boot To ".to_string(),
+            };
+            (boot_once_items(app, width), Line::from(title))
+        }
+        Focus::Extra(kind) => match app.extra_lists.iter().find(|l| l.kind == kind) {
+            Some(list) => (
+                order_items(
+                    app,
+                    panel,
+                    &app.panel_entries(panel),
+                    &list.moved_offsets(),
+                    width,
+                ),
+                Line::from(format!(" {} Order ", kind.prefix())),
+            ),
+            None => (Vec::new(), Line::default()),
+        },
+    }
+}
+
+// "— default: Windows * " for the Priority title: the entry applying the
+// order makes the default, by its alias if it has one. Marked with `*` and
+// highlighted while it isn't the default the firmware has now. None when
+// there is no room for it.
+fn prospective_default(app: &App, room: usize) -> Option<Span<'static>> {
+    let order = app.order_to_apply();
+    let id = order.first()?;
+    let name = app.all_entries().find(|e| &e.id == id).map_or_else(
+        || format!("Boot{}", id),
+        |e| app.display_name(e).to_string(),
+    );
+    let changed = !app.has_boot_order || app.live_order.first() != Some(id);
+    let mark = if changed { " *" } else { "" };
+    let fixed = "— default: ".width() + mark.width() + 1;
+    if room < fixed + 4 {
+        return None;
+    }
+    let text = format!(
+        "— default: {}{} ",
+        truncate_to_width(&name, room - fixed),
+        mark
+    );
+    Some(if changed {
+        Span::styled(text, Style::default().fg(Color::Yellow).bold())
+    } else {
+        Span::styled(text, Style::default().fg(Color::Gray))
+    })
+}
+
+// Every entry can be booted once, inactive or not; those are dimmed and
+// labelled like in the Priority panel.
+fn boot_once_items(app: &App, width: u16) -> Vec<ListItem<'static>> {
+    let entries = app.panel_entries(Focus::BootOnce);
+    let id_width = if app.show_ids { 7 } else { 0 };
+    let selected = app.selected_index(Focus::BootOnce);
+    let notes: Vec<String> = entries
+        .iter()
+        .map(|e| entry_note(app, e, app.device_state(e)))
+        .collect();
+    let note_width = notes.iter().map(|n| n.width()).max().unwrap_or(0);
+    // Places in BootOrder, so the numbers match the Priority panel once the
+    // order is applied.
+    let positions: Vec<Option<usize>> = entries
+        .iter()
+        .map(|e| {
+            app.boot_order
+                .iter()
+                .position(|id| *id == e.id)
+                .map(|p| p + 1)
+        })
+        .collect();
+    let number_width = positions
+        .iter()
+        .flatten()
+        .max()
+        .map_or(1, |n| n.to_string().len());
+    let position_width = position_column(app, None, number_width).width();
+    let names = display_names(
+        app,
+        &entries,
+        (width as usize).saturating_sub(2 + 5 + position_width + id_width + note_width),
+    );
+    entries
+        .iter()
+        .zip(&names)
+        .zip(&notes)
+        .zip(&positions)
+        .enumerate()
+        .map(|(i, (((e, name), note), &position))| {
+            let is_selected = app.is_focused(Focus::BootOnce) && Some(i) == selected;
+            let style = if is_selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).bold()
+            } else if !e.active || e.unknown || app.device_state(e) != DeviceState::Present {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let mut line = marker_gutter(app.markers(e), is_selected);
+            line.push(Span::raw(format!(
+                " {}{}{}{}",
+                position_column(app, position, number_width),
+                id_column(app, e),
+                name,
+                note
+            )));
+            ListItem::new(Line::from(line)).style(style)
+        })
+        .collect()
+}
+
+// The status line under the panels: a toast while one is showing,
+// otherwise the main key hints.
+fn footer(app: &App) -> Paragraph<'_> {
+    if let Some(moving) = &app.moving {
+        return Paragraph::new(format!(
+            "Moving {}: {}/{} place, Home/End top/bottom, {} drop, Esc cancel",
+            app.entry_name(&moving.id),
+            app.keymap.label(KeyAction::Up),
+            app.keymap.label(KeyAction::Down),
+            app.keymap.label(KeyAction::Confirm),
+        ))
+        .style(Style::default().fg(Color::Yellow));
+    }
+    match &app.toast {
+        Some((message, _)) => {
+            Paragraph::new(message.as_str()).style(Style::default().fg(Color::Green))
+        }
+        None => {
+            let k = |action| app.keymap.label(action);
+            // e.g. "u/d, Alt+↑/Alt+↓": the first two pairs of reorder keys.
+            let reorder = app
+                .keymap
+                .keys_for(KeyAction::MoveUp)
+                .into_iter()
+                .zip(app.keymap.keys_for(KeyAction::MoveDown))
+                .take(2)
+                .map(|(up, down)| format!("{}/{}", up, down))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let previous = match app.previous_boot_name() {
+                Some(name) => format!("Previously: {}  |  ", name),
+                None => String::new(),
+            };
+            let slow = match app.slow_read {
+                Some(took) => format!("efibootmgr took {:.1}s  |  ", took.as_secs_f64()),
+                None => String::new(),
+            };
+            let hidden = match app.hidden_entries().len() {
+                0 => String::new(),
+                n => format!(
+                    "+{} hidden (press {})  |  ",
+                    n,
+                    k(KeyAction::ToggleFirmwareEntries)
+                ),
+            };
+            Paragraph::new(format!(
+                "{}{}{}{}: Switch panel  |  {}{}: Move  |  {}: Reorder  |  {}: Apply/Boot  |  {}: IDs  |  {}: Help  |  {}: Quit",
+                slow,
+                previous,
+                hidden,
+                k(KeyAction::NextPanel),
+                k(KeyAction::Up),
+                k(KeyAction::Down),
+                reorder,
+                k(KeyAction::Confirm),
+                k(KeyAction::ToggleIds),
+                k(KeyAction::Help),
+                k(KeyAction::Quit),
+            ))
+            .style(Style::default().fg(Color::DarkGray))
+        }
+    }
+}
+
+fn draw_password_popup(f: &mut Frame, area: Rect, app: &App) {
+    let prompt = if app.password_empty {
+        Line::from("Password is empty").red()
+    } else {
+        Line::from("Enter sudo password")
+    };
+    let mut modal = Modal::new(" Authentication ").width(75);
+    if app.pending_action == Action::ReadEntries {
+        modal = modal.line(Line::from(format!("{}.", efi::ReadNeedsRoot)).yellow());
+    }
+    modal
+        .line(prompt)
+        .input(&app.password, !app.show_password)
+        .hint("Enter = Confirm  |  Esc = Cancel  |  Tab = Show/Hide")
+        .render(f, area);
+}
+
+fn draw_boot_once_confirm_popup(f: &mut Frame, area: Rect, app: &App) {
+    let name_of = |id: &str| {
+        app.all_entries()
+            .find(|e| e.id == id)
+            .map(|e| format!("{} ({})", app.display_name(e), display_id(id)))
+            .unwrap_or_else(|| display_id(id))
+    };
+    let target = app
+        .pending_action
+        .boot_once_id()
+        .map(name_of)
+        .unwrap_or_default();
+
+    // A combined apply + boot once lists both operations before asking.
+    let (title, lines) = if app.pending_action.sets_order() {
+        let mut lines = Vec::new();
+        for step in app.pending_action.steps() {
+            match step {
+                Action::SetOrder(order) => {
+                    let names: Vec<String> = order.iter().map(|id| name_of(id.as_str())).collect();
+                    lines.push(format!("1. Set boot order: {}", names.join(", ")));
+                }
+                Action::BootOnce(_) => lines.push(format!("2. Boot once into: {}", target)),
+                Action::BootOnceByReorder(_) => lines.push(format!(
+                    "2. Boot once into: {} (first in the boot order until the next run)",
+                    target
+                )),
+                _ => {}
+            }
+        }
+        lines.push("Continue?".to_string());
+        (" Apply & Boot Once ", lines)
+    } else {
+        let by_reorder = app
+            .pending_action
+            .steps()
+            .iter()
+            .any(|step| matches!(step, Action::BootOnceByReorder(_)));
+        let line = if by_reorder {
+            format!(
+                "Boot once into: {} (by putting it first until the next run)?",
+                target
+            )
+        } else {
+            format!("Boot once into: {}?", target)
+        };
+        (" Boot Once ", vec![line])
+    };
+
+    let popup_width = if lines.len() > 1 {
+        area.width * 3 / 4
+    } else {
+        area.width / 2
+    };
+    let popup_height = lines.len() as u16 + 6;
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(Block::default().borders(Borders::ALL).title(title), popup);
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(lines.len() as u16 + 1),
+            Constraint::Length(2),
+        ])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    let width = inner[0].width as usize;
+    let text: Vec<Line> = lines
+        .iter()
+        .map(|l| Line::from(truncate_to_width(l, width)))
+        .collect();
+    f.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::White)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(inner[1]);
+
+    let yes_style = if app.boot_once_yes {
+        Style::default().bg(Color::Green).fg(Color::Black).bold()
+    } else {
+        Style::default().fg(Color::White)
+    };
+
+    let no_style = if !app.boot_once_yes {
+        Style::default().bg(Color::Red).fg(Color::Black).bold()
+    } else {
+        Style::default().fg(Color::White)
+    };
+
+    f.render_widget(
+        Paragraph::new("[ Yes ]")
+            .alignment(Alignment::Center)
+            .style(yes_style),
+        buttons[0],
+    );
+    f.render_widget(
+        Paragraph::new("[ No ]")
+            .alignment(Alignment::Center)
+            .style(no_style),
+        buttons[1],
+    );
+}
+
+fn draw_reboot_popup(f: &mut Frame, area: Rect, app: &App, target: &ActionSummary) {
+    // Without BootTimeout the firmware picks its own delay, which may or may
+    // not show a menu; nothing more can be said.
+    let timeout = match app.timeout {
+        Some(0) => Line::from(format!(
+            "Firmware menu timeout: 0s, so there is no chance to pick another entry at boot. Press t to set {}s first",
+            REBOOT_TIMEOUT
+        ))
+        .yellow(),
+        Some(seconds) => Line::from(format!("Firmware menu timeout: {}s", seconds)).dark_gray(),
+        None => Line::from("Firmware menu timeout: not set (the firmware's default applies)")
+            .dark_gray(),
+    };
+    let mut modal = Modal::new(if target.changed {
+        " Apply Complete "
+    } else {
+        " Reboot "
+    })
+    .width(45)
+    .line(format!("Reboot into {} now?", target.target))
+    .line(Line::from(target.kind()).dark_gray());
+    if !target.changed && app.is_dirty() {
+        modal = modal.line(Line::from("The unapplied order changes are discarded").yellow());
+    }
+    modal
+        .line("")
+        .line(timeout)
+        .button("[ Yes ]", Color::Green)
+        .button("[ No ]", Color::Red)
+        .selected(if app.reboot_yes { 0 } else { 1 })
+        .render(f, area);
+}
+
+fn draw_boot_next_armed_popup(f: &mut Frame, area: Rect, app: &App, target: &ActionSummary) {
+    let popup_width = area.width / 2;
+    let popup_height = 7;
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" BootNext Armed "),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(2)])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    let message = format!("Next boot: {}", target.target);
+    f.render_widget(
+        Paragraph::new(truncate_to_width(&message, inner[0].width as usize))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::White)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(inner[1]);
+
+    let choices = [
+        (RebootChoice::Now, "[ Reboot now ]"),
+        (RebootChoice::Countdown, "[ Reboot in 5s ]"),
+        (RebootChoice::Later, "[ Later ]"),
+    ];
+    for ((choice, label), button) in choices.into_iter().zip(buttons.iter()) {
+        let style = if choice == app.reboot_choice {
+            Style::default().bg(Color::Green).fg(Color::Black).bold()
+        } else {
+            Style::default().fg(Color::White)
+        };
+        f.render_widget(
+            Paragraph::new(label)
+                .alignment(Alignment::Center)
+                .style(style),
+            *button,
+        );
+    }
+}
+
+fn draw_inactive_popup(f: &mut Frame, area: Rect, app: &App, id: &str) {
+    let popup_width = area.width * 3 / 4;
+    let popup_height = 7;
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Inactive Entry ")
+            .border_style(Style::default().fg(Color::Yellow)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(2)])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    let name = app
+        .entries
+        .iter()
+        .find(|e| e.id == id)
+        .map(|e| app.display_name(e).to_string())
+        .unwrap_or_else(|| display_id(id));
+    let message = format!("{} is inactive; the firmware will skip it.", name);
+    f.render_widget(
+        Paragraph::new(truncate_to_width(&message, inner[0].width as usize))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Yellow)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+            Constraint::Ratio(1, 3),
+        ])
+        .split(inner[1]);
+
+    let choices = [
+        (InactiveChoice::Activate, "[ Activate it ]"),
+        (InactiveChoice::Proceed, "[ Proceed ]"),
+        (InactiveChoice::Cancel, "[ Cancel ]"),
+    ];
+    for ((choice, label), button) in choices.into_iter().zip(buttons.iter()) {
+        let style = if choice == app.inactive_choice {
+            Style::default().bg(Color::Green).fg(Color::Black).bold()
+        } else {
+            Style::default().fg(Color::White)
+        };
+        f.render_widget(
+            Paragraph::new(label)
+                .alignment(Alignment::Center)
+                .style(style),
+            *button,
+        );
+    }
+}
+
+fn draw_windows_first_popup(f: &mut Frame, area: Rect, windows: &str, linux: &str, dismiss: bool) {
+    let popup_width = area.width * 3 / 4;
+    let popup_height = 9.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Windows First ")
+            .border_style(Style::default().fg(Color::Yellow)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(4), Constraint::Length(1)])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{} will boot before {}.", windows, linux)).bold(),
+            Line::from(""),
+            Line::from(format!(
+                "Windows updates sometimes rewrite the boot order and can drop {} from it. \
+                 Check the order again after the next big Windows update.",
+                linux
+            )),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+        .split(inner[1]);
+
+    let choices = [(false, "[ Continue ]"), (true, "[ Don't show again ]")];
+    for ((choice, label), button) in choices.into_iter().zip(buttons.iter()) {
+        let style = if choice == dismiss {
+            Style::default().bg(Color::Green).fg(Color::Black).bold()
+        } else {
+            Style::default().fg(Color::White)
+        };
+        f.render_widget(
+            Paragraph::new(label)
+                .alignment(Alignment::Center)
+                .style(style),
+            *button,
+        );
+    }
+}
+
+fn draw_boot_once_method_popup(f: &mut Frame, area: Rect, app: &App) {
+    let target = app
+        .pending_action
+        .boot_once_id()
+        .map(|id| app.entry_name(id))
+        .unwrap_or_default();
+    draw_choice_popup(
+        f,
+        area,
+        " Boot Once ",
+        vec![
+            Line::from(format!("How should {} be booted once?", target)).bold(),
+            Line::from(""),
+            Line::from(
+                "BootNext is the usual way. Some firmware ignores it; putting the entry first \
+                 works there too, and swiftboot offers to restore the order on its next run.",
+            ),
+        ],
+        ["[ BootNext ]", "[ Put it first temporarily ]"],
+        !app.boot_once_reorder,
+    );
+}
+
+fn draw_restore_order_popup(f: &mut Frame, area: Rect, app: &App, temporary: &TemporaryOrder) {
+    draw_choice_popup(
+        f,
+        area,
+        " Restore Boot Order ",
+        vec![
+            Line::from(format!(
+                "{} was put first to boot it once.",
+                app.entry_name(&temporary.target)
+            ))
+            .bold(),
+            Line::from(""),
+            Line::from(format!(
+                "Restore the previous order: {}?",
+                app.order_names(&temporary.original)
+            )),
+        ],
+        ["[ Restore ]", "[ Keep current order ]"],
+        app.restore_yes,
+    );
+}
+
+fn draw_defer_unavailable_popup(f: &mut Frame, area: Rect, app: &App, reason: &str) {
+    draw_choice_popup(
+        f,
+        area,
+        " Apply at Shutdown ",
+        vec![
+            Line::from("The order can't be deferred to the next shutdown:").bold(),
+            Line::from(reason.to_string()),
+            Line::from(""),
+            Line::from("Apply it now instead? It takes effect from the next reboot."),
+        ],
+        ["[ Apply now ]", "[ Cancel ]"],
+        app.apply_now_yes,
+    );
+}
+
+fn draw_deferred_popup(f: &mut Frame, area: Rect, app: &App) {
+    let Some(deferred) = app.deferred() else {
+        return;
+    };
+    draw_choice_popup(
+        f,
+        area,
+        " Deferred Change ",
+        vec![
+            Line::from(format!(
+                "Boot order to write at the next shutdown (set up {}):",
+                state::local_time(deferred.deferred_at)
+            ))
+            .bold(),
+            Line::from(app.order_names(&deferred.order)),
+            Line::from(""),
+            Line::from("Cancelling leaves the current boot order as it is."),
+        ],
+        ["[ Keep ]", "[ Cancel the change ]"],
+        app.keep_deferred,
+    );
+}
+
+// A question with two buttons side by side; `first` highlights the left one.
+fn draw_choice_popup(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    text: Vec<Line>,
+    labels: [&str; 2],
+    first: bool,
+) {
+    let mut modal = Modal::new(title).color(Color::Yellow).width(75);
+    for line in text {
+        modal = modal.line(line.yellow());
+    }
+    modal
+        .button(labels[0], Color::Green)
+        .button(labels[1], Color::Green)
+        .selected(if first { 0 } else { 1 })
+        .render(f, area);
+}
+
+fn draw_firmware_update_popup(f: &mut Frame, area: Rect, app: &App) {
+    let popup_width = area.width * 3 / 4;
+    let popup_height = 9.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Firmware Update Pending ")
+            .border_style(Style::default().fg(Color::Yellow)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(4), Constraint::Length(1)])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    let device = app.firmware_update.as_deref().unwrap_or_default();
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from("A firmware update will run first.").bold(),
+            Line::from(""),
+            Line::from(format!(
+                "fwupd has an update for {} staged for the next reboot. It starts before \
+                 anything in the boot order, and may take a few minutes and reboot again.",
+                device
+            )),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+        .split(inner[1]);
+
+    let choices = [(true, "[ Continue ]"), (false, "[ Cancel ]")];
+    for ((choice, label), button) in choices.into_iter().zip(buttons.iter()) {
+        let style = if choice == app.firmware_update_yes {
+            Style::default().bg(Color::Green).fg(Color::Black).bold()
+        } else {
+            Style::default().fg(Color::White)
+        };
+        f.render_widget(
+            Paragraph::new(label)
+                .alignment(Alignment::Center)
+                .style(style),
+            *button,
+        );
+    }
+}
+
+fn draw_processing_screen(f: &mut Frame, area: Rect, app: &App) {
+    let (elapsed, prompt) = app.job_status().unwrap_or_default();
+    let status = if app.is_cancelling() {
+        format!("{} Cancelling...", spinner(elapsed))
+    } else {
+        busy_status("Processing...", elapsed)
+    };
+    draw_busy_popup(f, area, status, prompt, "Esc: Cancel");
+}
+
+// A spinner and the text, with the elapsed time only once it's clear this
+// is taking a while.
+fn busy_status(text: &str, elapsed: Duration) -> String {
+    if elapsed >= Duration::from_secs(2) {
+        format!("{} {} {}s", spinner(elapsed), text, elapsed.as_secs())
+    } else {
+        format!("{} {}", spinner(elapsed), text)
+    }
+}
+
+fn spinner(elapsed: Duration) -> char {
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()]
+}
+
+fn draw_busy_popup(f: &mut Frame, area: Rect, status: String, prompt: Option<&str>, hint: &str) {
+    let popup_width = area.width / 2;
+    let popup_height = 7;
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(Block::default().borders(Borders::ALL), popup);
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(2),
+            Constraint::Length(1),
+        ])
+        .split(popup);
+
+    f.render_widget(
+        Paragraph::new(status)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Cyan).bold()),
+        layout[0],
+    );
+    if let Some(prompt) = prompt {
+        f.render_widget(
+            Paragraph::new(prompt.to_string())
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(Color::Yellow))
+                .wrap(Wrap { trim: true }),
+            layout[1],
+        );
+    }
+    f.render_widget(
+        Paragraph::new(hint.to_string())
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray)),
+        layout[2],
+    );
+}
+
+fn draw_password_error_popup(f: &mut Frame, area: Rect) {
+    Modal::new(" Authentication Failed ")
+        .color(Color::Red)
+        .line(Line::from("Incorrect password!").red().bold())
+        .line("Please try again.")
+        .hint("Press any key to continue")
+        .render(f, area);
+}
+
+fn draw_order_modified_popup(f: &mut Frame, area: Rect, requested: &[String], actual: &[String]) {
+    let popup_width = area.width * 2 / 3;
+    let popup_height = 9.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Warning ")
+            .style(Style::default().fg(Color::Yellow)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    f.render_widget(
+        Paragraph::new("The firmware modified the boot order")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Yellow).bold()),
+        inner[0],
+    );
+
+    let list = |ids: &[String]| {
+        let ids: Vec<String> = ids.iter().map(|id| display_id(id)).collect();
+        if ids.is_empty() {
+            "(empty)".to_string()
+        } else {
+            ids.join(", ")
+        }
+    };
+    f.render_widget(
+        Paragraph::new(vec![
+            Line::from(""),
+            Line::from(format!("Requested: {}", list(requested))),
+            Line::from(format!("Actual:    {}", list(actual))),
+        ])
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::White))
+        .wrap(Wrap { trim: true }),
+        inner[1],
+    );
+
+    f.render_widget(
+        Paragraph::new("Press any key to continue")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Gray)),
+        inner[2],
+    );
+}
+
+fn draw_countdown_screen(
+    f: &mut Frame,
+    area: Rect,
+    target: &ActionSummary,
+    remaining: Duration,
+    total: Duration,
+    paused: bool,
+) {
+    let popup_width = area.width / 2;
+    let popup_height = 11.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Rebooting ")
+            .style(Style::default().fg(Color::Cyan)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Length(2),
+            Constraint::Length(1),
+        ])
+        .split(popup.inner(Margin::new(1, 1)));
+
+    let width = inner[0].width as usize;
+    f.render_widget(
+        Paragraph::new(truncate_to_width(
+            &format!("Rebooting into: {}", target.target),
+            width,
+        ))
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Yellow).bold()),
+        inner[0],
+    );
+    f.render_widget(
+        Paragraph::new(truncate_to_width(target.kind(), width))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray)),
+        inner[1],
+    );
+
+    let seconds = remaining.as_millis().div_ceil(1000);
+    let message = if paused {
+        format!(
+            "Paused with {} second{} left",
+            seconds,
+            if seconds == 1 { "" } else { "s" }
+        )
+    } else {
+        format!(
+            "Rebooting in {} second{}...",
+            seconds,
+            if seconds == 1 { "" } else { "s" }
+        )
+    };
+
+    f.render_widget(
+        Paragraph::new(message)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::White)),
+        inner[2],
+    );
+
+    let progress = if total.is_zero() {
+        1.0
+    } else {
+        1.0 - remaining.as_secs_f64() / total.as_secs_f64()
+    };
+    let bar = Rect {
+        height: inner[3].height.min(1),
+        ..inner[3].inner(Margin::new(4, 0))
+    };
+
+    f.render_widget(
+        Gauge::default()
+            .ratio(progress.clamp(0.0, 1.0))
+            .label("")
+            .gauge_style(Style::default().fg(Color::Cyan).bg(Color::DarkGray)),
+        bar,
+    );
+
+    f.render_widget(
+        Paragraph::new(if paused {
+            "Esc: cancel  |  p: resume"
+        } else {
+            "Esc: cancel  |  p: pause"
+        })
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray)),
+        inner[4],
+    );
+}
+
+fn draw_quit_confirm_popup(f: &mut Frame, area: Rect, yes_selected: bool) {
+    Modal::new(" Quit ")
+        .color(Color::Yellow)
+        .width(33)
+        .line("Quit without applying?")
+        .button("[ Yes ]", Color::Red)
+        .button("[ No ]", Color::Green)
+        .selected(if yes_selected { 0 } else { 1 })
+        .render(f, area);
+}
+
+fn draw_quit_boot_next_popup(f: &mut Frame, area: Rect, name: &str, selected: usize) {
+    Modal::new(" Quit ")
+        .color(Color::Yellow)
+        .width(80)
+        .line(Line::from(vec![
+            Span::raw("BootNext is armed to "),
+            Span::styled(name, Style::default().fg(Color::Cyan).bold()),
+        ]))
+        .line("The next reboot starts it, whenever that is.")
+        .button("[ Keep it ]", Color::Green)
+        .button("[ Clear it ]", Color::Red)
+        .button("[ Cancel ]", Color::Green)
+        .selected(selected)
+        .hint("Clear it runs efibootmgr -N, then quits")
+        .render(f, area);
+}
+
+fn draw_confirm_delete_popup(f: &mut Frame, area: Rect, app: &App, id: &str) {
+    let name = app.entry_name(id);
+    let phrase = app.delete_phrase(id);
+    let mut modal = Modal::new(" Delete Boot Entry ")
+        .color(Color::Red)
+        .width(70)
+        .line(Line::from(vec![
+            Span::raw("Delete "),
+            Span::styled(name, Style::default().fg(Color::Cyan).bold()),
+            Span::raw(format!(" (Boot{})?", id)),
+        ]))
+        .line(if app.all_entries().any(|e| e.id == id && e.unknown) {
+            "efibootmgr lists no such entry, so it is only dropped from the boot order; applying the order writes that."
+        } else {
+            "efibootmgr -B removes it from the firmware for good; undo can't bring it back."
+        });
+    if let Some(phrase) = &phrase {
+        modal = modal
+            .line("")
+            .line(Line::from(vec![
+                Span::raw("Type "),
+                Span::styled(phrase.as_str(), Style::default().fg(Color::Yellow).bold()),
+                Span::raw(" to enable Delete:"),
+            ]))
+            .input(&app.delete_typed, false);
+    }
+    modal = if app.delete_allowed(id) {
+        modal.button("[ Delete ]", Color::Red)
+    } else {
+        modal.disabled_button("[ Delete ]")
+    };
+    modal
+        .button("[ Cancel ]", Color::Green)
+        .selected(app.delete_choice)
+        .hint(if phrase.is_some() {
+            "Tab = Switch  |  Esc = Cancel"
+        } else {
+            "y = Delete  |  n/Esc = Cancel"
+        })
+        .render(f, area);
+}
+
+fn draw_instance_locked_popup(f: &mut Frame, area: Rect, pid: Option<u32>, read_only: bool) {
+    let popup_width = area.width * 3 / 4;
+    let popup_height = 7;
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Already Running ")
+            .style(Style::default().fg(Color::Yellow)),
+        popup,
+    );
+
+    let inner = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(2)])
+        .split(Rect {
+            x: popup.x + 1,
+            y: popup.y + 1,
+            width: popup.width - 2,
+            height: popup.height - 2,
+        });
+
+    let message = match pid {
+        Some(pid) => format!("Another swiftboot instance (PID {}) is running.", pid),
+        None => "Another swiftboot instance is running.".to_string(),
+    };
+    f.render_widget(
+        Paragraph::new(truncate_to_width(&message, inner[0].width as usize))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::White)),
+        inner[0],
+    );
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(inner[1]);
+
+    let selected = Style::default().bg(Color::Green).fg(Color::Black).bold();
+    let normal = Style::default().fg(Color::White);
+
+    f.render_widget(
+        Paragraph::new("[ Open read-only ]")
+            .alignment(Alignment::Center)
+            .style(if read_only { selected } else { normal }),
+        buttons[0],
+    );
+    f.render_widget(
+        Paragraph::new("[ Exit ]")
+            .alignment(Alignment::Center)
+            .style(if read_only { normal } else { selected }),
+        buttons[1],
+    );
+}
+
+fn draw_help_screen(f: &mut Frame, area: Rect, keymap: &Keymap) {
+    let key_line = |action: KeyAction, description: &str| {
+        format!(
+            "  {:<16} {}",
+            keymap.keys_for(action).join(" / "),
+            description
+        )
+    };
+    let describe = |action: KeyAction| key_line(action, action.description());
+
+    let help_text = vec![
+        String::new(),
+        "Navigation:".to_string(),
+        describe(KeyAction::Up),
+        describe(KeyAction::Down),
+        describe(KeyAction::NextPanel),
+        describe(KeyAction::PrevPanel),
+        String::new(),
+        "Boot Priority Panel:".to_string(),
+        describe(KeyAction::MoveUp),
+        describe(KeyAction::MoveDown),
+        describe(KeyAction::SwapDefault),
+        describe(KeyAction::PinRest),
+        describe(KeyAction::EditOrder),
+        describe(KeyAction::Undo),
+        describe(KeyAction::Redo),
+        describe(KeyAction::ResetOrder),
+        key_line(KeyAction::Confirm, "Apply new boot order (requires reboot)"),
+        describe(KeyAction::ApplyAndBootOnce),
+        String::new(),
+        "Boot To Panel:".to_string(),
+        key_line(KeyAction::Confirm, "Boot directly to selected OS"),
+        describe(KeyAction::BootPrevious),
+        String::new(),
+        "Driver / SysPrep Panels (advanced mode):".to_string(),
+        key_line(KeyAction::MoveUp, "Move entry up"),
+        key_line(KeyAction::MoveDown, "Move entry down"),
+        key_line(KeyAction::Confirm, "Apply new order"),
+        String::new(),
+        "Password Dialog:".to_string(),
+        "  Tab              Toggle password visibility".to_string(),
+        "  Enter            Confirm".to_string(),
+        "  Esc              Cancel".to_string(),
+        String::new(),
+        "While Processing:".to_string(),
+        "  Esc              Stop the running command".to_string(),
+        String::new(),
+        "Markers:".to_string(),
+        "  ●                Running now (BootCurrent)".to_string(),
+        "  →                Default: first in the boot order".to_string(),
+        "  »                Starts on the next boot only (BootNext)".to_string(),
+        String::new(),
+        "General:".to_string(),
+        describe(KeyAction::Refresh),
+        describe(KeyAction::DismissBanner),
+        describe(KeyAction::Warnings),
+        describe(KeyAction::ToggleIds),
+        describe(KeyAction::ToggleGroups),
+        describe(KeyAction::ToggleFirmwareEntries),
+        describe(KeyAction::Details),
+        describe(KeyAction::DeleteEntry),
+        describe(KeyAction::Profiles),
+        describe(KeyAction::Help),
+        describe(KeyAction::Quit),
+        "  Esc              In a popup: go back one step".to_string(),
+        String::new(),
+        "Press any key to close this help screen".to_string(),
+    ];
+
+    let popup_width = area.width * 3 / 4;
+    let popup_height = (help_text.len() as u16 + 2).min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Help ")
+            .style(Style::default().fg(Color::Cyan)),
+        popup,
+    );
+
+    let inner = Rect {
+        x: popup.x + 2,
+        y: popup.y + 1,
+        width: popup.width - 4,
+        height: popup.height - 2,
+    };
+
+    f.render_widget(
+        Paragraph::new(help_text.join("\n"))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Left),
+        inner,
+    );
+}
+
+fn draw_profile_picker(f: &mut Frame, area: Rect, app: &App) {
+    let names = app.profile_names();
+    let popup_width = area.width * 3 / 4;
+    let popup_height = ((names.len().max(app.entries.len()) + 4) as u16).min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Profiles (Enter: apply, Esc: cancel) "),
+        popup,
+    );
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(popup.inner(Margin::new(1, 1)));
+
+    let items: Vec<ListItem> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let style = if i == app.profile_index {
+                Style::default().bg(Color::Cyan).fg(Color::Black).bold()
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(truncate_to_width(name, columns[0].width as usize)).style(style)
+        })
+        .collect();
+    f.render_widget(List::new(items), columns[0]);
+
+    // Preview of the resulting boot order.
+    let preview = match names.get(app.profile_index).map(|n| app.profile_order(n)) {
+        Some(Ok(order)) => order
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let e = app.all_entries().find(|e| &e.id == id)?;
+                Some(Line::raw(format!("{}. {}", i + 1, app.display_name(e))))
+            })
+            .collect(),
+        Some(Err(e)) => vec![Line::styled(e, Style::default().fg(Color::Red))],
+        None => Vec::new(),
+    };
+    f.render_widget(
+        Paragraph::new(preview)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::LEFT))
+            .style(Style::default().fg(Color::White)),
+        columns[1],
+    );
+}
+
+fn draw_welcome_popup(f: &mut Frame, area: Rect, keymap: &Keymap) {
+    let k = |action| keymap.label(action);
+    let text = vec![
+        Line::styled(
+            "Welcome to SwiftBoot!",
+            Style::default().fg(Color::Cyan).bold(),
+        ),
+        Line::raw(""),
+        Line::raw(format!(
+            "• Change the default: in Boot Priority, move entries with {}/{} and press {}",
+            k(KeyAction::MoveUp),
+            k(KeyAction::MoveDown),
+            k(KeyAction::Confirm)
+        )),
+        Line::raw(format!(
+            "• Boot something once: in Boot To, pick an entry and press {}",
+            k(KeyAction::Confirm)
+        )),
+        Line::raw(format!(
+            "• Switch between the panels with {}",
+            k(KeyAction::NextPanel)
+        )),
+        Line::raw(""),
+        Line::raw(format!(
+            "Press {} at any time for the full list of keys.",
+            k(KeyAction::Help)
+        )),
+        Line::styled("Press any key to start", Style::default().fg(Color::Gray)),
+    ];
+
+    let popup_width = area.width * 3 / 4;
+    let popup_height = (text.len() as u16 + 4).min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Welcome ")
+            .style(Style::default().fg(Color::Cyan)),
+        popup,
+    );
+    f.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(Color::White)),
+        popup.inner(Margin::new(2, 1)),
+    );
+}
+
+fn draw_warnings_popup(f: &mut Frame, area: Rect, app: &App) {
+    let warnings = app.command_warnings.as_deref().unwrap_or_default();
+    warnings
+        .lines()
+        .fold(
+            Modal::new(" efibootmgr Warnings ")
+                .color(Color::Yellow)
+                .width(75)
+                .line("The change was made; efibootmgr printed this along the way:")
+                .line(""),
+            |modal, line| modal.line(Line::from(line.to_string()).yellow()),
+        )
+        .hint("Press any key to close")
+        .render(f, area);
+}
+
+fn draw_details_popup(f: &mut Frame, area: Rect, app: &App, entry: &BootEntry) {
+    let fallback = entry.uses_fallback_loader() && !entry.is_removable();
+    let popup_width = area.width * 3 / 4;
+    let popup_height = if fallback { 19 } else { 13 }.min(area.height);
+    let popup = popup_area(f, area, popup_width, popup_height);
+
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Entry Details "),
+        popup,
+    );
+
+    let label = |text: &'static str| Span::styled(text, Style::default().fg(Color::Gray));
+    let status = if entry.active { "active" } else { "inactive" };
+
+    // Falls back to the raw device path when the partition can't be looked up.
+    let partition = match app.partition_status(entry) {
+        Some(PartitionStatus::Found(info)) => Span::raw(info.describe()),
+        Some(PartitionStatus::Missing) => Span::styled(
+            "⚠ not found on any attached disk (dangling entry)",
+            Style::default().fg(Color::Yellow).bold(),
+        ),
+        Some(PartitionStatus::Unknown) | None => Span::raw("unknown"),
+    };
+
+    let path = if entry.device_path.is_empty() {
+        "(none)"
+    } else {
+        entry.device_path.as_str()
+    };
+
+    let mut text = vec![
+        Line::from(vec![
+            label("Name:      "),
+            Span::raw(app.display_name(entry)),
+        ]),
+        Line::from(vec![
+            label("ID:        "),
+            Span::raw(format!("{} ({})", display_id(&entry.id), status)),
+        ]),
+        Line::from(vec![label("Partition: "), partition]),
+        Line::from(vec![
+            label("Device:    "),
+            Span::raw(entry.device_kind().describe()),
+        ]),
+        Line::from(vec![label("Path:      "), Span::raw(path)]),
+        Line::from(vec![
+            label("Firmware:  "),
+            Span::raw(
+                app.firmware
+                    .describe()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            ),
+        ]),
+    ];
+    if fallback {
+        text.push(Line::raw(""));
+        text.push(Line::styled(
+            "Starts the removable-media fallback loader, which any OS install or ESP \
+             cleanup may replace. On a fixed disk this usually means the OS's own entry \
+             was lost.",
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    let inner = popup.inner(Margin::new(2, 1));
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    f.render_widget(
+        Paragraph::new(text)
+            .wrap(Wrap { trim: false })
+            .style(Style::default().fg(Color::White)),
+        layout[0],
+    );
+    f.render_widget(
+        Paragraph::new("Press any key to close")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Gray)),
+        layout[1],
+    );
+}
+
+fn draw_error_message_popup(f: &mut Frame, area: Rect, error_msg: &str, retry: bool) {
+    let mut modal = Modal::new(" Error ")
+        .color(Color::Red)
+        .width(66)
+        .line(Line::from("Command failed:").red().bold());
+    for line in error_msg.lines() {
+        modal = modal.line(line);
+    }
+    error_buttons(modal, retry).render(f, area);
+}
+
+// Retrying makes sense once space has been freed, from another terminal.
+fn draw_nvram_full_popup(f: &mut Frame, area: Rect, error_msg: &str, retry: bool) {
+    let mut modal = Modal::new(" NVRAM full ").color(Color::Red).width(66);
+    for line in error_msg.lines() {
+        modal = modal.line(line);
+    }
+    modal = modal
+        .line("")
+        .line(Line::from(efi::NVRAM_FULL_ADVICE).yellow());
+    error_buttons(modal, retry).render(f, area);
+}
+
+fn error_buttons(modal: Modal, retry: bool) -> Modal {
+    modal
+        .button("[ Retry ]", Color::Green)
+        .button("[ Back ]", Color::Green)
+        .selected(if retry { 0 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::testing;
+    use crossterm::event::KeyCode;
+    use ratatui::{Terminal, backend::TestBackend, buffer::Buffer};
+
+    #[test]
+    fn short_names_are_left_alone() {
+        assert_eq!(truncate_to_width("Fedora", 10), "Fedora");
+        assert_eq!(truncate_to_width("Fedora", 6), "Fedora");
+    }
+
+    #[test]
+    fn long_names_end_in_an_ellipsis() {
+        let name = "UEFI: SanDisk Ultra USB 3.0, Partition 1 (8086MB)";
+        let short = truncate_to_width(name, 12);
+        assert_eq!(short, "UEFI: SanDi…");
+        assert_eq!(short.width(), 12);
+        assert_eq!(truncate_to_width(name, 1), "…");
+        assert_eq!(truncate_to_width(name, 0), "");
+    }
+
+    #[test]
+    fn wide_characters_are_never_split() {
+        // Each of these takes two columns.
+        let name = "日本語のブート";
+        assert_eq!(truncate_to_width(name, 6), "日本…");
+        assert_eq!(truncate_to_width(name, 5), "日本…");
+        assert!(truncate_to_width(name, 5).width() <= 5);
+        assert_eq!(truncate_to_width(name, 2), "…");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_letter() {
+        // "e" plus a combining acute accent is one column.
+        let name = "Re\u{301}sume\u{301} Linux";
+        assert_eq!(name.width(), 12);
+        assert_eq!(truncate_to_width(name, 12), name);
+        assert_eq!(truncate_to_width(name, 3), "Re\u{301}…");
+        assert_eq!(truncate_to_width(name, 3).width(), 3);
+    }
+
+    #[test]
+    fn names_that_truncate_alike_get_their_ids() {
+        let named = [
+            ("UEFI: SanDisk Ultra USB 3.0, Partition 1", "0007"),
+            ("UEFI: SanDisk Ultra USB 3.0, Partition 2", "0008"),
+            ("Fedora", "0001"),
+        ];
+        let names = disambiguated_names(&named, 20);
+        assert_eq!(names[0], "UEFI: SanDis… [0007]");
+        assert_eq!(names[1], "UEFI: SanDis… [0008]");
+        assert_eq!(names[2], "Fedora");
+        assert!(names.iter().all(|n| n.width() <= 20));
+    }
+
+    #[test]
+    fn identical_names_that_fit_stay_as_they_are() {
+        let named = [("Linux", "0001"), ("Linux", "0002")];
+        assert_eq!(disambiguated_names(&named, 10), ["Linux", "Linux"]);
+    }
+
+    #[test]
+    fn the_id_suffix_never_overflows_a_narrow_column() {
+        let named = [
+            ("Windows Boot Manager", "0002"),
+            ("Windows Boot Mgr", "0003"),
+        ];
+        for width in 0..10 {
+            for name in disambiguated_names(&named, width) {
+                assert!(name.width() <= width, "{:?} at width {}", name, width);
+            }
+        }
+    }
+
+    fn countdown(width: u16, remaining: u64) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, 14)).unwrap();
+        let target = ActionSummary {
+            target: "Windows Boot Manager".to_string(),
+            boot_once: true,
+            changed: true,
+        };
+        terminal
+            .draw(|f| {
+                draw_countdown_screen(
+                    f,
+                    f.area(),
+                    &target,
+                    Duration::from_secs(remaining),
+                    Duration::from_secs(10),
+                    false,
+                )
+            })
+            .unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn filled_cells(buffer: &Buffer) -> usize {
+        buffer
+            .content()
+            .iter()
+            // The (empty) label cell swaps the colours.
+            .filter(|c| (c.symbol() == "█" && c.fg == Color::Cyan) || c.bg == Color::Cyan)
+            .count()
+    }
+
+    #[test]
+    fn countdown_fits_a_tiny_terminal() {
+        // Popups of 4 and 6 columns leave no room for the bar at all.
+        assert_eq!(filled_cells(&countdown(8, 5)), 0);
+        assert_eq!(filled_cells(&countdown(12, 0)), 0);
+    }
+
+    #[test]
+    fn countdown_bar_follows_the_remaining_time() {
+        // 80 columns: a 40-column popup, 38 inside the border, 30 for the bar.
+        assert_eq!(filled_cells(&countdown(80, 10)), 0);
+        assert_eq!(filled_cells(&countdown(80, 5)), 15);
+        assert_eq!(filled_cells(&countdown(80, 0)), 30);
+    }
+
+    fn render(app: &mut App) -> Buffer {
+        render_sized(app, 100, 30)
+    }
+
+    fn render_sized(app: &mut App, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn rows(buffer: &Buffer) -> Vec<String> {
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|c| c.symbol()).collect())
+            .collect()
+    }
+
+    fn screen(app: &mut App) -> String {
+        rows(&render(app)).join("\n")
+    }
+
+    // The text of the selected row: the one drawn on cyan.
+    fn highlighted(app: &mut App) -> String {
+        let buffer = render(app);
+        let width = buffer.area.width as usize;
+        let row = buffer
+            .content()
+            .iter()
+            .position(|c| c.bg == Color::Cyan)
+            .expect("a highlighted row")
+            / width;
+        rows(&buffer)[row].trim_matches([' ', '│']).to_string()
+    }
+
+    // The row the title of the panel named `title` is on.
+    fn title_row(app: &mut App, title: &str) -> usize {
+        rows(&render(app))
+            .iter()
+            .position(|r| r.contains(title))
+            .expect("the panel title")
+    }
+
+    #[test]
+    fn tab_moves_the_highlight_between_panels() {
+        let mut app = testing::app();
+        assert_eq!(highlighted(&mut app), "●→  1. Fedora");
+        let boot_to = title_row(&mut app, "┌ Boot To ");
+        let buffer = render(&mut app);
+        assert_eq!(buffer[(17, boot_to as u16)].fg, Color::DarkGray);
+
+        app.handle_key(testing::key(KeyCode::Tab));
+        // Boot To starts on the first entry that isn't running.
+        assert_eq!(highlighted(&mut app), "2. Windows Boot Manager");
+        let buffer = render(&mut app);
+        assert_eq!(buffer[(17, boot_to as u16)].fg, Color::White);
+        assert_eq!(
+            buffer[(17, 1 + title_row(&mut app, "Boot Priority") as u16)].fg,
+            Color::DarkGray
+        );
+
+        app.handle_key(testing::key(KeyCode::Tab));
+        assert_eq!(highlighted(&mut app), "●→  1. Fedora");
+    }
+
+    #[test]
+    fn reordering_shows_the_shifts() {
+        let mut app = testing::app();
+        testing::press(&mut app, "d");
+        let text = screen(&mut app);
+        assert!(text.contains("SwiftBoot [modified]"));
+        assert!(text.contains("1. Windows Boot Manager ↑1"));
+        assert!(text.contains("2. Fedora ↓1"));
+        assert!(text.contains("— default: Windows Boot Mana… *"));
+        assert_eq!(highlighted(&mut app), "●→  2. Fedora ↓1");
+
+        testing::press(&mut app, "u");
+        let text = screen(&mut app);
+        assert!(!text.contains("[modified]"));
+        assert!(text.contains("1. Fedora  "));
+    }
+
+    #[test]
+    fn quitting_with_unsaved_changes_asks_first() {
+        let mut app = testing::app();
+        testing::press(&mut app, "q");
+        assert!(app.should_quit);
+
+        let mut app = testing::app();
+        testing::press(&mut app, "dq");
+        assert!(!app.should_quit);
+        let text = screen(&mut app);
+        assert!(text.contains("Quit without applying?"));
+        assert!(text.contains("[ Yes ]"));
+        // No is the default.
+        app.handle_key(testing::key(KeyCode::Enter));
+        assert!(!app.should_quit);
+        assert!(!screen(&mut app).contains("Quit without applying?"));
+
+        testing::press(&mut app, "q");
+        app.handle_key(testing::key(KeyCode::Left));
+        app.handle_key(testing::key(KeyCode::Enter));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn applying_asks_for_the_password_without_showing_it() {
+        let mut app = testing::app();
+        app.config.warn_windows_first = false;
+        testing::press(&mut app, "d");
+        app.handle_key(testing::key(KeyCode::Enter));
+        let text = screen(&mut app);
+        assert!(text.contains("Enter sudo password"), "{}", text);
+
+        testing::press(&mut app, "hunter2");
+        let text = screen(&mut app);
+        assert!(text.contains("│*******   "), "{}", text);
+        assert!(!text.contains("hunter2"));
+
+        app.handle_key(testing::key(KeyCode::Esc));
+        assert!(!screen(&mut app).contains("Enter sudo password"));
+        assert_eq!(testing::order(&app), ["0002", "0001", "0003"]);
+    }
+
+    // Columns from the first `from` to the first `to` on the row that
+    // contains `from`.
+    fn span_of(rows: &[String], from: &str, to: char) -> (usize, usize) {
+        let row = rows.iter().find(|r| r.contains(from)).expect(from);
+        let chars: Vec<char> = row.chars().collect();
+        let start = row[..row.find(from).unwrap()].chars().count();
+        let end = start + chars[start..].iter().position(|&c| c == to).unwrap();
+        (start, end)
+    }
+
+    #[test]
+    fn popups_stay_inside_the_app_on_a_wide_terminal() {
+        let mut app = testing::app();
+        testing::press(&mut app, "dq");
+        let rows = rows(&render_sized(&mut app, 240, 40));
+        let (frame_left, frame_right) = span_of(&rows, "┌ Boot To", '┐');
+        let (popup_left, popup_right) = span_of(&rows, "┌ Quit", '┐');
+        assert!(frame_left < popup_left && popup_right < frame_right);
+        // Centred on the app, give or take a column of rounding.
+        let centre = |l: usize, r: usize| (l + r) as isize;
+        assert!((centre(frame_left, frame_right) - centre(popup_left, popup_right)).abs() <= 2);
+
+        // The app stays drawn under the popup.
+        let text = rows.join("\n");
+        assert!(text.contains("1. Windows Boot Manager"));
+        assert!(text.contains("Quit without applying?"));
+    }
+
+    fn popup(width: u16, height: u16, draw: impl FnOnce(&mut Frame, Rect)) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, f.area())).unwrap();
+        rows(terminal.backend().buffer())
+    }
+
+    #[test]
+    fn reboot_popup_snapshot() {
+        let app = testing::app();
+        let target = ActionSummary {
+            target: "Windows Boot Manager".to_string(),
+            boot_once: true,
+            changed: true,
+        };
+        assert_eq!(
+            popup(60, 14, |f, area| draw_reboot_popup(f, area, &app, &target)),
+            [
+                "                                                            ",
+                "               ┌ Apply Complete ────────────┐               ",
+                "               │  Reboot into Windows Boot  │               ",
+                "               │        Manager now?        │               ",
+                "               │boot once; the default order│               ",
+                "               │        is unchanged        │               ",
+                "               │                            │               ",
+                "               │ Firmware menu timeout: not │               ",
+                "               │ set (the firmware's default│               ",
+                "               │          applies)          │               ",
+                "               │                            │               ",
+                "               │    [ Yes ]       [ No ]    │               ",
+                "               └────────────────────────────┘               ",
+                "                                                            ",
+            ]
+        );
+    }
+
+    #[test]
+    fn quit_confirm_popup_snapshot() {
+        let mut terminal = Terminal::new(TestBackend::new(40, 7)).unwrap();
+        terminal
+            .draw(|f| draw_quit_confirm_popup(f, f.area(), false))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(
+            rows(buffer),
+            [
+                "                                        ",
+                "     ┌ Quit ──────────────────────┐     ",
+                "     │   Quit without applying?   │     ",
+                "     │                            │     ",
+                "     │    [ Yes ]       [ No ]    │     ",
+                "     └────────────────────────────┘     ",
+                "                                        ",
+            ]
+        );
+        // "No" is highlighted in its own color, "Yes" is not.
+        assert_eq!(buffer[(25, 4)].bg, Color::Green);
+        assert_eq!(buffer[(11, 4)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn password_error_popup_snapshot() {
+        // The hint is drawn inside the popup, not below it.
+        assert_eq!(
+            popup(40, 7, draw_password_error_popup),
+            [
+                "                                        ",
+                "     ┌ Authentication Failed ─────┐     ",
+                "     │     Incorrect password!    │     ",
+                "     │      Please try again.     │     ",
+                "     │  Press any key to continue │     ",
+                "     └────────────────────────────┘     ",
+                "                                        ",
+            ]
+        );
+    }
+
+    #[test]
+    fn error_message_popup_snapshot() {
+        let message = "efibootmgr: Could not set BootOrder";
+        assert_eq!(
+            popup(50, 9, |f, area| draw_error_message_popup(
+                f, area, message, true
+            )),
+            [
+                "                                                  ",
+                "         ┌ Error ────────────────────────┐        ",
+                "         │        Command failed:        │        ",
+                "         │   efibootmgr: Could not set   │        ",
+                "         │           BootOrder           │        ",
+                "         │                               │        ",
+                "         │    [ Retry ]      [ Back ]    │        ",
+                "         └───────────────────────────────┘        ",
+                "                                                  ",
+            ]
+        );
+    }
+
+    fn gutter_text(spans: &[Span]) -> String {
+        spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn the_gutter_has_a_cell_per_marker() {
+        let all = Markers {
+            current: true,
+            default: true,
+            next: true,
+        };
+        let spans = marker_gutter(all, false);
+        assert_eq!(gutter_text(&spans), " ●→»");
+        let colors: Vec<_> = spans[1..].iter().map(|s| s.style.fg).collect();
+        assert_eq!(
+            colors,
+            [Some(Color::Green), Some(Color::Cyan), Some(Color::Yellow)]
+        );
+        // The highlight's colors win on the selected row.
+        assert!(
+            marker_gutter(all, true)[1..]
+                .iter()
+                .all(|s| s.style.fg.is_none())
+        );
+
+        let next_only = Markers {
+            next: true,
+            ..Markers::default()
+        };
+        assert_eq!(gutter_text(&marker_gutter(next_only, false)), "   »");
+        assert_eq!(
+            gutter_text(&marker_gutter(Markers::default(), false)),
+            "    "
+        );
+    }
+
+    #[test]
+    fn an_entry_can_show_all_three_markers() {
+        let mut app = testing::app();
+        app.boot_next = Some("0001".to_string());
+        let screen = screen(&mut app);
+        let fedora = screen
+            .lines()
+            .find(|row| row.contains("Fedora") && row.contains('»'))
+            .unwrap();
+        assert!(fedora.contains(" ●→» "));
+        // Windows has none, so its gutter is blank.
+        let windows = screen
+            .lines()
+            .find(|row| row.contains("Windows Boot Manager"))
+            .unwrap();
+        let name = windows.find("Windows").unwrap();
+        assert!(!windows[..name].contains(['●', '→', '»']));
+    }
+
+    // The Boot To panel's entry rows, box and all.
+    fn boot_to_rows(app: &mut App) -> Vec<String> {
+        let boot_to = title_row(app, "┌ Boot To ");
+        rows(&render(app))[boot_to..boot_to + 5]
+            .iter()
+            .map(|r| r.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn boot_to_numbers_follow_boot_order() {
+        let mut app = testing::app_with(
+            crate::config::Config::default(),
+            testing::entries(),
+            &["0002", "0001"],
+        );
+        // USB isn't in BootOrder, so it gets a dash in the same column.
+        assert_eq!(
+            boot_to_rows(&mut app),
+            [
+                "┌ Boot To ──────────────────────────────────────────────────────┐",
+                "│  →  1. Windows Boot Manager                                   │",
+                "│ ●   2. Fedora                                                 │",
+                "│     –  UEFI: USB (not in BootOrder)                           │",
+                "│                                                               │",
+            ]
+        );
+    }
+
+    #[test]
+    fn boot_to_numbers_can_be_hidden() {
+        let config = crate::config::Config {
+            show_numbers: false,
+            ..Default::default()
+        };
+        let mut app = testing::app_with(config, testing::entries(), &["0001", "0002"]);
+        assert_eq!(
+            boot_to_rows(&mut app)[1..4],
+            [
+                "│ ●→  Fedora                                                    │",
+                "│     Windows Boot Manager                                      │",
+                "│     UEFI: USB (not in BootOrder)                              │",
+            ]
+        );
+        assert!(screen(&mut app).contains("│ ●→  Fedora  "));
+    }
+
+    #[test]
+    fn a_short_terminal_gets_the_compact_layout() {
+        let mut app = testing::app();
+        let compact = rows(&render_sized(&mut app, 60, 12));
+        assert_eq!(
+            compact[..5],
+            [
+                "┌ Boot Priority (default order) — default: Fedora ─────────┐",
+                "│ ●→  1. Fedora                                            │",
+                "│     2. Windows Boot Manager                              │",
+                "│     3. UEFI: USB                                         │",
+                "│                                                          │",
+            ]
+        );
+        assert_eq!(
+            compact[11],
+            "└──── Tab: Panel  Enter: Apply/Boot  ?: Help  q: Quit ─────┘"
+        );
+
+        // Tab swaps the panel that fills the screen.
+        app.handle_key(testing::key(KeyCode::Tab));
+        let compact = rows(&render_sized(&mut app, 60, 12));
+        assert!(compact[0].starts_with("┌ Boot To ─"));
+        assert!(!compact.join("\n").contains("Boot Priority"));
+    }
+
+    #[test]
+    fn the_layout_follows_the_terminal_height() {
+        let mut app = testing::app();
+        for height in [8, 12, 15] {
+            let text = rows(&render_sized(&mut app, 60, height)).join("\n");
+            assert!(!text.contains("SwiftBoot"), "{} rows", height);
+            assert!(!text.contains("Boot To"), "{} rows", height);
+        }
+        for height in [16, 20, 40] {
+            let text = rows(&render_sized(&mut app, 60, height)).join("\n");
+            assert!(text.contains("SwiftBoot"), "{} rows", height);
+            assert!(text.contains("┌ Boot To "), "{} rows", height);
+            assert!(text.contains("Tab: Switch panel"), "{} rows", height);
+        }
+    }
+
+    #[test]
+    fn the_compact_border_shows_changes_and_toasts() {
+        let mut app = testing::app();
+        testing::press(&mut app, "d");
+        let compact = rows(&render_sized(&mut app, 60, 12));
+        assert!(compact[0].contains("[modified]"));
+
+        app.show_toast("Order reverted");
+        let compact = rows(&render_sized(&mut app, 60, 12));
+        assert!(compact[11].contains(" Order reverted "));
+        assert!(!compact[11].contains("Help"));
+    }
+
+    #[test]
+    fn fixed_disks_booting_the_fallback_get_a_note() {
+        let hd = "HD(1,GPT,x,0x800,0x100000)/File(\\EFI\\BOOT\\BOOTX64.EFI)";
+        let mut entries = testing::entries();
+        entries[0].device_path = hd.to_string();
+        // Expected on a stick: it has nothing else to boot.
+        entries[2].device_path = format!("USB(0,0)/{}", hd);
+        let mut app = testing::app_with(
+            crate::config::Config::default(),
+            entries,
+            &["0001", "0002", "0003"],
+        );
+        let text = screen(&mut app);
+        assert!(text.contains("1. Fedora (fallback loader)"));
+        assert!(!text.contains("USB (fallback loader)"));
+    }
+
+    #[test]
+    fn an_empty_password_is_pointed_out() {
+        let mut app = testing::app();
+        app.config.warn_windows_first = false;
+        testing::press(&mut app, "d");
+        app.handle_key(testing::key(KeyCode::Enter));
+        app.handle_key(testing::key(KeyCode::Enter));
+        let text = screen(&mut app);
+        assert!(text.contains("Password is empty"), "{}", text);
+        assert!(!text.contains("Enter sudo password"));
+
+        testing::press(&mut app, "a");
+        assert!(screen(&mut app).contains("Enter sudo password"));
+    }
+
+    #[test]
+    fn a_carried_entry_is_shown_apart() {
+        let mut app = testing::app();
+        testing::press(&mut app, "m");
+        app.handle_key(testing::key(KeyCode::Down));
+        let buffer = render(&mut app);
+        let text = rows(&buffer);
+        let row = text
+            .iter()
+            .position(|r| r.contains("2. Fedora"))
+            .expect("the carried entry");
+        let column = text[row].find("Fedora").unwrap();
+        let cell = &buffer[(text[row][..column].chars().count() as u16, row as u16)];
+        assert_eq!(cell.bg, Color::Yellow);
+        assert!(
+            text.iter()
+                .any(|r| r.contains("Moving Fedora: ↑/↓ place, Home/End top/bottom"))
+        );
+    }
+
+    #[test]
+    fn the_title_names_the_default_to_be() {
+        let mut app = testing::app();
+        let same = prospective_default(&app, 40).unwrap();
+        assert_eq!(same.content, "— default: Fedora ");
+        assert_eq!(same.style.fg, Some(Color::Gray));
+
+        testing::press(&mut app, "d");
+        let changed = prospective_default(&app, 40).unwrap();
+        assert_eq!(changed.content, "— default: Windows Boot Manager * ");
+        assert_eq!(changed.style.fg, Some(Color::Yellow));
+
+        // Back where it was, it's the firmware's default again.
+        testing::press(&mut app, "u");
+        assert_eq!(
+            prospective_default(&app, 40).unwrap().content,
+            "— default: Fedora "
+        );
+    }
+
+    #[test]
+    fn the_default_in_the_title_uses_aliases_and_fits() {
+        let mut config = crate::config::Config::default();
+        config
+            .aliases
+            .insert("0002".to_string(), "Windows".to_string());
+        let mut app = testing::app_with(config, testing::entries(), &["0001", "0002", "0003"]);
+        testing::press(&mut app, "d");
+        assert_eq!(
+            prospective_default(&app, 40).unwrap().content,
+            "— default: Windows * "
+        );
+        assert_eq!(
+            prospective_default(&app, 19).unwrap().content,
+            "— default: Wind… * "
+        );
+        // Too narrow for even a few letters of the name.
+        assert!(prospective_default(&app, 17).is_none());
+    }
+
+    #[test]
+    fn the_default_in_the_title_follows_the_written_order() {
+        // With the rest pinned, only the moved entries trade places.
+        let mut app = testing::app();
+        app.pin_rest = true;
+        testing::press(&mut app, "jd");
+        assert_eq!(testing::order(&app), ["0001", "0003", "0002"]);
+        assert_eq!(
+            prospective_default(&app, 40).unwrap().content,
+            "— default: Fedora "
+        );
+        assert!(screen(&mut app).contains("(default order, rest pinned) — default: Fedora ─"));
+    }
+
+    #[test]
+    fn the_delete_button_is_dimmed_until_the_name_is_typed() {
+        let mut app = testing::app();
+        app.config.confirm_destructive = crate::config::ConfirmDestructive::TypeName;
+        app.handle_key(testing::key(KeyCode::Delete));
+        let delete_fg = |app: &mut App| {
+            let buffer = render(app);
+            let text = rows(&buffer);
+            let row = text.iter().position(|r| r.contains("[ Delete ]")).unwrap();
+            let column = text[row][..text[row].find("[ Delete ]").unwrap()]
+                .chars()
+                .count();
+            buffer[(column as u16 + 2, row as u16)].fg
+        };
+        assert!(screen(&mut app).contains("Type Fedo to enable Delete:"));
+        assert_eq!(delete_fg(&mut app), Color::DarkGray);
+
+        testing::press(&mut app, "fedo");
+        assert_ne!(delete_fg(&mut app), Color::DarkGray);
+    }
+}
@@ -0,0 +1,96 @@
+//! list → set-order → verify → boot-next → clear through the [`Efivarfs`]
+//! boot manager, against a temporary directory laid out like efivarfs
+//! instead of the firmware's variables. Run with
+//! `cargo test --features efivarfs-it`.
+#![cfg(feature = "efivarfs-it")]
+
+use std::{fs, path::Path};
+use swiftboot::{BootManager, Efivarfs, EntryId, efivars};
+
+// EFI_GLOBAL_VARIABLE.
+const GLOBAL: &str = "8be4df61-93ca-11d2-aa0d-00e098032b8c";
+// NON_VOLATILE | BOOTSERVICE_ACCESS | RUNTIME_ACCESS.
+const ATTRIBUTES: [u8; 4] = [0x07, 0, 0, 0];
+
+// An efivarfs file: the attributes, then the data.
+fn put(dir: &Path, name: &str, data: &[u8]) {
+    let mut contents = ATTRIBUTES.to_vec();
+    contents.extend_from_slice(data);
+    fs::write(dir.join(format!("{}-{}", name, GLOBAL)), contents).unwrap();
+}
+
+fn ids(ids: &[u16]) -> Vec<u8> {
+    ids.iter().flat_map(|id| id.to_le_bytes()).collect()
+}
+
+// An active EFI_LOAD_OPTION with `name` and a bare `\EFI\<dir>\boot.efi`
+// file path.
+fn load_option(name: &str, dir: &str) -> Vec<u8> {
+    let ucs2 = |text: &str| -> Vec<u8> {
+        text.encode_utf16()
+            .chain([0])
+            .flat_map(u16::to_le_bytes)
+            .collect()
+    };
+    let file = ucs2(&format!("\\EFI\\{}\\boot.efi", dir));
+    let mut path = vec![0x04, 0x04];
+    path.extend_from_slice(&(4 + file.len() as u16).to_le_bytes());
+    path.extend_from_slice(&file);
+    path.extend_from_slice(&[0x7f, 0xff, 0x04, 0x00]);
+
+    let mut data = 1u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&(path.len() as u16).to_le_bytes());
+    data.extend_from_slice(&ucs2(name));
+    data.extend_from_slice(&path);
+    data
+}
+
+fn id(text: &str) -> EntryId {
+    EntryId::parse(text).unwrap()
+}
+
+#[test]
+fn list_reorder_and_boot_next() {
+    let dir = std::env::temp_dir().join(format!("swiftboot-efivarfs-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    put(&dir, "Boot0001", &load_option("Fedora", "fedora"));
+    put(
+        &dir,
+        "Boot0002",
+        &load_option("Windows Boot Manager", "Microsoft"),
+    );
+    put(&dir, "BootOrder", &ids(&[1, 2]));
+    put(&dir, "BootCurrent", &ids(&[1]));
+    put(&dir, "Timeout", &ids(&[5]));
+    efivars::set_base(&dir);
+
+    let manager = Efivarfs;
+
+    // list
+    let entries = manager.list_entries().unwrap();
+    let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["Fedora", "Windows Boot Manager"]);
+    assert_eq!(entries[1].device_path, "File(\\EFI\\Microsoft\\boot.efi)");
+    let status = manager.status().unwrap();
+    assert_eq!(status.current.as_deref(), Some("0001"));
+    assert_eq!(status.order, ["0001", "0002"]);
+    assert_eq!(status.next, None);
+
+    // set-order, then verify it reads back
+    manager.set_order(&[id("0002"), id("0001")]).unwrap();
+    assert_eq!(manager.status().unwrap().order, ["0002", "0001"]);
+
+    // boot-next
+    manager.set_boot_next(&id("0001")).unwrap();
+    assert_eq!(manager.status().unwrap().next.as_deref(), Some("0001"));
+
+    // clear
+    manager.clear_boot_next().unwrap();
+    let status = manager.status().unwrap();
+    assert_eq!(status.next, None);
+    assert_eq!(status.order, ["0002", "0001"]);
+    assert_eq!(status.timeout, Some(5));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -22,5 +22,6 @@ fi
 
 echo -e "${YELLOW}Removing swiftboot from /usr/local/bin...${NC}"
 sudo rm -f /usr/local/bin/swiftboot
+sudo rm -f /usr/local/share/man/man1/swiftboot.1
 
 echo -e "${GREEN}✓ Uninstallation complete!${NC}"